@@ -3,20 +3,59 @@
 use crate::array::*;
 
 /// An activation function, which is applied to the output of a layer, and implements the differentiable
-/// activation operation.
-pub type Activation = Box<dyn Fn(Array) -> Array>;
+/// activation operation. The activations of this module also carry the type of their ONNX operator.
+pub struct Activation {
+    function: Box<dyn Fn(Array) -> Array>,
+    onnx_op: Option<&'static str>,
+}
+
+impl Activation {
+    /// Creates a custom activation from a differentiable operation, which cannot be exported to ONNX.
+    pub fn new(function: impl Fn(Array) -> Array + 'static) -> Activation {
+        Activation {
+            function: Box::new(function),
+            onnx_op: None,
+        }
+    }
+
+    /// Creates an activation, which is exported to ONNX as the given operator type.
+    fn with_onnx_op(
+        function: impl Fn(Array) -> Array + 'static,
+        onnx_op: &'static str,
+    ) -> Activation {
+        Activation {
+            function: Box::new(function),
+            onnx_op: Some(onnx_op),
+        }
+    }
+
+    /// Applies the activation to an array.
+    pub fn apply(&self, input: Array) -> Array {
+        (self.function)(input)
+    }
 
-/// Creates a ReLU activation function closure.
+    /// Retrieves the type of the ONNX operator of the activation, if it has one.
+    pub fn onnx_op(&self) -> Option<&'static str> {
+        self.onnx_op
+    }
+}
+
+/// Creates a ReLU activation function.
 pub fn relu() -> Activation {
-    Box::new(|x| x.relu())
+    Activation::with_onnx_op(|x| x.relu(), "Relu")
 }
 
-/// Creates a sigmoid activation function closure.
+/// Creates a sigmoid activation function.
 pub fn sigmoid() -> Activation {
-    Box::new(|x| x.sigmoid())
+    Activation::with_onnx_op(|x| x.sigmoid(), "Sigmoid")
+}
+
+/// Creates a hyperbolic tangent activation function.
+pub fn tanh() -> Activation {
+    Activation::with_onnx_op(|x| x.tanh(), "Tanh")
 }
 
-/// Creates a softmax activation function closure.
+/// Creates a softmax activation function.
 pub fn softmax() -> Activation {
-    Box::new(|x| x.softmax())
+    Activation::with_onnx_op(|x| x.softmax(), "Softmax")
 }
@@ -11,7 +11,13 @@ pub(crate) fn mul_values(a: &[Float], b: &[Float]) -> Vec<Float> {
 
 impl Array {
     #[inline]
-    fn element_wise_op<F: 'static>(&self, other: &Array, f: F, backward_op: BackwardOp) -> Array
+    fn element_wise_op<F: 'static>(
+        &self,
+        op_name: &'static str,
+        other: &Array,
+        f: F,
+        backward_op: BackwardOp,
+    ) -> Array
     where
         F: Fn(Float, Float) -> Float,
     {
@@ -33,6 +39,7 @@ impl Array {
 
         let dimensions = Rc::new(dimensions);
         Array::sliced_op(
+            op_name,
             vec![&self, other],
             &op,
             backward_op,
@@ -56,7 +63,7 @@ impl Array {
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("reciprocal", backward_op)
         }
     }
 
@@ -73,11 +80,36 @@ impl Array {
         if !self.is_tracked.get() {
             result
         } else {
-            let backward_op: BackwardOp = Rc::new(move |c, _, x| vec![Some(&(&c[0] * 2.0) * x)]);
+            let backward_op: BackwardOp =
+                Rc::new(move |c, _, x| vec![Some(&(&c[0].powf(exponent - 1.0) * exponent) * x)]);
+
+            result
+                .with_children(vec![self.clone()])
+                .with_backward_op("powf", backward_op)
+        }
+    }
+
+    /// Clamps each value of the array to be within `min`, and `max`. The gradient is zero for clamped values.
+    pub fn clamp(&self, min: Float, max: Float) -> Array {
+        let values: Vec<Float> = self.values.iter().map(|x| x.max(min).min(max)).collect();
+        let result = Array::from((self.dimensions.clone(), values));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                let values: Vec<Float> = c[0]
+                    .values
+                    .iter()
+                    .zip(x.values.iter())
+                    .map(|(&v, d)| if v >= min && v <= max { *d } else { 0.0 })
+                    .collect();
+                vec![Some(Array::from((c[0].dimensions.clone(), values)))]
+            });
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("clamp", backward_op)
         }
     }
 
@@ -93,7 +125,7 @@ impl Array {
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("ln", backward_op)
         }
     }
 
@@ -116,7 +148,7 @@ impl Array {
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("exp", backward_op)
         }
     }
 
@@ -152,6 +184,7 @@ impl Array {
                 });
 
                 vec![Some(Array::sliced_op(
+                    "sum_backward",
                     vec![&x],
                     &op,
                     None,
@@ -164,6 +197,7 @@ impl Array {
         };
 
         Array::sliced_op(
+            "sum",
             vec![&self],
             &op,
             backward_op,
@@ -174,6 +208,27 @@ impl Array {
         )
     }
 
+    /// Computes the mean along the last `dimension_count` dimensions, keeping the averaged dimensions with length 1.
+    pub fn mean(&self, dimension_count: usize) -> Array {
+        let leading_count = self.dimensions.len().saturating_sub(dimension_count);
+        let count: usize = self.dimensions[leading_count..].iter().product();
+        let dimensions: Vec<usize> = self.dimensions[0..leading_count]
+            .iter()
+            .copied()
+            .chain(vec![1; self.dimensions.len() - leading_count])
+            .collect();
+
+        &self.sum(dimension_count).reshape(dimensions) * (1.0 / count as Float)
+    }
+
+    /// Computes the biased variance along the last `dimension_count` dimensions, keeping the reduced dimensions with
+    /// length 1.
+    pub fn variance(&self, dimension_count: usize) -> Array {
+        (self - &self.mean(dimension_count))
+            .powf(2.0)
+            .mean(dimension_count)
+    }
+
     /// Sums all the values of the array.
     pub fn sum_all(&self) -> Float {
         self.values.iter().sum()
@@ -192,7 +247,7 @@ impl ops::Add<&Array> for &Array {
             ]
         });
 
-        self.element_wise_op(other, Float::add, backward_op)
+        self.element_wise_op("add", other, Float::add, backward_op)
     }
 }
 
@@ -218,7 +273,7 @@ impl ops::Neg for &Array {
             let backward_op: BackwardOp = Rc::new(|_, _, x| vec![Some(-x)]);
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("neg", backward_op)
         }
     }
 }
@@ -245,7 +300,7 @@ impl ops::Mul<Float> for &Array {
             let backward_op: BackwardOp = Rc::new(move |_, _, x| vec![Some(x * other)]);
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("scale", backward_op)
         }
     }
 }
@@ -262,7 +317,7 @@ impl ops::Mul<&Array> for &Array {
             ]
         });
 
-        self.element_wise_op(other, Float::mul, backward_op)
+        self.element_wise_op("mul", other, Float::mul, backward_op)
     }
 }
 
@@ -282,7 +337,7 @@ impl ops::Div<&Array> for &Array {
             ]
         });
 
-        self.element_wise_op(other, Float::div, backward_op)
+        self.element_wise_op("div", other, Float::div, backward_op)
     }
 }
 
@@ -424,6 +479,42 @@ mod tests {
         assert_eq!(a.gradient().to_owned().unwrap(), gradient_expect);
     }
 
+    #[test]
+    fn test_mean() {
+        let a = arr![
+            arr![arr![1.0, 2.0, 3.0], arr![4.0, 5.0, 6.0]],
+            arr![arr![9.0, 8.0, 7.0], arr![7.0, 6.0, 5.0]]
+        ]
+        .tracked();
+        assert_eq!(
+            a.mean(1),
+            arr![arr![arr![2.0], arr![5.0]], arr![arr![8.0], arr![6.0]]]
+        );
+
+        let result = a.mean(2);
+        assert_eq!(result, arr![arr![arr![3.5]], arr![arr![7.0]]]);
+
+        result.backward(None);
+        assert_relative_eq!(
+            a.gradient().to_owned().unwrap(),
+            Array::from((vec![2, 2, 3], vec![1.0 / 6.0; 12]))
+        );
+    }
+
+    #[test]
+    fn test_variance() {
+        let a = arr![arr![1.0, 2.0, 3.0], arr![2.0, 2.0, 2.0]].tracked();
+
+        let result = a.variance(1);
+        assert_relative_eq!(result, arr![arr![2.0 / 3.0], arr![0.0]]);
+
+        result.backward(None);
+        assert_relative_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![-2.0 / 3.0, 0.0, 2.0 / 3.0], arr![0.0, 0.0, 0.0]]
+        );
+    }
+
     #[test]
     fn test_backward_sum() {
         let a = arr![1.0, 2.0, 3.0].tracked();
@@ -460,6 +551,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_powf_backward() {
+        let a = arr![1.0, 2.0, 4.0].tracked();
+
+        let result = a.powf(0.5);
+        result.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![0.5, 0.25 * (2.0 as Float).sqrt(), 0.25]
+        );
+    }
+
+    #[test]
+    fn test_clamp() {
+        let a = arr![-2.0, 0.5, 3.0].tracked();
+
+        let result = a.clamp(0.0, 1.0);
+        assert_eq!(result, arr![0.0, 0.5, 1.0]);
+
+        result.backward(None);
+        assert_eq!(a.gradient().to_owned().unwrap(), arr![0.0, 1.0, 0.0]);
+    }
+
     #[test]
     fn test_backward_div_sum() {
         let a = arr![arr![2.0, 4.0, 2.0]].tracked();
@@ -486,4 +600,29 @@ mod tests {
             arr![arr![1.0, 2.0, 3.0], arr![1.0, 2.0, 3.0]]
         );
     }
+
+    #[test]
+    fn test_add_broadcast_leading() {
+        let a = Array::from((
+            vec![2, 2, 1, 2],
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0],
+        ))
+        .tracked();
+        let b = Array::from((vec![2, 1, 1], vec![10.0, 20.0])).tracked();
+
+        let result = &a + &b;
+        assert_eq!(
+            result,
+            Array::from((
+                vec![2, 2, 1, 2],
+                vec![11.0, 12.0, 23.0, 24.0, 15.0, 16.0, 27.0, 28.0]
+            ))
+        );
+
+        result.backward(None);
+        assert_eq!(
+            b.gradient().to_owned().unwrap(),
+            Array::from((vec![2, 1, 1], vec![4.0, 4.0]))
+        );
+    }
 }
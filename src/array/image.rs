@@ -1,111 +1,158 @@
 use crate::array::*;
 
+/// Computes the number of positions a filter is strided to along each spatial dimension.
+fn stride_counts(
+    spatial_dimensions: &[usize],
+    strides: &[usize],
+    filter_dimensions: &[usize],
+) -> Vec<usize> {
+    spatial_dimensions
+        .iter()
+        .zip(strides)
+        .zip(filter_dimensions)
+        .map(|((&size, &stride), &filter_size)| (size - filter_size) / stride + 1)
+        .collect()
+}
+
+/// Computes the offsets into an image with the given spatial dimensions of each strided position of the filter, and
+/// of each value of the filter relative to its position, both in row-major order.
+fn block_offsets(
+    spatial_dimensions: &[usize],
+    strides: &[usize],
+    filter_dimensions: &[usize],
+) -> (Vec<usize>, Vec<usize>) {
+    // the offsets of each position of the counts, where each index is scaled
+    let offsets = |counts: &[usize], scales: &[usize]| {
+        let mut offsets = vec![0];
+        for ((&count, &scale), &size) in counts.iter().zip(scales).zip(spatial_dimensions) {
+            offsets = offsets
+                .iter()
+                .flat_map(|&offset| (0..count).map(move |i| offset * size + i * scale))
+                .collect();
+        }
+        offsets
+    };
+
+    let counts = stride_counts(spatial_dimensions, strides, filter_dimensions);
+    (
+        offsets(&counts, strides),
+        offsets(filter_dimensions, &vec![1; filter_dimensions.len()]),
+    )
+}
+
+/// The input indices, and weights which each output index of a resized dimension is interpolated from.
+type Interpolation = Vec<Vec<(usize, Float)>>;
+
+/// Computes the interpolation of nearest neighbour resizing, where each output index takes the input index it falls in.
+fn nearest_interpolation(input_size: usize, output_size: usize) -> Interpolation {
+    (0..output_size)
+        .map(|i| vec![(i * input_size / output_size, 1.0)])
+        .collect()
+}
+
+/// Computes the interpolation of linear resizing, where each output index is aligned to the center of the value it
+/// falls in, and is weighted between the two nearest input indices.
+fn linear_interpolation(input_size: usize, output_size: usize) -> Interpolation {
+    let scale = input_size as Float / output_size as Float;
+    (0..output_size)
+        .map(|i| {
+            let position = ((i as Float + 0.5) * scale - 0.5).max(0.0);
+            let lower = (position.floor() as usize).min(input_size - 1);
+            let upper = (lower + 1).min(input_size - 1);
+            let weight = position - lower as Float;
+            vec![(lower, 1.0 - weight), (upper, weight)]
+        })
+        .collect()
+}
+
 impl Array {
     /// Unrolls an image into rows of blocks with the length of the filter.
-    fn unroll_blocks(
-        image: &Array,
-        stride_dimensions: (usize, usize),
-        filter_dimensions: (usize, usize),
-    ) -> Array {
+    ///
+    /// The image has the dimensions depth followed by as many spatial dimensions as there are strides.
+    fn unroll_blocks(image: &Array, strides: &[usize], filter_dimensions: &[usize]) -> Array {
+        let spatial_count = strides.len();
         let dimension_count = image.dimensions.len();
-        let (stride_rows, stride_cols) = stride_dimensions;
-        let (filter_rows, filter_cols) = filter_dimensions;
-
-        let image_depth = image.dimensions[dimension_count - 3];
-        let image_rows = image.dimensions[dimension_count - 2];
-        let image_cols = image.dimensions[dimension_count - 1];
-
-        let image_dimensions = (image_depth, image_rows, image_cols);
+        let image_dimensions = image.dimensions[dimension_count - spatial_count - 1..].to_vec();
 
-        // the number of values in strided to
-        let row_stride_count = (image_rows - filter_rows) / stride_rows + 1;
-        let col_stride_count = (image_cols - filter_cols) / stride_cols + 1;
+        let image_depth = image_dimensions[0];
+        let image_size: usize = image_dimensions[1..].iter().product();
+        let (stride_offsets, filter_offsets) =
+            block_offsets(&image_dimensions[1..], strides, filter_dimensions);
 
         // the number of unrolled rows
-        let unrolled_count = row_stride_count * col_stride_count;
+        let unrolled_count = stride_offsets.len();
         // the length of each unrolled row
-        let unrolled_size = filter_rows * filter_cols;
+        let unrolled_size = filter_offsets.len();
 
         let output_dimensions: Vec<usize> = image
             .dimensions
             .iter()
             .cloned()
-            .take(dimension_count - 3)
+            .take(dimension_count - spatial_count - 1)
             .chain(vec![unrolled_count, image_depth * unrolled_size])
             .collect();
 
         let op: SlicedOp = Box::new(move |output_slice, arrays| {
             let mut output_index = 0;
-            for r in 0..row_stride_count {
-                for c in 0..col_stride_count {
-                    for k in 0..image_depth {
-                        for m in 0..filter_rows {
-                            // the filter row position plus the stride row position
-                            let row_index = m + stride_rows * r;
-                            for n in 0..filter_cols {
-                                // the filter col position plus the stride col position
-                                let col_index = n + stride_cols * c;
-                                let input_index =
-                                    col_index + image_cols * (row_index + image_rows * k);
-                                output_slice[output_index] = arrays[0][input_index];
-                                output_index += 1;
-                            }
-                        }
+            for stride_offset in &stride_offsets {
+                for k in 0..image_depth {
+                    for filter_offset in &filter_offsets {
+                        let input_index = filter_offset + stride_offset + image_size * k;
+                        output_slice[output_index] = arrays[0][input_index];
+                        output_index += 1;
                     }
                 }
             }
         });
 
         let result = Array::sliced_op(
+            "unroll_blocks",
             vec![image],
             &op,
             None,
             &image.dimensions,
             &output_dimensions,
-            3,
+            spatial_count + 1,
             0,
         );
 
         if !image.is_tracked.get() {
             result
         } else {
+            let (strides, filter_dimensions) = (strides.to_vec(), filter_dimensions.to_vec());
             let backward_op: BackwardOp = Rc::new(move |_, t, x| {
                 vec![if t[0] {
                     Some(Array::roll_blocks(
                         &x,
-                        image_dimensions,
-                        stride_dimensions,
-                        filter_dimensions,
+                        &image_dimensions,
+                        &strides,
+                        &filter_dimensions,
                     ))
                 } else {
                     None
                 }]
             });
             result
-                .with_backward_op(backward_op)
+                .with_backward_op("unroll_blocks", backward_op)
                 .with_children(vec![image.clone()])
         }
     }
 
-    /// Inverse of unrolling the blocks.
+    /// Inverse of unrolling the blocks, which sums the values of overlapping blocks.
+    ///
+    /// The image dimensions are the depth followed by as many spatial dimensions as there are strides.
     fn roll_blocks(
         unrolled: &Array,
-        image_dimensions: (usize, usize, usize),
-        stride_dimensions: (usize, usize),
-        filter_dimensions: (usize, usize),
+        image_dimensions: &[usize],
+        strides: &[usize],
+        filter_dimensions: &[usize],
     ) -> Array {
         let dimension_count = unrolled.dimensions.len();
-        let (image_depth, image_rows, image_cols) = image_dimensions;
-        let (stride_rows, stride_cols) = stride_dimensions;
-        let (filter_rows, filter_cols) = filter_dimensions;
 
-        // the number of unrolled rows
-        let unrolled_count = unrolled.dimensions[dimension_count - 2];
-        // the length of each unrolled row
-        let unrolled_size = filter_rows * filter_cols;
-
-        // the number of values in strided to
-        let col_stride_count = (image_cols - filter_cols) / stride_cols + 1;
+        let image_depth = image_dimensions[0];
+        let image_size: usize = image_dimensions[1..].iter().product();
+        let (stride_offsets, filter_offsets) =
+            block_offsets(&image_dimensions[1..], strides, filter_dimensions);
 
         let leading_dimensions = unrolled
             .dimensions
@@ -114,85 +161,67 @@ impl Array {
             .take(dimension_count - 2);
 
         let output_dimensions: Vec<usize> = leading_dimensions
-            .chain(vec![image_depth, image_rows, image_cols])
+            .chain(image_dimensions.iter().copied())
             .collect();
 
         let op: SlicedOp = Box::new(move |output_slice, arrays| {
-            for i in 0..unrolled_count {
-                let stride_offset = {
-                    let stride_row_index = i / col_stride_count;
-                    let stride_col_index = i % col_stride_count;
-
-                    let stride_row_offset = image_cols * stride_rows * stride_row_index;
-                    let stride_col_offset = stride_cols * stride_col_index;
-
-                    stride_row_offset + stride_col_offset
-                };
-
-                for j in 0..unrolled_size * image_depth {
-                    let current_depth = j / unrolled_size;
-                    let filter_index = j % unrolled_size;
-
-                    let input_index = j + unrolled_size * image_depth * i;
-                    let output_index = {
-                        let depth_offset = image_rows * image_cols * current_depth;
-
-                        let filter_row_index = filter_index / filter_cols;
-                        let filter_col_index = filter_index % filter_cols;
-                        let filter_row_offset = image_cols * filter_row_index;
-
-                        filter_col_index + filter_row_offset + depth_offset + stride_offset
-                    };
-
-                    output_slice[output_index] = arrays[0][input_index];
+            let mut input_index = 0;
+            for stride_offset in &stride_offsets {
+                for k in 0..image_depth {
+                    for filter_offset in &filter_offsets {
+                        let output_index = filter_offset + stride_offset + image_size * k;
+                        output_slice[output_index] += arrays[0][input_index];
+                        input_index += 1;
+                    }
                 }
             }
         });
 
         let result = Array::sliced_op(
+            "roll_blocks",
             vec![unrolled],
             &op,
             None,
             &unrolled.dimensions,
             &output_dimensions,
-            3,
+            2,
             0,
         );
 
         if !unrolled.is_tracked.get() {
             result
         } else {
+            let (strides, filter_dimensions) = (strides.to_vec(), filter_dimensions.to_vec());
             let backward_op: BackwardOp = Rc::new(move |_, t, x| {
                 vec![if t[0] {
-                    Some(Array::unroll_blocks(
-                        &x,
-                        stride_dimensions,
-                        filter_dimensions,
-                    ))
+                    Some(Array::unroll_blocks(&x, &strides, &filter_dimensions))
                 } else {
                     None
                 }]
             });
             result
-                .with_backward_op(backward_op)
+                .with_backward_op("roll_blocks", backward_op)
                 .with_children(vec![unrolled.clone()])
         }
     }
 
-    /// Transforms arrays of the form (output rows * output cols, depth) to (depth, output rows, output cols).
-    fn expand_conv(&self, stride_counts: (usize, usize)) -> Array {
-        let (row_stride_count, col_stride_count) = stride_counts;
+    /// Transforms arrays of the form (product of stride counts, depth) to (depth, stride counts...).
+    fn expand_conv(&self, stride_counts: &[usize]) -> Array {
         let filter_count = self.dimensions[self.dimensions.len() - 1];
 
         let values_length = self.values.len();
         // the stride between two convolution outputs
-        let stride = values_length / filter_count;
+        let stride = self.dimensions[self.dimensions.len() - 2];
+        // the length of the convolution outputs of each image
+        let image_length = stride * filter_count;
         let mut result = vec![0.0; values_length];
         let mut result_index = 0;
-        for k in 0..filter_count {
-            for i in 0..stride {
-                result[result_index] = self.values[k + filter_count * i];
-                result_index += 1;
+        for offset in (0..values_length).step_by(image_length) {
+            for k in 0..filter_count {
+                for i in 0..stride {
+                    result[result_index] = self.values[offset + k + filter_count * i];
+                    result_index += 1;
+                }
             }
         }
 
@@ -201,7 +230,8 @@ impl Array {
             .iter()
             .take(self.dimensions.len() - 2)
             .copied()
-            .chain(vec![filter_count, row_stride_count, col_stride_count])
+            .chain(vec![filter_count])
+            .chain(stride_counts.iter().copied())
             .collect();
 
         let result = Array::from((output_dimensions, result));
@@ -212,10 +242,12 @@ impl Array {
             let backward_op: BackwardOp = Rc::new(move |c, _, x| {
                 let mut result = vec![0.0; values_length];
                 let mut delta_index = 0;
-                for k in 0..filter_count {
-                    for i in 0..stride {
-                        result[k + filter_count * i] = x.values[delta_index];
-                        delta_index += 1;
+                for offset in (0..values_length).step_by(image_length) {
+                    for k in 0..filter_count {
+                        for i in 0..stride {
+                            result[offset + k + filter_count * i] = x.values[delta_index];
+                            delta_index += 1;
+                        }
                     }
                 }
 
@@ -223,51 +255,65 @@ impl Array {
             });
 
             result
-                .with_backward_op(backward_op)
+                .with_backward_op("expand_conv", backward_op)
                 .with_children(vec![self.clone()])
         }
     }
 
     /// Computes the image convolution of the array with the filter.
     pub fn conv(&self, filters: &Array, stride_dimensions: (usize, usize)) -> Array {
+        let (stride_rows, stride_cols) = stride_dimensions;
+        self.conv_nd(filters, &[stride_rows, stride_cols])
+    }
+
+    /// Computes the convolution of the array with the filter, over as many trailing spatial dimensions as there are
+    /// strides.
+    ///
+    /// The array has the dimensions depth followed by the spatial dimensions, and the filter has the dimensions filter
+    /// count by depth followed by the spatial dimensions of the filter.
+    pub fn conv_nd(&self, filters: &Array, strides: &[usize]) -> Array {
+        let spatial_count = strides.len();
         let dimension_count = self.dimensions.len();
         let filter_dimension_count = filters.dimensions.len();
         // the number of dimensions for the unrolled image
-        let unrolled_dimension_count = dimension_count - 1;
+        let unrolled_dimension_count = dimension_count + 1 - spatial_count;
 
         assert!(
-            dimension_count >= 3 && filter_dimension_count >= 3,
-            "error: cannot convolve with fewer than 3 dimensions"
+            spatial_count > 0
+                && dimension_count > spatial_count
+                && filter_dimension_count > spatial_count,
+            "error: cannot convolve over {} spatial dimensions with fewer than {} dimensions",
+            spatial_count,
+            spatial_count + 1
         );
 
-        let (stride_rows, stride_cols) = stride_dimensions;
+        let image_depth = self.dimensions[dimension_count - spatial_count - 1];
+        let spatial_dimensions = &self.dimensions[dimension_count - spatial_count..];
+        let filter_dimensions = &filters.dimensions[filter_dimension_count - spatial_count..];
 
-        let (image_depth, image_rows, image_cols) = (
-            self.dimensions[dimension_count - 3],
-            self.dimensions[dimension_count - 2],
-            self.dimensions[dimension_count - 1],
-        );
-
-        let (filter_rows, filter_cols) = (
-            filters.dimensions[filter_dimension_count - 2],
-            filters.dimensions[filter_dimension_count - 1],
+        assert!(
+            strides.iter().all(|&s| s > 0)
+                && filter_dimensions
+                    .iter()
+                    .zip(spatial_dimensions)
+                    .all(|(f, i)| f <= i),
+            "error: the filter dimensions {:?} must fit in the image dimensions {:?}, with nonzero strides",
+            filter_dimensions,
+            spatial_dimensions
         );
 
-        let filter_dimensions = (filter_rows, filter_cols);
-
-        let row_stride_count = (image_rows - filter_rows) / stride_rows + 1;
-        let col_stride_count = (image_cols - filter_cols) / stride_cols + 1;
+        let stride_counts = stride_counts(spatial_dimensions, strides, filter_dimensions);
 
         // convert image dimensions to (unrolled count, unrolled size * image depth)
-        let unrolled = Array::unroll_blocks(&self, stride_dimensions, filter_dimensions);
+        let unrolled = Array::unroll_blocks(self, strides, filter_dimensions);
         let unrolled_size = unrolled.dimensions[unrolled_dimension_count - 1] / image_depth;
 
-        // combine last three filter dimensions to single row to (filter count, unrolled size * image depth)
+        // combine the filter depth, and spatial dimensions to single row to (filter count, unrolled size * image depth)
         let filter_matrix_dimensions = filters
             .dimensions
             .iter()
             .cloned()
-            .take(filter_dimension_count.saturating_sub(3))
+            .take(filter_dimension_count.saturating_sub(spatial_count + 1))
             .chain(vec![unrolled_size * image_depth])
             .collect();
 
@@ -275,12 +321,237 @@ impl Array {
 
         // convert unrolled dimensions to (unrolled count, filter count)
         let convolved = Array::matmul((&unrolled, false), (&filter_matrix, true), None);
-        // convert convolved dimensions to (filter count, row stride count, col stride count)
-        convolved.expand_conv((row_stride_count, col_stride_count))
+        // convert convolved dimensions to (filter count, stride counts...)
+        convolved.expand_conv(&stride_counts)
     }
 
-    // pub fn pool(&self, pool_dimensions: (usize, usize)) {
-    // }
+    /// Computes the max pooling of the array over non-overlapping windows, along as many trailing dimensions as there
+    /// are pool dimensions, where any remainder is discarded.
+    pub fn max_pool(&self, pool_dimensions: &[usize]) -> Array {
+        self.pool(pool_dimensions, true)
+    }
+
+    /// Computes the average pooling of the array over non-overlapping windows, along as many trailing dimensions as
+    /// there are pool dimensions, where any remainder is discarded.
+    pub fn avg_pool(&self, pool_dimensions: &[usize]) -> Array {
+        self.pool(pool_dimensions, false)
+    }
+
+    /// Pools the array, taking the max of each window if `is_max`, and the average otherwise.
+    fn pool(&self, pool_dimensions: &[usize], is_max: bool) -> Array {
+        let spatial_count = pool_dimensions.len();
+        let dimension_count = self.dimensions.len();
+
+        assert!(
+            spatial_count > 0
+                && dimension_count >= spatial_count
+                && pool_dimensions
+                    .iter()
+                    .zip(&self.dimensions[dimension_count - spatial_count..])
+                    .all(|(&p, &i)| p > 0 && p <= i),
+            "error: cannot pool with dimensions {:?} over dimensions {:?}",
+            pool_dimensions,
+            self.dimensions
+        );
+
+        let spatial_dimensions = &self.dimensions[dimension_count - spatial_count..];
+        let image_size: usize = spatial_dimensions.iter().product();
+        let (stride_offsets, window_offsets) =
+            block_offsets(spatial_dimensions, pool_dimensions, pool_dimensions);
+        let window_size = window_offsets.len();
+
+        let output_dimensions: Vec<usize> = self
+            .dimensions
+            .iter()
+            .take(dimension_count - spatial_count)
+            .copied()
+            .chain(stride_counts(
+                spatial_dimensions,
+                pool_dimensions,
+                pool_dimensions,
+            ))
+            .collect();
+
+        // the index of the max of each window
+        let mut sources = Vec::new();
+        let mut values = Vec::with_capacity(output_dimensions.iter().product());
+        for image_offset in (0..self.values.len()).step_by(image_size) {
+            for stride_offset in &stride_offsets {
+                let offset = image_offset + stride_offset;
+                if is_max {
+                    let source =
+                        window_offsets
+                            .iter()
+                            .map(|w| offset + w)
+                            .fold(offset, |source, i| {
+                                if self.values[i] > self.values[source] {
+                                    i
+                                } else {
+                                    source
+                                }
+                            });
+                    sources.push(source);
+                    values.push(self.values[source]);
+                } else {
+                    let sum: Float = window_offsets.iter().map(|w| self.values[offset + w]).sum();
+                    values.push(sum / window_size as Float);
+                }
+            }
+        }
+
+        let result = Array::from((output_dimensions, values));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                let mut result = vec![0.0; c[0].values.len()];
+                if is_max {
+                    for (&source, delta) in sources.iter().zip(x.values.iter()) {
+                        result[source] += delta;
+                    }
+                } else {
+                    let mut deltas = x.values.iter();
+                    for image_offset in (0..result.len()).step_by(image_size) {
+                        for stride_offset in &stride_offsets {
+                            let delta = deltas.next().unwrap() / window_size as Float;
+                            for window_offset in &window_offsets {
+                                result[image_offset + stride_offset + window_offset] += delta;
+                            }
+                        }
+                    }
+                }
+
+                vec![Some(Array::from((c[0].dimensions.clone(), result)))]
+            });
+
+            result
+                .with_backward_op(if is_max { "max_pool" } else { "avg_pool" }, backward_op)
+                .with_children(vec![self.clone()])
+        }
+    }
+
+    /// Upsamples the last two dimensions of the array to the given rows, and columns, using the nearest value.
+    pub fn upsample_nearest(&self, size: (usize, usize)) -> Array {
+        self.resize(size, nearest_interpolation)
+    }
+
+    /// Upsamples the last two dimensions of the array to the given rows, and columns, using bilinear interpolation.
+    pub fn upsample_bilinear(&self, size: (usize, usize)) -> Array {
+        self.resize(size, linear_interpolation)
+    }
+
+    /// Upsamples the last two dimensions of the array by the scale factor, rounding down, using the nearest value.
+    pub fn upsample_nearest_scaled(&self, scale_factor: Float) -> Array {
+        self.resize(self.scaled_size(scale_factor), nearest_interpolation)
+    }
+
+    /// Upsamples the last two dimensions of the array by the scale factor, rounding down, using bilinear
+    /// interpolation.
+    pub fn upsample_bilinear_scaled(&self, scale_factor: Float) -> Array {
+        self.resize(self.scaled_size(scale_factor), linear_interpolation)
+    }
+
+    /// Computes the rows, and columns of the last two dimensions of the array scaled by the scale factor.
+    fn scaled_size(&self, scale_factor: Float) -> (usize, usize) {
+        let dimension_count = self.dimensions.len();
+        assert!(
+            dimension_count >= 2 && scale_factor > 0.0,
+            "error: cannot scale dimensions {:?} by {}",
+            self.dimensions,
+            scale_factor
+        );
+
+        let scale = |x: usize| (x as Float * scale_factor).floor() as usize;
+        (
+            scale(self.dimensions[dimension_count - 2]),
+            scale(self.dimensions[dimension_count - 1]),
+        )
+    }
+
+    /// Resizes the last two dimensions of the array, where each output value is the weighted sum of the input values
+    /// given by interpolating the rows, and columns.
+    fn resize(
+        &self,
+        size: (usize, usize),
+        interpolation: fn(usize, usize) -> Interpolation,
+    ) -> Array {
+        let dimension_count = self.dimensions.len();
+        let (output_rows, output_cols) = size;
+
+        assert!(
+            dimension_count >= 2
+                && self.dimensions[dimension_count - 2] > 0
+                && self.dimensions[dimension_count - 1] > 0
+                && output_rows > 0
+                && output_cols > 0,
+            "error: cannot resize dimensions {:?} to {:?}",
+            self.dimensions,
+            size
+        );
+
+        let (image_rows, image_cols) = (
+            self.dimensions[dimension_count - 2],
+            self.dimensions[dimension_count - 1],
+        );
+
+        let row_weights = Rc::new(interpolation(image_rows, output_rows));
+        let col_weights = Rc::new(interpolation(image_cols, output_cols));
+
+        let output_dimensions: Vec<usize> = self
+            .dimensions
+            .iter()
+            .take(dimension_count - 2)
+            .copied()
+            .chain(vec![output_rows, output_cols])
+            .collect();
+
+        let image_count = self.values.len() / (image_rows * image_cols);
+        let mut values = Vec::with_capacity(image_count * output_rows * output_cols);
+        for image in self.values.chunks(image_rows * image_cols) {
+            for rows in row_weights.iter() {
+                for cols in col_weights.iter() {
+                    let mut value = 0.0;
+                    for (i, row_weight) in rows {
+                        for (j, col_weight) in cols {
+                            value += row_weight * col_weight * image[j + image_cols * i];
+                        }
+                    }
+                    values.push(value);
+                }
+            }
+        }
+
+        let result = Array::from((output_dimensions, values));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            // each input value accumulates the delta of the outputs it was interpolated to
+            let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                let mut result = vec![0.0; c[0].values.len()];
+                let mut deltas = x.values.iter();
+                for image in result.chunks_mut(image_rows * image_cols) {
+                    for rows in row_weights.iter() {
+                        for cols in col_weights.iter() {
+                            let delta = deltas.next().unwrap();
+                            for (i, row_weight) in rows {
+                                for (j, col_weight) in cols {
+                                    image[j + image_cols * i] += row_weight * col_weight * delta;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                vec![Some(Array::from((c[0].dimensions.clone(), result)))]
+            });
+
+            result
+                .with_backward_op("upsample", backward_op)
+                .with_children(vec![self.clone()])
+        }
+    }
 }
 
 #[cfg(test)]
@@ -291,7 +562,7 @@ mod tests {
     #[test]
     fn test_expand_conv() {
         let a = arr![arr![1.0, 4.0], arr![2.0, 5.0], arr![3.0, 6.0]].tracked();
-        let expanded = a.expand_conv((1, 3));
+        let expanded = a.expand_conv(&[1, 3]);
         assert_eq!(
             expanded,
             arr![arr![arr![1.0, 2.0, 3.0]], arr![arr![4.0, 5.0, 6.0]]]
@@ -308,7 +579,7 @@ mod tests {
             arr![4.0, 5.0, 6.0],
             arr![7.0, 8.0, 9.0]
         ]];
-        let result = Array::unroll_blocks(&a, (1, 1), (2, 2));
+        let result = Array::unroll_blocks(&a, &[1, 1], &[2, 2]);
         assert_eq!(
             result,
             arr![
@@ -318,8 +589,16 @@ mod tests {
                 arr![5.0, 6.0, 8.0, 9.0]
             ]
         );
-        let rolled = Array::roll_blocks(&result, (1, 3, 3), (1, 1), (2, 2));
-        assert_eq!(rolled, a);
+        // values in overlapping blocks are summed
+        let rolled = Array::roll_blocks(&result, &[1, 3, 3], &[1, 1], &[2, 2]);
+        assert_eq!(
+            rolled,
+            arr![arr![
+                arr![1.0, 4.0, 3.0],
+                arr![8.0, 20.0, 12.0],
+                arr![7.0, 16.0, 9.0]
+            ]]
+        );
     }
 
     #[test]
@@ -329,7 +608,7 @@ mod tests {
             arr![5.0, 6.0, 7.0, 8.0],
             arr![9.0, 10.0, 11.0, 12.0]
         ]];
-        let result = Array::unroll_blocks(&a, (1, 1), (2, 3));
+        let result = Array::unroll_blocks(&a, &[1, 1], &[2, 3]);
         assert_eq!(
             result,
             arr![
@@ -339,8 +618,15 @@ mod tests {
                 arr![6.0, 7.0, 8.0, 10.0, 11.0, 12.0]
             ]
         );
-        let rolled = Array::roll_blocks(&result, (1, 3, 4), (1, 1), (2, 3));
-        assert_eq!(rolled, a);
+        let rolled = Array::roll_blocks(&result, &[1, 3, 4], &[1, 1], &[2, 3]);
+        assert_eq!(
+            rolled,
+            arr![arr![
+                arr![1.0, 4.0, 6.0, 4.0],
+                arr![10.0, 24.0, 28.0, 16.0],
+                arr![9.0, 20.0, 22.0, 12.0]
+            ]]
+        );
     }
 
     #[test]
@@ -349,7 +635,7 @@ mod tests {
             arr![arr![1.0, 2.0, 3.0, 4.0], arr![5.0, 6.0, 7.0, 8.0]],
             arr![arr![9.0, 10.0, 11.0, 12.0], arr![13.0, 14.0, 15.0, 16.0]]
         ];
-        let result = Array::unroll_blocks(&a, (1, 2), (1, 2));
+        let result = Array::unroll_blocks(&a, &[1, 2], &[1, 2]);
         assert_eq!(
             result,
             arr![
@@ -359,7 +645,7 @@ mod tests {
                 arr![7.0, 8.0, 15.0, 16.0]
             ],
         );
-        let rolled = Array::roll_blocks(&result, (2, 2, 4), (1, 2), (1, 2));
+        let rolled = Array::roll_blocks(&result, &[2, 2, 4], &[1, 2], &[1, 2]);
         assert_eq!(rolled, a);
     }
 
@@ -389,6 +675,36 @@ mod tests {
         assert_eq!(conv, arr![arr![arr![arr![51.0, 67.0], arr![99.0, 115.0]]]]);
     }
 
+    #[test]
+    fn test_conv_batch() {
+        let a = Array::from((
+            vec![2, 1, 3, 3],
+            (0..18).map(|x| x as Float).collect::<Vec<Float>>(),
+        ))
+        .tracked();
+
+        let filters = arr![
+            arr![arr![arr![1.0, 0.0], arr![0.0, 1.0]]],
+            arr![arr![arr![0.0, 1.0], arr![0.0, 0.0]]]
+        ];
+        let conv = a.conv(&filters, (1, 1));
+        assert_eq!(
+            conv,
+            Array::from((
+                vec![2, 2, 2, 2],
+                vec![
+                    4.0, 6.0, 10.0, 12.0, 1.0, 2.0, 4.0, 5.0, 22.0, 24.0, 28.0, 30.0, 10.0, 11.0,
+                    13.0, 14.0
+                ]
+            ))
+        );
+
+        conv.backward(None);
+        let gradient = a.gradient().to_owned().unwrap();
+        assert_eq!(gradient.dimensions(), &[2, 1, 3, 3]);
+        assert_eq!(gradient.values()[0..9], gradient.values()[9..18]);
+    }
+
     #[test]
     fn test_conv_multi() {
         let input_dimensions = vec![3, 9, 9];
@@ -473,4 +789,145 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_conv_1d() {
+        let a = arr![arr![1.0, 2.0, 3.0, 4.0, 5.0], arr![0.0, 1.0, 0.0, 1.0, 0.0]].tracked();
+        let filters = arr![arr![arr![1.0, -1.0], arr![2.0, 0.0]]].tracked();
+
+        let conv = a.conv_nd(&filters, &[2]);
+        assert_eq!(conv, arr![arr![-1.0, -1.0]]);
+
+        conv.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![
+                arr![1.0, -1.0, 1.0, -1.0, 0.0],
+                arr![2.0, 0.0, 2.0, 0.0, 0.0]
+            ]
+        );
+        assert_eq!(
+            filters.gradient().to_owned().unwrap(),
+            arr![arr![arr![4.0, 6.0], arr![0.0, 2.0]]]
+        );
+    }
+
+    #[test]
+    fn test_conv_3d() {
+        let a = Array::from((
+            vec![2, 1, 3, 3, 3],
+            (0..54).map(|x| x as Float).collect::<Vec<Float>>(),
+        ))
+        .tracked();
+
+        // the filter sums the corners of each 2 by 2 by 2 block
+        let filters = Array::from((
+            vec![1, 1, 2, 2, 2],
+            vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+        ));
+        let conv = a.conv_nd(&filters, &[1, 1, 1]);
+        assert_eq!(conv.dimensions(), &[2, 1, 2, 2, 2]);
+        assert_eq!(
+            conv.values(),
+            &[
+                13.0, 15.0, 19.0, 21.0, 31.0, 33.0, 37.0, 39.0, 67.0, 69.0, 73.0, 75.0, 85.0, 87.0,
+                91.0, 93.0
+            ]
+        );
+
+        conv.backward(None);
+        let gradient = a.gradient().to_owned().unwrap();
+        assert_eq!(gradient[0], 1.0);
+        assert_eq!(gradient[13], 2.0);
+        assert_eq!(gradient[26], 1.0);
+    }
+
+    #[test]
+    fn test_max_pool() {
+        let a = arr![arr![
+            arr![1.0, 5.0, 2.0, 0.0, 9.0],
+            arr![3.0, 4.0, 8.0, 6.0, 9.0],
+            arr![9.0, 9.0, 9.0, 9.0, 9.0]
+        ]]
+        .tracked();
+
+        let pooled = a.max_pool(&[2, 2]);
+        assert_eq!(pooled, arr![arr![arr![5.0, 8.0]]]);
+
+        pooled.backward(Some(arr![arr![arr![1.0, 2.0]]]));
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![
+                arr![0.0, 1.0, 0.0, 0.0, 0.0],
+                arr![0.0, 0.0, 2.0, 0.0, 0.0],
+                arr![0.0, 0.0, 0.0, 0.0, 0.0]
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_avg_pool() {
+        let a = arr![arr![1.0, 3.0, 2.0, 6.0, 7.0], arr![0.0, 4.0, 1.0, 1.0, 5.0]].tracked();
+
+        let pooled = a.avg_pool(&[2]);
+        assert_eq!(pooled, arr![arr![2.0, 4.0], arr![2.0, 1.0]]);
+
+        pooled.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![0.5, 0.5, 0.5, 0.5, 0.0], arr![0.5, 0.5, 0.5, 0.5, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_upsample_nearest() {
+        let a = arr![arr![arr![1.0, 2.0], arr![3.0, 4.0]]].tracked();
+
+        let upsampled = a.upsample_nearest((4, 3));
+        assert_eq!(
+            upsampled,
+            arr![arr![
+                arr![1.0, 1.0, 2.0],
+                arr![1.0, 1.0, 2.0],
+                arr![3.0, 3.0, 4.0],
+                arr![3.0, 3.0, 4.0]
+            ]]
+        );
+
+        upsampled.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![arr![4.0, 2.0], arr![4.0, 2.0]]]
+        );
+    }
+
+    #[test]
+    fn test_upsample_bilinear() {
+        let a = arr![arr![1.0, 3.0], arr![5.0, 7.0]].tracked();
+
+        let upsampled = a.upsample_bilinear((4, 4));
+        assert_eq!(
+            upsampled,
+            arr![
+                arr![1.0, 1.5, 2.5, 3.0],
+                arr![2.0, 2.5, 3.5, 4.0],
+                arr![4.0, 4.5, 5.5, 6.0],
+                arr![5.0, 5.5, 6.5, 7.0]
+            ]
+        );
+
+        // the weights of each output sum to one, and are spread evenly between the inputs
+        upsampled.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![4.0, 4.0], arr![4.0, 4.0]]
+        );
+    }
+
+    #[test]
+    fn test_upsample_scaled() {
+        let a = arr![arr![arr![1.0, 2.0], arr![3.0, 4.0]]];
+        assert_eq!(a.upsample_nearest_scaled(1.5), a.upsample_nearest((3, 3)));
+        assert_eq!(a.upsample_bilinear_scaled(2.0), a.upsample_bilinear((4, 4)));
+    }
 }
@@ -23,7 +23,268 @@ impl Array {
             });
 
             result
-                .with_backward_op(backward_op)
+                .with_backward_op("reshape", backward_op)
+                .with_children(vec![self.clone()])
+        }
+    }
+
+    /// Stacks arrays of the same dimensions along a new first dimension.
+    pub fn stack(arrays: &[&Array]) -> Array {
+        let is_dimensions_valid = match arrays.split_first() {
+            Some((first, elements)) => elements.iter().all(|a| a.dimensions == first.dimensions),
+            None => false,
+        };
+
+        assert!(
+            is_dimensions_valid,
+            "error: stacked arrays must be non-empty, with matching dimensions"
+        );
+
+        let dimensions: Vec<usize> = vec![arrays.len()]
+            .into_iter()
+            .chain(arrays[0].dimensions.iter().copied())
+            .collect();
+        let values: Vec<Float> = arrays
+            .iter()
+            .flat_map(|a| a.values.iter().copied())
+            .collect();
+
+        let result = Array::from((dimensions, values));
+
+        if arrays.iter().all(|a| !a.is_tracked.get()) {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(|c, t, x| {
+                let length = c[0].values.len();
+                c.iter()
+                    .zip(t)
+                    .enumerate()
+                    .map(|(i, (c, t))| {
+                        if *t {
+                            Some(Array::from((
+                                c.dimensions.clone(),
+                                x.values[i * length..(i + 1) * length].to_vec(),
+                            )))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            });
+
+            result
+                .with_children(arrays.iter().map(|a| (*a).clone()).collect())
+                .with_backward_op("stack", backward_op)
+        }
+    }
+
+    /// Splits the array along the first dimension, into arrays with the remaining dimensions.
+    pub fn unstack(&self) -> Vec<Array> {
+        assert!(
+            self.dimensions.len() > 1,
+            "error: cannot unstack an array with fewer than 2 dimensions"
+        );
+
+        let dimensions = self.dimensions[1..].to_vec();
+        let length: usize = dimensions.iter().product();
+        (0..self.dimensions[0])
+            .map(|i| {
+                let result = Array::from((
+                    dimensions.clone(),
+                    self.values[i * length..(i + 1) * length].to_vec(),
+                ));
+
+                if !self.is_tracked.get() {
+                    result
+                } else {
+                    let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                        let mut values = vec![0.0; c[0].values.len()];
+                        values[i * length..(i + 1) * length].copy_from_slice(&x.values);
+                        vec![Some(Array::from((c[0].dimensions.clone(), values)))]
+                    });
+
+                    result
+                        .with_children(vec![self.clone()])
+                        .with_backward_op("unstack", backward_op)
+                }
+            })
+            .collect()
+    }
+
+    /// Concatenates arrays with matching leading dimensions along the last dimension.
+    pub fn concat(arrays: &[&Array]) -> Array {
+        let leading_dimensions = |a: &Array| a.dimensions[0..a.dimensions.len() - 1].to_vec();
+        let is_dimensions_valid = match arrays.split_first() {
+            Some((first, elements)) => elements
+                .iter()
+                .all(|a| leading_dimensions(a) == leading_dimensions(first)),
+            None => false,
+        };
+
+        assert!(
+            is_dimensions_valid,
+            "error: concatenated arrays must be non-empty, with matching leading dimensions"
+        );
+
+        let lengths: Rc<Vec<usize>> = Rc::new(
+            arrays
+                .iter()
+                .map(|a| *a.dimensions.last().unwrap())
+                .collect(),
+        );
+        let total_length: usize = lengths.iter().sum();
+        let dimensions: Vec<usize> = leading_dimensions(arrays[0])
+            .into_iter()
+            .chain(vec![total_length])
+            .collect();
+
+        let row_count = arrays[0].values.len() / lengths[0].max(1);
+        let mut values = Vec::with_capacity(row_count * total_length);
+        for row in 0..row_count {
+            for (a, &length) in arrays.iter().zip(lengths.iter()) {
+                values.extend_from_slice(&a.values[row * length..(row + 1) * length]);
+            }
+        }
+
+        let result = Array::from((dimensions, values));
+
+        if arrays.iter().all(|a| !a.is_tracked.get()) {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+                let mut offset = 0;
+                c.iter()
+                    .zip(t)
+                    .zip(lengths.iter())
+                    .map(|((c, t), &length)| {
+                        let values: Vec<Float> = x
+                            .values
+                            .chunks(total_length)
+                            .flat_map(|row| row[offset..offset + length].iter().copied())
+                            .collect();
+                        offset += length;
+
+                        if *t {
+                            Some(Array::from((c.dimensions.clone(), values)))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            });
+
+            result
+                .with_children(arrays.iter().map(|a| (*a).clone()).collect())
+                .with_backward_op("concat", backward_op)
+        }
+    }
+
+    /// Splits the array along the last dimension into `count` arrays of equal length.
+    pub fn split(&self, count: usize) -> Vec<Array> {
+        let total_length = *self.dimensions.last().unwrap();
+        assert!(
+            total_length.checked_rem(count) == Some(0),
+            "error: the last dimension {} cannot be split into {} arrays",
+            total_length,
+            count
+        );
+
+        let length = total_length / count;
+        let dimensions: Vec<usize> = self.dimensions[0..self.dimensions.len() - 1]
+            .iter()
+            .copied()
+            .chain(vec![length])
+            .collect();
+
+        (0..count)
+            .map(|i| {
+                let offset = i * length;
+                let values: Vec<Float> = self
+                    .values
+                    .chunks(total_length)
+                    .flat_map(|row| row[offset..offset + length].iter().copied())
+                    .collect();
+                let result = Array::from((dimensions.clone(), values));
+
+                if !self.is_tracked.get() {
+                    result
+                } else {
+                    let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                        let mut values = vec![0.0; c[0].values.len()];
+                        for (row, delta) in
+                            values.chunks_mut(total_length).zip(x.values.chunks(length))
+                        {
+                            row[offset..offset + length].copy_from_slice(delta);
+                        }
+                        vec![Some(Array::from((c[0].dimensions.clone(), values)))]
+                    });
+
+                    result
+                        .with_children(vec![self.clone()])
+                        .with_backward_op("split", backward_op)
+                }
+            })
+            .collect()
+    }
+
+    /// Permutes the dimensions of the array, where dimension `i` of the output is dimension `axes[i]` of the array.
+    pub fn permute(&self, axes: &[usize]) -> Array {
+        let dimension_count = self.dimensions.len();
+        let mut is_permutation = axes.len() == dimension_count;
+        let mut inverse = vec![0; dimension_count];
+        for (i, &axis) in axes.iter().enumerate() {
+            is_permutation =
+                is_permutation && axis < dimension_count && !axes[0..i].contains(&axis);
+            if axis < dimension_count {
+                inverse[axis] = i;
+            }
+        }
+
+        assert!(
+            is_permutation,
+            "error: the axes {:?} must be a permutation of the dimensions {:?}",
+            axes, self.dimensions
+        );
+
+        // the stride of each dimension of the array
+        let mut strides = vec![1; dimension_count];
+        for i in (0..dimension_count.saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * self.dimensions[i + 1];
+        }
+
+        let dimensions: Vec<usize> = axes.iter().map(|&a| self.dimensions[a]).collect();
+        let mut values = Vec::with_capacity(self.values.len());
+        let mut indices = vec![0; dimension_count];
+        for _ in 0..self.values.len() {
+            let offset: usize = indices.iter().zip(axes).map(|(i, &a)| i * strides[a]).sum();
+            values.push(self.values[offset]);
+
+            // increment the last index that is not about to overflow, and reset the indices after it
+            for (x, d) in indices.iter_mut().zip(&dimensions).rev() {
+                if *x == *d - 1 {
+                    *x = 0;
+                } else {
+                    *x += 1;
+                    break;
+                }
+            }
+        }
+
+        let result = Array::from((dimensions, values));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |_, t, x| {
+                vec![if t[0] {
+                    Some(x.permute(&inverse))
+                } else {
+                    None
+                }]
+            });
+
+            result
+                .with_backward_op("permute", backward_op)
                 .with_children(vec![self.clone()])
         }
     }
@@ -60,7 +321,16 @@ impl Array {
                 }))
             };
 
-            Array::sliced_op(vec![x, y], &op, backward_op, &dimensions, &dimensions, 1, 0)
+            Array::sliced_op(
+                "axpy",
+                vec![x, y],
+                &op,
+                backward_op,
+                &dimensions,
+                &dimensions,
+                1,
+                0,
+            )
         }
     }
 
@@ -247,6 +517,7 @@ impl Array {
         let zeros = arr![0.0];
         let c = if let Some(c) = c { c } else { &zeros };
         Array::sliced_op(
+            "matmul",
             vec![a, b, c],
             &op,
             backward_op,
@@ -275,6 +546,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stack() {
+        let a = arr![arr![1.0, 2.0]].tracked();
+        let b = arr![arr![3.0, 4.0]];
+        let stacked = Array::stack(&[&a, &b]);
+        assert_eq!(stacked, arr![arr![arr![1.0, 2.0]], arr![arr![3.0, 4.0]]]);
+
+        stacked.backward(Some(arr![arr![arr![5.0, 6.0]], arr![arr![7.0, 8.0]]]));
+        assert_eq!(a.gradient().to_owned().unwrap(), arr![arr![5.0, 6.0]]);
+        assert!(b.gradient().is_none());
+    }
+
+    #[test]
+    fn test_unstack() {
+        let a = arr![arr![1.0, 2.0], arr![3.0, 4.0], arr![5.0, 6.0]].tracked();
+        let unstacked = a.unstack();
+        assert_eq!(unstacked.len(), 3);
+        assert_eq!(unstacked[1], arr![3.0, 4.0]);
+
+        (&(&unstacked[0] * 2.0) + &unstacked[2]).backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![2.0, 2.0], arr![0.0, 0.0], arr![1.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = arr![arr![1.0], arr![2.0]].tracked();
+        let b = arr![arr![3.0, 4.0], arr![5.0, 6.0]].tracked();
+        let result = Array::concat(&[&a, &b]);
+        assert_eq!(result, arr![arr![1.0, 3.0, 4.0], arr![2.0, 5.0, 6.0]]);
+
+        result.backward(Some(arr![arr![1.0, 2.0, 3.0], arr![4.0, 5.0, 6.0]]));
+        assert_eq!(a.gradient().to_owned().unwrap(), arr![arr![1.0], arr![4.0]]);
+        assert_eq!(
+            b.gradient().to_owned().unwrap(),
+            arr![arr![2.0, 3.0], arr![5.0, 6.0]]
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let a = arr![arr![1.0, 2.0, 3.0, 4.0], arr![5.0, 6.0, 7.0, 8.0]].tracked();
+        let split = a.split(2);
+        assert_eq!(split[0], arr![arr![1.0, 2.0], arr![5.0, 6.0]]);
+        assert_eq!(split[1], arr![arr![3.0, 4.0], arr![7.0, 8.0]]);
+
+        (&split[1] * 3.0).backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            arr![arr![0.0, 0.0, 3.0, 3.0], arr![0.0, 0.0, 3.0, 3.0]]
+        );
+    }
+
+    #[test]
+    fn test_permute() {
+        let a = Array::from((
+            vec![2, 3, 2],
+            (0..12).map(|x| x as Float).collect::<Vec<Float>>(),
+        ))
+        .tracked();
+
+        let permuted = a.permute(&[2, 0, 1]);
+        assert_eq!(
+            permuted,
+            arr![
+                arr![arr![0.0, 2.0, 4.0], arr![6.0, 8.0, 10.0]],
+                arr![arr![1.0, 3.0, 5.0], arr![7.0, 9.0, 11.0]]
+            ]
+        );
+
+        permuted.backward(Some(permuted.clone()));
+        assert_eq!(a.gradient().to_owned().unwrap(), a);
+    }
+
     #[test]
     fn test_axpy() {
         let a = arr![arr![1.0, 2.0, 3.0], arr![3.0, 2.0, 1.0]].tracked();
@@ -30,12 +30,14 @@ mod arithmetic;
 mod image;
 mod linalg;
 mod nonlinearity;
+mod npy;
 
 use crate::numbers::*;
 
 use approx::{AbsDiffEq, RelativeEq};
 
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::{From, Into};
 use std::fmt;
 
@@ -74,6 +76,7 @@ pub struct Array {
     values: Rc<Vec<Float>>,
     children: Rc<Vec<Array>>,
     backward_op: Option<BackwardOp>,
+    op_name: Option<&'static str>,
     is_tracked: Cell<bool>,
     keep_gradient: Cell<bool>,
     consumer_count: Rc<Cell<usize>>,
@@ -203,6 +206,7 @@ impl From<(Vec<usize>, Rc<Vec<Float>>)> for Array {
             values,
             children: Rc::new(Vec::new()),
             backward_op: None,
+            op_name: None,
             is_tracked: Cell::new(false),
             keep_gradient: Cell::new(false),
             consumer_count: Rc::new(Cell::new(0)),
@@ -380,9 +384,11 @@ impl Array {
         self.tracked()
     }
 
-    /// Sets the backward operation of the array for the backward pass.
-    fn with_backward_op(mut self, backward_op: BackwardOp) -> Array {
+    /// Sets the backward operation of the array for the backward pass, and the name of the operation, which labels
+    /// the array in the graph.
+    fn with_backward_op(mut self, op_name: &'static str, backward_op: BackwardOp) -> Array {
         self.backward_op = Some(backward_op);
+        self.op_name = Some(op_name);
         self
     }
 
@@ -400,6 +406,47 @@ impl Array {
         }
     }
 
+    /// Describes the graph of operations which computed the array in the Graphviz DOT format, where each array is
+    /// labelled with the name of its operation, its dimensions, whether it is tracked, and its consumer count, and
+    /// each edge points from an input of an operation to its output. Arrays which were not computed by a tracked
+    /// operation are labelled as leaves.
+    pub fn to_dot(&self) -> String {
+        // clones of an array share their gradient, which identifies the array in the graph
+        let id = |array: &Array| Rc::as_ptr(&array.gradient);
+        let mut ids = HashMap::new();
+        ids.insert(id(self), 0);
+
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        let mut stack = vec![self];
+        while let Some(array) = stack.pop() {
+            let index = ids[&id(array)];
+            nodes.push_str(&format!(
+                "    node{} [label=\"{}\\n{:?}\\n{}\\nconsumers: {}\"];\n",
+                index,
+                array.op_name.unwrap_or("leaf"),
+                array.dimensions,
+                if array.is_tracked.get() {
+                    "tracked"
+                } else {
+                    "untracked"
+                },
+                array.consumer_count.get()
+            ));
+
+            for child in array.children.iter() {
+                let next_index = ids.len();
+                let child_index = *ids.entry(id(child)).or_insert_with(|| {
+                    stack.push(child);
+                    next_index
+                });
+                edges.push_str(&format!("    node{} -> node{};\n", child_index, index));
+            }
+        }
+
+        format!("digraph {{\n    node [shape=box];\n{}{}}}\n", nodes, edges)
+    }
+
     /// Computes the backward pass, computing gradients for all descendants, and propagating consumer counts if requested.
     ///
     /// # Panics
@@ -440,9 +487,10 @@ impl Array {
                     if let Some(delta) = delta {
                         let child = &self.children[i];
                         {
+                            let delta = delta.flatten_to(&child.dimensions);
                             match child.delta.take() {
                                 Some(x) => child.delta.set(Some(&x + &delta)),
-                                None => child.delta.set(Some(delta.flatten_to(&child.dimensions))),
+                                None => child.delta.set(Some(delta)),
                             }
                         }
 
@@ -480,6 +528,7 @@ impl Array {
     ///
     /// # Arguments
     ///
+    /// * `op_name` - The name of the operation, which labels the output in the graph.
     /// * `arrays` - The arrays to perform the operations on.
     /// * `op` - The `SlicedOp`, which takes in slices of the arrays, and modifies the output slice.
     /// * `input_dimensions` - The target dimensions to broadcast inputs to.
@@ -490,7 +539,9 @@ impl Array {
     /// # Panics
     ///
     /// Panics if unable to broadcast the arrays to `input_dimensions`.
+    #[allow(clippy::too_many_arguments)]
     fn sliced_op(
+        op_name: &'static str,
         arrays: Vec<&Array>,
         op: &SlicedOp,
         backward_op: Option<BackwardOp>,
@@ -515,107 +566,53 @@ impl Array {
 
         // count of leading dimensions
         let leading_count = input_dimensions.len().saturating_sub(op_dimension_count);
-        let output_leading_count = output_dimensions.len().saturating_sub(op_dimension_count);
+        let leading_dimensions = &input_dimensions[0..leading_count];
 
-        let unbroadcasted_group_length: usize = input_dimensions
+        // the leading dimensions, and total length of the slices of each array
+        let array_groups: Vec<(&[usize], usize)> = arrays
             .iter()
-            .rev()
-            .take(op_dimension_count)
-            .product();
-        let mut slices: Vec<&[Float]> = Vec::with_capacity(arrays.len());
-        let mut broadcasted_slices: Vec<(&Array, usize, *mut &[Float])> = Vec::new();
-        let mut unbroadcasted_slices: Vec<(&Array, *mut &[Float])> = Vec::new();
-        for array in &arrays {
-            let index = slices.len();
-            // total length of the slices
-            if array.dimensions == input_dimensions {
-                let slice = &array.values[0..unbroadcasted_group_length];
-                slices.push(slice);
-                unbroadcasted_slices.push((array, &mut slices[index]));
-            } else {
-                let group_length = array
-                    .dimensions
-                    .iter()
-                    .rev()
-                    .take(op_dimension_count)
-                    .product();
-                let slice = &array.values[0..group_length];
-                slices.push(slice);
-                broadcasted_slices.push((array, group_length, &mut slices[index]));
-            }
-        }
+            .map(|array| {
+                let array_leading_count = array.dimensions.len().saturating_sub(op_dimension_count);
+                let group_length = array.dimensions[array_leading_count..].iter().product();
+                (&array.dimensions[0..array_leading_count], group_length)
+            })
+            .collect();
 
         // total length of the output
         let output_length = output_dimensions.iter().product();
-        let output_group_length: usize = output_dimensions.iter().skip(leading_count).product();
+        let output_leading_count = cmp::min(leading_count, output_dimensions.len());
+        let output_leading_dimensions = &output_dimensions[0..output_leading_count];
+        let output_group_length: usize = output_dimensions[output_leading_count..].iter().product();
         let mut output_values = vec![0.0; output_length];
-        let mut output_slice = &mut output_values[0..output_group_length];
-
-        if leading_count == 0 {
-            op(output_slice, &slices);
-        } else {
-            // total length of the leading values
-            let leading_length = input_dimensions
-                .iter()
-                .rev()
-                .skip(op_dimension_count)
-                .product();
 
-            let mut indices = vec![0; cmp::max(input_dimensions.len(), output_dimensions.len())];
-            let mut flat_unbroadcasted_index = 0;
-            let mut flat_broadcasted_indices = vec![0; broadcasted_slices.len()];
-            let mut output_offset = 0;
-            for _ in 0..leading_length {
-                op(output_slice, &slices);
-
-                for (i, (x, d)) in indices
-                    .iter_mut()
-                    .zip(input_dimensions)
-                    .enumerate()
-                    .rev()
-                    .skip(op_dimension_count)
-                {
-                    // increment the first dimension that is not about to overflow
-                    if *x == *d - 1 {
-                        *x = 0;
-                    } else {
-                        // do not increment the index if we have broadcasted by extending the array's dimensions (if the current dimension is greater than the array's dimensions)
-                        // or if the dimension is broadcasted from 1
-                        for (index, &(array, group_length, slice)) in
-                            flat_broadcasted_indices.iter_mut().zip(&broadcasted_slices)
-                        {
-                            if i < array.dimensions.len().saturating_sub(op_dimension_count)
-                                && array.dimensions[i] != 1
-                            {
-                                *index += group_length;
-                                unsafe {
-                                    // we may use unsafe code, since we are just modifying slices
-                                    *slice = &array.values[*index..*index + group_length];
-                                }
-                            }
-                        }
-
-                        if i < leading_count && input_dimensions[i] != 1 {
-                            flat_unbroadcasted_index += unbroadcasted_group_length;
-                            for &(array, slice) in &unbroadcasted_slices {
-                                unsafe {
-                                    // we may use unsafe code, since we are just modifying slices
-                                    *slice = &array.values[flat_unbroadcasted_index
-                                        ..flat_unbroadcasted_index + unbroadcasted_group_length];
-                                }
-                            }
-                        }
+        // total length of the leading values
+        let leading_length: usize = leading_dimensions.iter().product();
 
-                        if i < output_leading_count && output_dimensions[i] != 1
-                        {
-                            output_offset += output_group_length;
-                            output_slice = &mut output_values
-                                [output_offset..output_offset + output_group_length];
-                        }
+        let mut indices = vec![0; leading_count];
+        let mut slices: Vec<&[Float]> = Vec::with_capacity(arrays.len());
+        for _ in 0..leading_length {
+            slices.clear();
+            for (array, &(array_leading_dimensions, group_length)) in
+                arrays.iter().zip(&array_groups)
+            {
+                let offset = broadcast_offset(&indices, array_leading_dimensions) * group_length;
+                slices.push(&array.values[offset..offset + group_length]);
+            }
 
-                        *x += 1;
-                        break;
-                    }
+            let output_offset =
+                broadcast_offset(&indices, output_leading_dimensions) * output_group_length;
+            op(
+                &mut output_values[output_offset..output_offset + output_group_length],
+                &slices,
+            );
+
+            // increment the last index that is not about to overflow, and reset the indices after it
+            for (x, d) in indices.iter_mut().zip(leading_dimensions).rev() {
+                if *x == *d - 1 {
+                    *x = 0;
+                } else {
+                    *x += 1;
+                    break;
                 }
             }
         }
@@ -636,7 +633,7 @@ impl Array {
         if let Some(backward_op) = backward_op {
             result
                 .with_children(arrays.into_iter().cloned().collect())
-                .with_backward_op(backward_op)
+                .with_backward_op(op_name, backward_op)
         } else {
             result
         }
@@ -647,24 +644,22 @@ impl Array {
         if self.dimensions == dimensions {
             self
         } else {
-            let flatten_dimension_count = self.dimensions.len().saturating_sub(dimensions.len());
+            let mut values = vec![0.0; dimensions.iter().product()];
+            let mut indices = vec![0; self.dimensions.len()];
+            for value in self.values.iter() {
+                values[broadcast_offset(&indices, dimensions)] += value;
 
-            let op: SlicedOp = Box::new(move |output_slice, arrays| {
-                let stride = output_slice.len();
-                for (i, output) in output_slice.iter_mut().enumerate() {
-                    *output += arrays[0].iter().skip(i).step_by(stride).sum::<Float>();
+                for (x, d) in indices.iter_mut().zip(&self.dimensions).rev() {
+                    if *x == *d - 1 {
+                        *x = 0;
+                    } else {
+                        *x += 1;
+                        break;
+                    }
                 }
-            });
-
-            Array::sliced_op(
-                vec![&self],
-                &op,
-                None,
-                &self.dimensions,
-                &*dimensions,
-                flatten_dimension_count + 1,
-                0,
-            )
+            }
+
+            Array::from((dimensions.to_vec(), values))
         }
     }
 
@@ -718,11 +713,22 @@ impl Array {
     /// # }
     /// ```
     pub fn op(arrays: &[&Array], op: ForwardOp, backward_op: Option<BackwardOp>) -> Array {
+        Array::named_op("op", arrays, op, backward_op)
+    }
+
+    /// Computes an operation on arrays, like `op`, where the output is labelled with the name of the operation in
+    /// the graph.
+    pub fn named_op(
+        op_name: &'static str,
+        arrays: &[&Array],
+        op: ForwardOp,
+        backward_op: Option<BackwardOp>,
+    ) -> Array {
         let result = op(arrays);
         if let Some(backward_op) = backward_op {
             result
                 .with_children(arrays.iter().map(|v| (*v).clone()).collect())
-                .with_backward_op(backward_op)
+                .with_backward_op(op_name, backward_op)
         } else {
             result
         }
@@ -741,6 +747,7 @@ impl Clone for Array {
             values: Rc::clone(&self.values),
             children: Rc::clone(&self.children),
             backward_op,
+            op_name: self.op_name,
             is_tracked: Cell::new(self.is_tracked.get()),
             keep_gradient: Cell::new(self.keep_gradient.get()),
             consumer_count: Rc::clone(&self.consumer_count),
@@ -827,6 +834,16 @@ impl Index<Vec<usize>> for Array {
     }
 }
 
+/// Converts indices to a single flattened index for an array which is broadcast to the dimensions of the indices,
+/// by aligning the dimensions to the right, and repeating dimensions of length 1.
+fn broadcast_offset(indices: &[usize], dimensions: &[usize]) -> usize {
+    let length = cmp::min(indices.len(), dimensions.len());
+    indices[indices.len() - length..]
+        .iter()
+        .zip(&dimensions[dimensions.len() - length..])
+        .fold(0, |acc, (&i, &d)| acc * d + if d == 1 { 0 } else { i })
+}
+
 /// Converts indices by dimension to a single flattened index.
 fn flatten_indices(indices: &[usize], dimensions: &[usize]) -> usize {
     // dimensions will always have at least one element
@@ -960,7 +977,7 @@ mod tests {
             output_slice[0] = arrays[0][0] + arrays[0][1];
         });
 
-        Array::sliced_op(vec![&a], &op, None, &[2], &[1], 0, 0);
+        Array::sliced_op("sum", vec![&a], &op, None, &[2], &[1], 0, 0);
     }
 
     #[test]
@@ -979,6 +996,48 @@ mod tests {
         assert_eq!(result, arr![22.0, 26.0, 30.0]);
     }
 
+    #[test]
+    fn test_flatten_to_broadcast() {
+        let a = Array::from((
+            vec![2, 3, 2],
+            (0..12).map(|x| x as Float).collect::<Vec<Float>>(),
+        ));
+
+        // the dimensions are aligned to the right, so dimensions of length 1 are summed
+        let result = a.flatten_to(&[3, 1]);
+        assert_eq!(result, Array::from((vec![3, 1], vec![14.0, 22.0, 30.0])));
+    }
+
+    #[test]
+    fn test_broadcast_inner() {
+        let a = Array::from((vec![2, 1, 3], vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0])).tracked();
+        let b = Array::from((
+            vec![4, 3],
+            (0..12).map(|x| 10.0 * x as Float).collect::<Vec<Float>>(),
+        ))
+        .tracked();
+
+        let result = &a + &b;
+        let expected: Vec<Float> = (0..2)
+            .flat_map(|i| {
+                (0..4).flat_map(move |j| {
+                    (0..3).map(move |k| (3 * i + k) as Float + 10.0 * (3 * j + k) as Float)
+                })
+            })
+            .collect();
+        assert_eq!(result, Array::from((vec![2, 4, 3], expected)));
+
+        result.backward(None);
+        assert_eq!(
+            a.gradient().to_owned().unwrap(),
+            Array::from((vec![2, 1, 3], vec![4.0; 6]))
+        );
+        assert_eq!(
+            b.gradient().to_owned().unwrap(),
+            Array::from((vec![4, 3], vec![2.0; 12]))
+        );
+    }
+
     #[test]
     fn test_backward_op() {
         let a = arr![5.0].tracked();
@@ -1193,4 +1252,24 @@ mod tests {
         assert!(b.gradient().is_none());
         assert!(a.gradient().is_some());
     }
+
+    #[test]
+    fn test_to_dot() {
+        let a = arr![1.0, -2.0].tracked();
+        let b = arr![3.0, 4.0];
+        let product = &a * &b;
+        let result = (&product + &a).relu();
+
+        let dot = result.to_dot();
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("node0 [label=\"relu\\n[2]\\ntracked\\nconsumers: 0\"];"));
+        assert!(dot.contains("[label=\"leaf\\n[2]\\nuntracked\\nconsumers: 0\"];"));
+        for name in &["add", "mul", "leaf\\n[2]\\ntracked"] {
+            assert!(dot.contains(&format!("[label=\"{}", name)), "{}", dot);
+        }
+
+        // the shared input is a single node, with an edge to each of its consumers
+        assert_eq!(dot.matches("[label=").count(), 5);
+        assert_eq!(dot.matches(" -> ").count(), 5);
+    }
 }
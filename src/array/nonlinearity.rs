@@ -26,7 +26,7 @@ impl Array {
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("relu", backward_op)
         }
     }
 
@@ -55,7 +55,59 @@ impl Array {
 
             result
                 .with_children(vec![self.clone()])
-                .with_backward_op(backward_op)
+                .with_backward_op("sigmoid", backward_op)
+        }
+    }
+
+    /// Computes the hyperbolic tangent of each value of the array.
+    pub fn tanh(&self) -> Array {
+        let values = Rc::new(self.values.iter().map(|x| x.tanh()).collect::<Vec<Float>>());
+
+        let cached = Rc::clone(&values);
+        let result = Array::from((self.dimensions.clone(), values));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                let values = arithmetic::mul_values(
+                    &cached.iter().map(|v| 1.0 - v * v).collect::<Vec<Float>>(),
+                    &x.values,
+                );
+                vec![Some(Array::from((c[0].dimensions.clone(), values)))]
+            });
+
+            result
+                .with_children(vec![self.clone()])
+                .with_backward_op("tanh", backward_op)
+        }
+    }
+
+    /// Computes the softplus of the array, defined as ln(1 + exp(x)), and computed without overflow for large x.
+    pub fn softplus(&self) -> Array {
+        let values: Vec<Float> = self
+            .values
+            .iter()
+            .map(|&x| x.max(0.0) + (-x.abs()).exp().ln_1p())
+            .collect();
+
+        let result = Array::from((self.dimensions.clone(), values));
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(|c, _, x| {
+                let values: Vec<Float> = c[0]
+                    .values
+                    .iter()
+                    .map(|x| 1.0 / (1.0 + (-x).exp()))
+                    .collect();
+                let derivative = Array::from((c[0].dimensions.clone(), values));
+                vec![Some(&derivative * x)]
+            });
+
+            result
+                .with_children(vec![self.clone()])
+                .with_backward_op("softplus", backward_op)
         }
     }
 
@@ -64,6 +116,47 @@ impl Array {
         let exponentials = self.exp();
         &exponentials / &exponentials.sum(1)
     }
+
+    /// Computes the logarithm of the softmax of the array along the last dimension, which is numerically stable for
+    /// values of any magnitude.
+    pub fn log_softmax(&self) -> Array {
+        let length = *self.dimensions.last().unwrap();
+        let values: Vec<Float> = self
+            .values
+            .chunks(length)
+            .flat_map(|row| {
+                let max = row.iter().copied().fold(Float::NEG_INFINITY, Float::max);
+                let log_sum = max + row.iter().map(|x| (x - max).exp()).sum::<Float>().ln();
+                row.iter().map(move |x| x - log_sum)
+            })
+            .collect();
+
+        let cached = Rc::new(values);
+        let result = Array::from((self.dimensions.clone(), Rc::clone(&cached)));
+
+        if !self.is_tracked.get() {
+            result
+        } else {
+            let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+                // the delta minus the softmax scaled by the sum of the delta
+                let values: Vec<Float> = cached
+                    .chunks(length)
+                    .zip(x.values.chunks(length))
+                    .flat_map(|(row, delta)| {
+                        let delta_sum: Float = delta.iter().sum();
+                        row.iter()
+                            .zip(delta)
+                            .map(move |(v, d)| d - v.exp() * delta_sum)
+                    })
+                    .collect();
+                vec![Some(Array::from((c[0].dimensions.clone(), values)))]
+            });
+
+            result
+                .with_children(vec![self.clone()])
+                .with_backward_op("log_softmax", backward_op)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -99,6 +192,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_log_softmax() {
+        let a = arr![arr![1.0, 2.0, 3.0], arr![1000.0, 0.0, -1000.0]].tracked();
+        let b = arr![arr![3.0, 5.0, 1.0], arr![2.0, 5.0, 4.0]];
+
+        let result = a.log_softmax();
+        assert_relative_eq!(
+            arr![result[0], result[1], result[2]],
+            arr![1.0, 2.0, 3.0].softmax().ln(),
+            max_relative = 1e-6
+        );
+        assert_relative_eq!(arr![result[3], result[4]], arr![0.0, -1000.0]);
+
+        (&result * &b).backward(None);
+        let softmax = arr![1.0, 2.0, 3.0].softmax();
+        let gradient = a.gradient().to_owned().unwrap();
+        assert_relative_eq!(
+            arr![gradient[0], gradient[1], gradient[2]],
+            &arr![3.0, 5.0, 1.0] - &(9.0 * &softmax),
+            max_relative = 1e-6
+        );
+        assert_relative_eq!(arr![gradient[3], gradient[4]], arr![-9.0, 5.0]);
+    }
+
     #[test]
     fn test_relu() {
         let a = arr![1.0, -2.0, 0.0].tracked();
@@ -110,6 +227,28 @@ mod tests {
         assert_eq!(a.gradient().to_owned().unwrap(), arr![1.0, 0.0, 0.0]);
     }
 
+    #[test]
+    fn test_tanh() {
+        let a = arr![0.0, (3.0 as Float).ln()].tracked();
+
+        let result = a.tanh();
+        assert_relative_eq!(result, arr![0.0, 0.8]);
+
+        result.backward(None);
+        assert_relative_eq!(a.gradient().to_owned().unwrap(), arr![1.0, 0.36]);
+    }
+
+    #[test]
+    fn test_softplus() {
+        let a = arr![0.0, 1000.0, -1000.0].tracked();
+
+        let result = a.softplus();
+        assert_relative_eq!(result, arr![(2.0 as Float).ln(), 1000.0, 0.0]);
+
+        result.backward(None);
+        assert_relative_eq!(a.gradient().to_owned().unwrap(), arr![0.5, 1.0, 0.0]);
+    }
+
     #[test]
     fn test_sigmoid() {
         let a = arr![arr![(3.0 as Float).ln()]].tracked();
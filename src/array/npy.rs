@@ -0,0 +1,501 @@
+//! Reading, and writing of arrays in the NumPy `.npy` format, and of `.npz` archives of named arrays.
+//!
+//! An `.npy` file consists of the magic bytes `\x93NUMPY`, the major, and minor format version, the length of the
+//! header as a little-endian `u16` for version 1, or `u32` for versions 2, and 3, and the header, which is a Python
+//! dictionary literal with the keys `descr`, `fortran_order`, and `shape`, padded with spaces, and a newline. The C-order
+//! values follow the header.
+//!
+//! The `<f4`, and `<f8` dtypes are supported, and are converted to `Float`. Arrays are written with the dtype of
+//! `Float`, using version 1 of the format unless the header is too long.
+
+use crate::array::*;
+use crate::numbers::*;
+use crate::serialize::invalid_data;
+use crate::zip;
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, Read, Result, Write};
+use std::path::Path;
+
+/// The bytes at the start of every `.npy` file.
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The alignment of the total length of the magic bytes, version, header length, and header.
+const HEADER_ALIGNMENT: usize = 64;
+
+/// A value of the header dictionary.
+#[derive(Debug, PartialEq)]
+enum HeaderValue {
+    Text(String),
+    Boolean(bool),
+    Shape(Vec<usize>),
+}
+
+/// A parser of the Python dictionary literal of the header.
+struct HeaderParser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl<'a> HeaderParser<'a> {
+    fn error(&self, expected: &str) -> Error {
+        invalid_data(format!(
+            "the npy header is malformed at position {}, expected {}",
+            self.position, expected
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.text.get(self.position), Some(c) if c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes a character after any whitespace, returning whether it was present.
+    fn consume(&mut self, character: u8) -> bool {
+        self.skip_whitespace();
+        let is_present = self.text.get(self.position) == Some(&character);
+        if is_present {
+            self.position += 1;
+        }
+
+        is_present
+    }
+
+    fn expect(&mut self, character: u8) -> Result<()> {
+        if self.consume(character) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}'", character as char)))
+        }
+    }
+
+    fn parse_text(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let quote = match self.text.get(self.position) {
+            Some(&q) if q == b'\'' || q == b'"' => q,
+            _ => return Err(self.error("a string")),
+        };
+
+        let start = self.position + 1;
+        let length = self.text[start..]
+            .iter()
+            .position(|&c| c == quote)
+            .ok_or_else(|| self.error("the end of a string"))?;
+        self.position = start + length + 1;
+        Ok(String::from_utf8_lossy(&self.text[start..start + length]).into_owned())
+    }
+
+    fn parse_integer(&mut self) -> Result<usize> {
+        self.skip_whitespace();
+        let start = self.position;
+        while matches!(self.text.get(self.position), Some(c) if c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        let digits = std::str::from_utf8(&self.text[start..self.position]).unwrap();
+        let value = digits.parse().map_err(|_| self.error("an integer"))?;
+
+        // Python 2 wrote long integers with a suffix
+        self.consume(b'L');
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<HeaderValue> {
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+        if rest.starts_with(b"True") || rest.starts_with(b"False") {
+            let value = rest.starts_with(b"True");
+            self.position += if value { 4 } else { 5 };
+            Ok(HeaderValue::Boolean(value))
+        } else if self.consume(b'(') {
+            let mut shape = Vec::new();
+            while !self.consume(b')') {
+                shape.push(self.parse_integer()?);
+                if !self.consume(b',') {
+                    self.expect(b')')?;
+                    break;
+                }
+            }
+
+            Ok(HeaderValue::Shape(shape))
+        } else {
+            Ok(HeaderValue::Text(self.parse_text()?))
+        }
+    }
+
+    fn parse_dictionary(&mut self) -> Result<Vec<(String, HeaderValue)>> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        while !self.consume(b'}') {
+            let key = self.parse_text()?;
+            self.expect(b':')?;
+            entries.push((key, self.parse_value()?));
+            if !self.consume(b',') {
+                self.expect(b'}')?;
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Parses the header, returning the width of the values in bytes, and the dimensions.
+fn parse_header(header: &[u8]) -> Result<(usize, Vec<usize>)> {
+    let mut parser = HeaderParser {
+        text: header,
+        position: 0,
+    };
+    let entries = parser.parse_dictionary()?;
+
+    let (mut descr, mut fortran_order, mut shape) = (None, None, None);
+    for (key, value) in entries {
+        match (key.as_str(), value) {
+            ("descr", HeaderValue::Text(d)) => descr = Some(d),
+            ("fortran_order", HeaderValue::Boolean(f)) => fortran_order = Some(f),
+            ("shape", HeaderValue::Shape(s)) => shape = Some(s),
+            (key, value) => {
+                return Err(invalid_data(format!(
+                    "the npy header has an unexpected entry {}: {:?}",
+                    key, value
+                )))
+            }
+        }
+    }
+
+    let (descr, fortran_order, shape) = match (descr, fortran_order, shape) {
+        (Some(d), Some(f), Some(s)) => (d, f, s),
+        _ => {
+            return Err(invalid_data(
+                "the npy header must have the keys descr, fortran_order, and shape".to_string(),
+            ))
+        }
+    };
+
+    let width = match descr.as_str() {
+        "<f4" => 4,
+        "<f8" => 8,
+        _ => {
+            return Err(invalid_data(format!(
+                "the npy dtype {} is not supported, expected <f4, or <f8",
+                descr
+            )))
+        }
+    };
+
+    if fortran_order {
+        return Err(invalid_data(
+            "Fortran-order npy arrays are not supported".to_string(),
+        ));
+    }
+
+    if shape.contains(&0) {
+        return Err(invalid_data(format!(
+            "the npy shape {:?} must not have empty dimensions",
+            shape
+        )));
+    }
+
+    Ok((width, shape))
+}
+
+/// Formats the dimensions as a Python tuple, which has a trailing comma if it has one element.
+fn format_shape(dimensions: &[usize]) -> String {
+    match dimensions {
+        [dimension] => format!("({},)", dimension),
+        _ => format!(
+            "({})",
+            dimensions
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+impl Array {
+    /// Reads an array in the `.npy` format.
+    pub fn read_npy_from(reader: &mut impl Read) -> Result<Array> {
+        let mut preamble = [0; 8];
+        reader.read_exact(&mut preamble)?;
+        if &preamble[0..6] != MAGIC {
+            return Err(invalid_data("the data is not an npy file".to_string()));
+        }
+
+        let header_length = match preamble[6] {
+            1 => {
+                let mut length = [0; 2];
+                reader.read_exact(&mut length)?;
+                u16::from_le_bytes(length) as usize
+            }
+            2 | 3 => {
+                let mut length = [0; 4];
+                reader.read_exact(&mut length)?;
+                u32::from_le_bytes(length) as usize
+            }
+            version => {
+                return Err(invalid_data(format!(
+                    "the npy format version {} is not supported",
+                    version
+                )))
+            }
+        };
+
+        let mut header = vec![0; header_length];
+        reader.read_exact(&mut header)?;
+        let (width, dimensions) = parse_header(&header)?;
+
+        let length = dimensions
+            .iter()
+            .try_fold(width, |length, &d| length.checked_mul(d))
+            .ok_or_else(|| {
+                invalid_data(format!("the npy dimensions {:?} are too large", dimensions))
+            })?;
+        // the values are read as they arrive, rather than allocated up-front, as the header may be corrupt
+        let mut bytes = Vec::new();
+        reader
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(invalid_data("the npy values are truncated".to_string()));
+        }
+        let values: Vec<Float> = if width == 4 {
+            bytes
+                .chunks(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Float)
+                .collect()
+        } else {
+            bytes
+                .chunks(8)
+                .map(|b| {
+                    let mut value = [0; 8];
+                    value.copy_from_slice(b);
+                    f64::from_le_bytes(value) as Float
+                })
+                .collect()
+        };
+
+        Ok(Array::from((dimensions, values)))
+    }
+
+    /// Writes the array in the `.npy` format.
+    pub fn write_npy_to(&self, writer: &mut impl Write) -> Result<()> {
+        let descr = if std::mem::size_of::<Float>() == 4 {
+            "<f4"
+        } else {
+            "<f8"
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr,
+            format_shape(&self.dimensions)
+        );
+
+        // the header is padded, so that the values are aligned
+        let is_long = header.len() + 11 > u16::MAX as usize;
+        let preamble_length = if is_long { 12 } else { 10 };
+        let padding = (HEADER_ALIGNMENT - (preamble_length + header.len() + 1) % HEADER_ALIGNMENT)
+            % HEADER_ALIGNMENT;
+        header += &" ".repeat(padding);
+        header.push('\n');
+
+        writer.write_all(MAGIC)?;
+        if is_long {
+            writer.write_all(&[2, 0])?;
+            writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        } else {
+            writer.write_all(&[1, 0])?;
+            writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        }
+        writer.write_all(header.as_bytes())?;
+
+        for value in self.values.iter() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads an array from an `.npy` file.
+    pub fn read_npy(path: impl AsRef<Path>) -> Result<Array> {
+        Array::read_npy_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the array to an `.npy` file.
+    pub fn write_npy(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_npy_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads the named arrays of an `.npz` archive, which may be compressed. The `.npy` extension of each name is
+    /// removed.
+    pub fn read_npz(path: impl AsRef<Path>) -> Result<Vec<(String, Array)>> {
+        zip::read_archive(&fs::read(path)?)?
+            .into_iter()
+            .map(|(name, contents)| {
+                let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+                Ok((name, Array::read_npy_from(&mut contents.as_slice())?))
+            })
+            .collect()
+    }
+
+    /// Writes named arrays to an uncompressed `.npz` archive, which may be loaded by `numpy.load`. Archives of at
+    /// least 4 GiB, or 65535 arrays, are not supported, and return an error.
+    pub fn write_npz(path: impl AsRef<Path>, arrays: &[(&str, &Array)]) -> Result<()> {
+        let entries = arrays
+            .iter()
+            .map(|(name, array)| {
+                let mut contents = Vec::new();
+                array.write_npy_to(&mut contents)?;
+                Ok((format!("{}.npy", name), contents))
+            })
+            .collect::<Result<Vec<(String, Vec<u8>)>>>()?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        zip::write_archive(&mut writer, &entries)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arr;
+
+    /// Constructs an `.npy` file, as written by `numpy.save`.
+    fn fixture(header: &str, values: &[u8]) -> Vec<u8> {
+        let padding = 63 - (10 + header.len()) % 64;
+        let header = format!("{}{}\n", header, " ".repeat(padding));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(values);
+        bytes
+    }
+
+    #[test]
+    fn test_read() {
+        let values: Vec<u8> = [1.0f64, 2.0, 3.0, 4.0, 5.0, 6.5]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let bytes = fixture(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }",
+            &values,
+        );
+        assert_eq!(bytes.len() % 64, 48);
+        assert_eq!(
+            Array::read_npy_from(&mut bytes.as_slice()).unwrap(),
+            arr![arr![1.0, 2.0, 3.0], arr![4.0, 5.0, 6.5]]
+        );
+
+        let values: Vec<u8> = [0.5f32, -1.25]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let bytes = fixture(
+            "{\"shape\": (2L,), \"fortran_order\": False, \"descr\": \"<f4\"}",
+            &values,
+        );
+        assert_eq!(
+            Array::read_npy_from(&mut bytes.as_slice()).unwrap(),
+            arr![0.5, -1.25]
+        );
+
+        let bytes = fixture(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (), }",
+            &3.0f64.to_le_bytes(),
+        );
+        let scalar = Array::read_npy_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(scalar.dimensions(), &[] as &[usize]);
+        assert_eq!(scalar.values(), &[3.0]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let headers = [
+            "{'descr': '>f8', 'fortran_order': False, 'shape': (1,), }",
+            "{'descr': '<i8', 'fortran_order': False, 'shape': (1,), }",
+            "{'descr': '<f8', 'fortran_order': True, 'shape': (1,), }",
+            "{'descr': '<f8', 'shape': (1,), }",
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (0,), }",
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (1,) ",
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (4294967296, 4294967296), }",
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (1000000000000,), }",
+        ];
+        for header in headers.iter() {
+            let bytes = fixture(header, &[0; 8]);
+            assert!(Array::read_npy_from(&mut bytes.as_slice()).is_err());
+        }
+
+        // the values are truncated
+        let bytes = fixture(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }",
+            &[0; 8],
+        );
+        assert!(Array::read_npy_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let array = arr![arr![1.0, -2.0], arr![0.25, 8.0], arr![3.0, 4.0]];
+        let mut bytes = Vec::new();
+        array.write_npy_to(&mut bytes).unwrap();
+
+        let header_length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_length) % 64, 0);
+        let descr = if std::mem::size_of::<Float>() == 4 {
+            "<f4"
+        } else {
+            "<f8"
+        };
+        assert!(
+            String::from_utf8_lossy(&bytes[10..10 + header_length]).starts_with(&format!(
+                "{{'descr': '{}', 'fortran_order': False, 'shape': (3, 2), }}",
+                descr
+            ))
+        );
+        assert_eq!(Array::read_npy_from(&mut bytes.as_slice()).unwrap(), array);
+
+        let mut bytes = Vec::new();
+        arr![1.0].write_npy_to(&mut bytes).unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("'shape': (1,)"));
+    }
+
+    #[test]
+    fn test_npz() {
+        let path = std::env::temp_dir().join(format!("corgi_npz_{}.npz", std::process::id()));
+        let (a, b) = (arr![arr![1.0, 2.0]], arr![3.0, 4.0, 5.0]);
+        Array::write_npz(&path, &[("a", &a), ("b", &b)]).unwrap();
+        assert_eq!(
+            Array::read_npz(&path).unwrap(),
+            vec![("a".to_string(), a), ("b".to_string(), b)]
+        );
+
+        // an archive written by numpy.savez_compressed, containing an <f4 array named w
+        let compressed: [u8; 192] = [
+            80, 75, 3, 4, 20, 0, 0, 0, 8, 0, 2, 125, 82, 93, 55, 228, 66, 73, 84, 0, 0, 0, 144, 0,
+            0, 0, 5, 0, 0, 0, 119, 46, 110, 112, 121, 155, 236, 23, 234, 27, 16, 201, 200, 80, 198,
+            80, 173, 158, 146, 90, 156, 92, 164, 110, 165, 160, 110, 147, 102, 162, 174, 163, 160,
+            158, 150, 95, 84, 82, 148, 152, 23, 159, 95, 148, 146, 10, 18, 119, 75, 204, 41, 78, 5,
+            138, 23, 103, 36, 22, 164, 2, 249, 26, 70, 58, 10, 70, 154, 58, 10, 181, 10, 100, 3,
+            46, 6, 134, 3, 246, 12, 64, 130, 129, 161, 193, 14, 72, 59, 2, 0, 80, 75, 1, 2, 20, 3,
+            20, 0, 0, 0, 8, 0, 2, 125, 82, 93, 55, 228, 66, 73, 84, 0, 0, 0, 144, 0, 0, 0, 5, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 1, 0, 0, 0, 0, 119, 46, 110, 112, 121, 80, 75, 5, 6, 0,
+            0, 0, 0, 1, 0, 1, 0, 51, 0, 0, 0, 119, 0, 0, 0, 0, 0,
+        ];
+        fs::write(&path, &compressed[..]).unwrap();
+        assert_eq!(
+            Array::read_npz(&path).unwrap(),
+            vec![("w".to_string(), arr![arr![1.5, -2.0], arr![0.25, 8.0]])]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
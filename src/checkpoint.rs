@@ -0,0 +1,320 @@
+//! Checkpoints of the full training state of a model, which allow an interrupted run to be resumed exactly.
+//!
+//! Besides the parameters, and buffers of the model, a checkpoint stores the epoch, and step counters, which are the
+//! position of any learning rate schedule, the state of the optimizer, and the states of the random number generator
+//! of the thread, and of every layer.
+//!
+//! All integers are little-endian. The format consists of:
+//!
+//! * the magic bytes `CRGC`
+//! * the format version, as a `u32`
+//! * the epoch, and step counters, as `u64` values
+//! * the state of the generator of the thread
+//! * the number of layer generators, as a `u64`, followed by the state of each generator
+//! * the parameters, and buffers of the model, in the format of `serialize`
+//! * the state of the optimizer, in the format of `serialize`
+//!
+//! The state of a generator is its 32-byte key, its stream as a `u64`, and its word position as a `u128`.
+
+use crate::array::*;
+use crate::model::Model;
+use crate::numbers::*;
+use crate::random::{self, GeneratorState};
+use crate::serialize::{self, invalid_data, read_u64, write_u64};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Result, Write};
+use std::path::Path;
+
+/// The bytes at the start of every checkpoint file.
+const MAGIC: &[u8; 4] = b"CRGC";
+
+/// The version of the format, which is incremented when the format changes.
+pub const VERSION: u32 = 1;
+
+/// A model, with the counters of its training progress.
+pub struct Checkpoint {
+    model: Model,
+    epoch: usize,
+    step: usize,
+}
+
+fn write_generator(writer: &mut impl Write, state: &GeneratorState) -> Result<()> {
+    writer.write_all(&state.seed)?;
+    writer.write_all(&state.stream.to_le_bytes())?;
+    writer.write_all(&state.word_position.to_le_bytes())
+}
+
+fn read_generator(reader: &mut impl Read) -> Result<GeneratorState> {
+    let mut seed = [0; 32];
+    reader.read_exact(&mut seed)?;
+    let mut stream = [0; 8];
+    reader.read_exact(&mut stream)?;
+    let mut word_position = [0; 16];
+    reader.read_exact(&mut word_position)?;
+
+    Ok(GeneratorState {
+        seed,
+        stream: u64::from_le_bytes(stream),
+        word_position: u128::from_le_bytes(word_position),
+    })
+}
+
+impl Checkpoint {
+    /// Constructs a new checkpoint of a model, which has not started training.
+    pub fn new(model: Model) -> Checkpoint {
+        Checkpoint {
+            model,
+            epoch: 0,
+            step: 0,
+        }
+    }
+
+    /// Retrieves the model.
+    pub fn model(&mut self) -> &mut Model {
+        &mut self.model
+    }
+
+    /// Consumes the checkpoint, returning the model.
+    pub fn into_model(self) -> Model {
+        self.model
+    }
+
+    /// Retrieves the number of completed epochs.
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Retrieves the number of completed steps, which is not reset between epochs.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Trains the model on a batch, by computing the forward, and backward passes, and updating the parameters.
+    /// Returns the loss of the batch.
+    pub fn train_step(&mut self, input: Array, target: Array) -> Float {
+        self.model.forward(input);
+        let loss = self.model.backward(target);
+        self.model.update();
+        self.step += 1;
+
+        loss
+    }
+
+    /// Marks the end of an epoch.
+    pub fn end_epoch(&mut self) {
+        self.epoch += 1;
+    }
+
+    /// Writes the full training state.
+    pub fn write(&mut self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_u64(writer, self.epoch)?;
+        write_u64(writer, self.step)?;
+
+        write_generator(writer, &random::thread_state())?;
+        let generators = self.model.generators();
+        write_u64(writer, generators.len())?;
+        for generator in generators {
+            write_generator(writer, &GeneratorState::capture(generator))?;
+        }
+
+        let arrays = self.model.arrays();
+        serialize::write_arrays(writer, &arrays.iter().collect::<Vec<&Array>>())?;
+        let state = self.model.optimizer().state();
+        serialize::write_arrays(writer, &state.iter().collect::<Vec<&Array>>())
+    }
+
+    /// Reads the full training state, which must have been written by a checkpoint of a model with the same
+    /// architecture, and optimizer. The checkpoint is unchanged if an error is returned.
+    pub fn read(&mut self, reader: &mut impl Read) -> Result<()> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data(
+                "the data is not a checkpoint file".to_string(),
+            ));
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "the checkpoint version {} is not supported, expected {}",
+                version, VERSION
+            )));
+        }
+
+        let epoch = read_u64(reader)?;
+        let step = read_u64(reader)?;
+
+        let thread_state = read_generator(reader)?;
+        let generator_count = read_u64(reader)?;
+        if generator_count != self.model.generators().len() {
+            return Err(invalid_data(format!(
+                "the checkpoint contains {} generators, but the model has {}",
+                generator_count,
+                self.model.generators().len()
+            )));
+        }
+        let generator_states = (0..generator_count)
+            .map(|_| read_generator(reader))
+            .collect::<Result<Vec<GeneratorState>>>()?;
+
+        let arrays = serialize::read_arrays(reader)?;
+        let optimizer_state = serialize::read_arrays(reader)?;
+
+        // the arrays are checked before the optimizer state is restored, so that neither is changed on an error
+        self.model.check_arrays(&arrays)?;
+        let parameter_dimensions: Vec<Vec<usize>> = self
+            .model
+            .parameters()
+            .iter()
+            .map(|p| p.dimensions().to_vec())
+            .collect();
+        self.model
+            .optimizer()
+            .set_state(optimizer_state, &parameter_dimensions)?;
+        self.model.set_arrays(arrays)?;
+        for (generator, state) in self.model.generators().into_iter().zip(&generator_states) {
+            *generator = state.restore();
+        }
+        random::set_thread_state(&thread_state);
+        self.epoch = epoch;
+        self.step = step;
+
+        Ok(())
+    }
+
+    /// Saves the full training state to a file.
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Loads the full training state from a file, into a checkpoint of a model with the same architecture, and
+    /// optimizer.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.read(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::dense::Dense;
+    use crate::layer::dropout::Dropout;
+    use crate::optimizer::gd::GradientDescent;
+    use crate::optimizer::momentum::Momentum;
+    use crate::optimizer::Optimizer;
+    use crate::{activation, cost, initializer};
+
+    /// Constructs a model with dropout, whose generator is derived from the generator of the thread.
+    fn model() -> Model {
+        model_with(Box::new(GradientDescent::new(0.05)))
+    }
+
+    fn model_with(optimizer: Box<dyn Optimizer>) -> Model {
+        let initializer = initializer::he();
+        Model::new(
+            vec![
+                Box::new(Dense::new(3, 8, &initializer, Some(activation::relu()))),
+                Box::new(Dropout::new(0.25, None)),
+                Box::new(Dense::new(8, 2, &initializer, None)),
+            ],
+            optimizer,
+            cost::mse(cost::Reduction::Mean),
+        )
+    }
+
+    /// Trains for a number of steps, on batches which depend on the step.
+    fn train(checkpoint: &mut Checkpoint, steps: usize) -> Vec<Float> {
+        (0..steps)
+            .map(|_| {
+                let offset = checkpoint.step() as Float;
+                let input = Array::from((
+                    vec![4, 3],
+                    (0..12)
+                        .map(|i| ((i as Float + offset) * 0.37).sin())
+                        .collect::<Vec<Float>>(),
+                ));
+                let target = Array::from((
+                    vec![4, 2],
+                    (0..8)
+                        .map(|i| ((i as Float - offset) * 0.21).cos())
+                        .collect::<Vec<Float>>(),
+                ));
+                checkpoint.train_step(input, target)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resume() {
+        resume(|| Box::new(GradientDescent::new(0.05)));
+    }
+
+    #[test]
+    fn test_resume_momentum() {
+        resume(|| Box::new(Momentum::new(0.05, 0.9)));
+    }
+
+    /// Checks that training resumed from a checkpoint matches uninterrupted training, with a new optimizer from
+    /// `optimizer`.
+    fn resume(optimizer: impl Fn() -> Box<dyn Optimizer>) {
+        random::seed(11);
+        let mut uninterrupted = Checkpoint::new(model_with(optimizer()));
+        let expected = train(&mut uninterrupted, 10);
+
+        random::seed(11);
+        let mut interrupted = Checkpoint::new(model_with(optimizer()));
+        let mut losses = train(&mut interrupted, 4);
+        interrupted.end_epoch();
+
+        let mut bytes = Vec::new();
+        interrupted.write(&mut bytes).unwrap();
+        drop(interrupted);
+
+        // the resumed model is initialized differently, and samples different masks until it is restored
+        random::seed(12);
+        let mut resumed = Checkpoint::new(model_with(optimizer()));
+        resumed.read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((resumed.epoch(), resumed.step()), (1, 4));
+
+        losses.extend(train(&mut resumed, 6));
+        assert_eq!(losses, expected);
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let mut checkpoint = Checkpoint::new(model());
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes).unwrap();
+
+        let mut other = Checkpoint::new(Model::new(
+            vec![Box::new(Dense::new(3, 2, &initializer::he(), None))],
+            Box::new(GradientDescent::new(0.05)),
+            cost::mse(cost::Reduction::Mean),
+        ));
+        assert!(other.read(&mut bytes.as_slice()).is_err());
+        assert_eq!(other.step(), 0);
+    }
+
+    #[test]
+    fn test_optimizer_mismatch() {
+        let mut checkpoint = Checkpoint::new(model_with(Box::new(Momentum::new(0.05, 0.9))));
+        train(&mut checkpoint, 1);
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes).unwrap();
+
+        // the stateless optimizer cannot restore the velocities, so the parameters are not restored either
+        let mut other = Checkpoint::new(model());
+        let parameters = other.model().arrays();
+        assert!(other.read(&mut bytes.as_slice()).is_err());
+        assert_eq!(other.model().arrays(), parameters);
+        assert_eq!(other.step(), 0);
+    }
+}
@@ -3,22 +3,1423 @@
 use crate::array::*;
 use crate::numbers::*;
 
+use std::rc::Rc;
+
 /// A cost function, which computes the loss given a target. The cost function takes in the output
 /// as the first argument, and the target as the second.
 pub type CostFunction = Box<dyn Fn(&Array, &Array) -> Array>;
 
-/// Creates a mean square error loss closure.
-pub fn mse() -> CostFunction {
-    Box::new(|output, target| {
-        let length: usize = output.dimensions().iter().product();
-        (1.0 / length as Float) * &(target - output).powf(2.0)
+/// The reduction applied to an element-wise loss, which determines the dimensions of the loss, and what
+/// `Model::backward` returns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reduction {
+    /// The loss is left element-wise, with the dimensions of the output. `Model::backward` returns its sum.
+    None,
+    /// The loss is the mean of the element-wise loss, as an array with a single value.
+    Mean,
+    /// The loss is the sum of the element-wise loss, as an array with a single value.
+    Sum,
+}
+
+/// Reduces an element-wise loss to a single value, or leaves it unchanged for `Reduction::None`.
+pub fn reduce(loss: &Array, reduction: Reduction) -> Array {
+    match reduction {
+        Reduction::None => loss.clone(),
+        Reduction::Sum => loss.sum(loss.dimensions().len()),
+        Reduction::Mean => {
+            let length = loss.values().len();
+            (1.0 / length as Float) * &loss.sum(loss.dimensions().len())
+        }
+    }
+}
+
+/// Creates an array of ones with the same dimensions as the given array.
+fn ones_like(array: &Array) -> Array {
+    Array::from((array.dimensions().to_vec(), vec![1.0; array.values().len()]))
+}
+
+/// Computes an element-wise loss of the difference between the output, and the target.
+/// The loss function, and its derivative, take in the difference, output minus target.
+fn difference_op<F, D>(
+    op_name: &'static str,
+    output: &Array,
+    target: &Array,
+    loss: F,
+    derivative: D,
+) -> Array
+where
+    F: Fn(Float) -> Float + 'static,
+    D: Fn(Float) -> Float + 'static,
+{
+    assert!(
+        output.dimensions() == target.dimensions(),
+        "error: the output dimensions {:?}, and target dimensions {:?} must match",
+        output.dimensions(),
+        target.dimensions()
+    );
+
+    let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+        Array::from((
+            x[0].dimensions().to_vec(),
+            x[0].values()
+                .iter()
+                .zip(x[1].values())
+                .map(|(o, t)| loss(o - t))
+                .collect::<Vec<Float>>(),
+        ))
+    });
+
+    let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+        let values: Vec<Float> = c[0]
+            .values()
+            .iter()
+            .zip(c[1].values())
+            .zip(x.values())
+            .map(|((o, t), d)| derivative(o - t) * d)
+            .collect();
+        let delta = Array::from((c[0].dimensions().to_vec(), values));
+
+        vec![
+            if t[0] { Some(delta.clone()) } else { None },
+            if t[1] { Some(-&delta) } else { None },
+        ]
+    });
+
+    Array::named_op(op_name, &[output, target], op, Some(backward_op))
+}
+
+/// The loss of a single sample, given the output for each class, and the target class, returning the loss, and the
+/// derivative of the loss with respect to the output for each class.
+type ClassLoss = dyn Fn(&[Float], usize) -> (Float, Vec<Float>);
+
+/// Computes the loss of each sample of an output with the dimensions `[.., classes]`, where the target has the
+/// leading dimensions of the output, and holds the index of the target class of each sample.
+fn class_op(op_name: &'static str, output: &Array, target: &Array, loss: Rc<ClassLoss>) -> Array {
+    let dimension_count = output.dimensions().len();
+    let class_count = output.dimensions()[dimension_count - 1];
+    assert!(
+        target.values().len() * class_count == output.values().len(),
+        "error: the target dimensions {:?} must hold a class index for each sample of the output dimensions {:?}",
+        target.dimensions(),
+        output.dimensions()
+    );
+
+    let forward_loss = Rc::clone(&loss);
+    let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+        let values = x[0]
+            .values()
+            .chunks(class_count)
+            .zip(x[1].values())
+            .map(|(o, &t)| forward_loss(o, class_index(t, class_count)).0)
+            .collect::<Vec<Float>>();
+        Array::from((x[1].dimensions().to_vec(), values))
+    });
+
+    let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+        let mut values = Vec::with_capacity(c[0].values().len());
+        for ((o, &t), d) in c[0]
+            .values()
+            .chunks(class_count)
+            .zip(c[1].values())
+            .zip(x.values())
+        {
+            values.extend(loss(o, class_index(t, class_count)).1.iter().map(|g| g * d));
+        }
+
+        vec![
+            if t[0] {
+                Some(Array::from((c[0].dimensions().to_vec(), values)))
+            } else {
+                None
+            },
+            None,
+        ]
+    });
+
+    Array::named_op(op_name, &[output, target], op, Some(backward_op))
+}
+
+/// Converts a target value to the index of a class, checking that it is valid.
+fn class_index(value: Float, class_count: usize) -> usize {
+    assert!(
+        value >= 0.0 && value.fract() == 0.0 && (value as usize) < class_count,
+        "error: the target {} is not a valid class index for {} classes",
+        value,
+        class_count
+    );
+
+    value as usize
+}
+
+/// The loss of a single sample, given its embeddings, and its label, returning the loss, and the derivative of the
+/// loss with respect to each embedding.
+type EmbeddingLoss = dyn Fn(&[&[Float]], Float) -> (Float, Vec<Vec<Float>>);
+
+/// Computes the loss of each sample of `count` embeddings stacked as `[count, batch, ..]`, such as by
+/// `Array::stack`. The target holds the label of each sample in the batch, if the loss uses labels.
+fn embedding_op(
+    op_name: &'static str,
+    output: &Array,
+    target: Option<&Array>,
+    count: usize,
+    loss: Rc<EmbeddingLoss>,
+) -> Array {
+    let dimensions = output.dimensions();
+    assert!(
+        dimensions.len() >= 2 && dimensions[0] == count,
+        "error: the output dimensions {:?} must stack {} embeddings along the first dimension",
+        dimensions,
+        count
+    );
+
+    let batch_size = dimensions[1];
+    let embedding_size = output.values().len() / (count * batch_size);
+    if let Some(target) = target {
+        assert!(
+            target.values().len() == batch_size,
+            "error: the target dimensions {:?} must hold a label for each of the {} samples",
+            target.dimensions(),
+            batch_size
+        );
+    }
+
+    // retrieves the embeddings of the sample with the given index
+    let embeddings = move |values: &[Float], i: usize| -> Vec<Vec<Float>> {
+        (0..count)
+            .map(|k| {
+                let offset = embedding_size * (i + batch_size * k);
+                values[offset..offset + embedding_size].to_vec()
+            })
+            .collect()
+    };
+
+    let forward_loss = Rc::clone(&loss);
+    let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+        let values = (0..batch_size)
+            .map(|i| {
+                let sample = embeddings(x[0].values(), i);
+                let sample: Vec<&[Float]> = sample.iter().map(|e| &e[..]).collect();
+                let label = x.get(1).map_or(0.0, |t| t.values()[i]);
+                forward_loss(&sample, label).0
+            })
+            .collect::<Vec<Float>>();
+        Array::from((vec![batch_size], values))
+    });
+
+    let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+        let mut values = vec![0.0; c[0].values().len()];
+        for i in 0..batch_size {
+            let sample = embeddings(c[0].values(), i);
+            let sample: Vec<&[Float]> = sample.iter().map(|e| &e[..]).collect();
+            let label = c.get(1).map_or(0.0, |t| t.values()[i]);
+            for (k, gradient) in loss(&sample, label).1.into_iter().enumerate() {
+                let offset = embedding_size * (i + batch_size * k);
+                for (v, g) in values[offset..offset + embedding_size]
+                    .iter_mut()
+                    .zip(gradient)
+                {
+                    *v = g * x.values()[i];
+                }
+            }
+        }
+
+        let mut deltas = vec![if t[0] {
+            Some(Array::from((c[0].dimensions().to_vec(), values)))
+        } else {
+            None
+        }];
+        deltas.resize(c.len(), None);
+        deltas
+    });
+
+    match target {
+        Some(target) => Array::named_op(op_name, &[output, target], op, Some(backward_op)),
+        None => Array::named_op(op_name, &[output], op, Some(backward_op)),
+    }
+}
+
+/// Computes the Euclidean distance between two vectors.
+fn distance(x: &[Float], y: &[Float]) -> Float {
+    x.iter()
+        .zip(y)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<Float>()
+        .sqrt()
+}
+
+/// Computes log-probabilities along the last dimension, either from logits with the stable log-softmax, or from
+/// probabilities, such as those of a softmax, which are clamped to avoid the logarithm of zero.
+fn log_probabilities(output: &Array, from_logits: bool) -> Array {
+    if from_logits {
+        output.log_softmax()
+    } else {
+        output.clamp(Float::MIN_POSITIVE, 1.0).ln()
+    }
+}
+
+/// Sums a loss along the last (class) dimension, leaving the loss of each sample.
+fn sum_classes(loss: &Array) -> Array {
+    let dimensions = loss.dimensions();
+    let sample_dimensions = if dimensions.len() > 1 {
+        dimensions[..dimensions.len() - 1].to_vec()
+    } else {
+        vec![1]
+    };
+
+    loss.sum(1).reshape(sample_dimensions)
+}
+
+/// Adds two values in log space, such that `log_add(ln(a), ln(b)) = ln(a + b)`.
+fn log_add(a: Float, b: Float) -> Float {
+    if a == Float::NEG_INFINITY {
+        b
+    } else if b == Float::NEG_INFINITY {
+        a
+    } else {
+        a.max(b) + (-(a - b).abs()).exp().ln_1p()
+    }
+}
+
+/// Computes the CTC loss of a single sample, and the gradient of the loss with respect to the log-probabilities of
+/// each time step, and class, using the forward-backward recursions in log space.
+///
+/// The log-probability of class k at time step t is given by `log_probability(t, k)`.
+fn ctc_sample<P>(
+    log_probability: P,
+    time_count: usize,
+    class_count: usize,
+    labels: &[usize],
+    blank: usize,
+) -> (Float, Vec<Float>)
+where
+    P: Fn(usize, usize) -> Float,
+{
+    // the labels, with blanks at the start, between each label, and at the end
+    let extended: Vec<usize> = labels
+        .iter()
+        .flat_map(|&l| vec![blank, l])
+        .chain(vec![blank])
+        .collect();
+    let extended_count = extended.len();
+
+    // whether a path may skip the blank before the extended label at index s
+    let can_skip = |s: usize| s >= 2 && extended[s] != blank && extended[s] != extended[s - 2];
+
+    let mut alpha = vec![vec![Float::NEG_INFINITY; extended_count]; time_count];
+    alpha[0][0] = log_probability(0, blank);
+    if extended_count > 1 {
+        alpha[0][1] = log_probability(0, extended[1]);
+    }
+
+    for t in 1..time_count {
+        for s in 0..extended_count {
+            let mut sum = alpha[t - 1][s];
+            if s >= 1 {
+                sum = log_add(sum, alpha[t - 1][s - 1]);
+            }
+            if can_skip(s) {
+                sum = log_add(sum, alpha[t - 1][s - 2]);
+            }
+
+            alpha[t][s] = sum + log_probability(t, extended[s]);
+        }
+    }
+
+    let mut beta = vec![vec![Float::NEG_INFINITY; extended_count]; time_count];
+    let last = time_count - 1;
+    beta[last][extended_count - 1] = log_probability(last, blank);
+    if extended_count > 1 {
+        beta[last][extended_count - 2] = log_probability(last, extended[extended_count - 2]);
+    }
+
+    for t in (0..last).rev() {
+        for s in 0..extended_count {
+            let mut sum = beta[t + 1][s];
+            if s + 1 < extended_count {
+                sum = log_add(sum, beta[t + 1][s + 1]);
+            }
+            if s + 2 < extended_count && can_skip(s + 2) {
+                sum = log_add(sum, beta[t + 1][s + 2]);
+            }
+
+            beta[t][s] = sum + log_probability(t, extended[s]);
+        }
+    }
+
+    let mut log_likelihood = alpha[last][extended_count - 1];
+    if extended_count > 1 {
+        log_likelihood = log_add(log_likelihood, alpha[last][extended_count - 2]);
+    }
+
+    let mut gradient = vec![0.0; time_count * class_count];
+    // there is no gradient if no alignment of the labels is possible
+    if log_likelihood == Float::NEG_INFINITY {
+        return (Float::INFINITY, gradient);
+    }
+
+    for t in 0..time_count {
+        for (s, &k) in extended.iter().enumerate() {
+            // both alpha, and beta include the log-probability of the current time step
+            let log_occupancy = alpha[t][s] + beta[t][s] - log_probability(t, k);
+            gradient[t * class_count + k] -= (log_occupancy - log_likelihood).exp();
+        }
+    }
+
+    (-log_likelihood, gradient)
+}
+
+/// Computes the sign of a value, which is zero for zero.
+fn sign(x: Float) -> Float {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// Creates a square error loss closure, which is the mean square error for `Reduction::Mean`.
+pub fn mse(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op("mse", output, target, |d| d * d, |d| 2.0 * d);
+        reduce(&loss, reduction)
     })
 }
 
-/// Creates a cross-entropy loss closure.
-pub fn cross_entropy() -> CostFunction {
-    Box::new(|output, target| {
-        let batch_size: usize = output.dimensions()[0];
-        (1.0 / batch_size as Float) * &(&(-target) * &output.ln())
+/// Creates a mean absolute error (L1) loss closure.
+pub fn l1(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op("l1", output, target, Float::abs, sign);
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a Huber loss closure, which is quadratic for differences with a magnitude of at most `delta`, and linear
+/// beyond, so it is less sensitive to outliers than the mean square error.
+pub fn huber(delta: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op(
+            "huber",
+            output,
+            target,
+            move |d| {
+                if d.abs() <= delta {
+                    0.5 * d * d
+                } else {
+                    delta * (d.abs() - 0.5 * delta)
+                }
+            },
+            move |d| {
+                if d.abs() <= delta {
+                    d
+                } else {
+                    delta * sign(d)
+                }
+            },
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a smooth L1 loss closure, which is quadratic for differences with a magnitude less than `beta`, and
+/// equal to the L1 loss beyond. It is the Huber loss divided by `beta`, and is the L1 loss when `beta` is zero.
+pub fn smooth_l1(beta: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op(
+            "smooth_l1",
+            output,
+            target,
+            move |d| {
+                if d.abs() < beta {
+                    0.5 * d * d / beta
+                } else {
+                    d.abs() - 0.5 * beta
+                }
+            },
+            move |d| if d.abs() < beta { d / beta } else { sign(d) },
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a log-cosh loss closure, which computes ln(cosh(d)) for each difference d, without overflow.
+pub fn log_cosh(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op(
+            "log_cosh",
+            output,
+            target,
+            |d| {
+                let d = d.abs();
+                d + (-2.0 * d).exp().ln_1p() - (2.0 as Float).ln()
+            },
+            Float::tanh,
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a quantile (pinball) loss closure, which penalizes outputs below the target by `tau`, and outputs above
+/// the target by `1 - tau`, so the loss is minimized by the `tau` quantile of the target.
+pub fn quantile(tau: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = difference_op(
+            "quantile",
+            output,
+            target,
+            move |d| if d > 0.0 { (1.0 - tau) * d } else { -tau * d },
+            move |d| if d > 0.0 { 1.0 - tau } else { -tau },
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a cross-entropy loss closure, for outputs which are probabilities, such as those of a softmax, with the
+/// dimensions `[.., classes]`. The target holds the probability of each class, such as a one-hot encoding, and the
+/// loss is computed for each sample.
+pub fn cross_entropy(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = sum_classes(&(&(-target) * &log_probabilities(output, false)));
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a binary cross-entropy loss closure, for outputs which are probabilities, such as those of a sigmoid.
+/// Each value of the target is the probability of the positive label, so multi-label targets are supported, and the
+/// loss is element-wise.
+pub fn binary_cross_entropy(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let ones = ones_like(output);
+        // the output is clamped, as a saturated sigmoid gives probabilities of exactly 0, or 1
+        let output = output.clamp(Float::EPSILON, 1.0 - Float::EPSILON);
+        let loss = &(target * &output.ln()) + &(&(&ones - target) * &(&ones - &output).ln());
+        reduce(&(-&loss), reduction)
+    })
+}
+
+/// Creates a binary cross-entropy loss closure, which applies the sigmoid to the output itself, and is
+/// numerically stable for outputs of any magnitude. The loss is element-wise, as for `binary_cross_entropy`.
+///
+/// The optional `pos_weight` scales the loss of the positive labels, and is broadcast along the last dimension,
+/// so it should have one value for each class.
+pub fn bce_with_logits(pos_weight: Option<Array>, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let ones = ones_like(output);
+
+        // the loss is (1 - t) * x + (1 + (p - 1) * t) * ln(1 + exp(-x))
+        let log_weight = match &pos_weight {
+            Some(p) => &(&ones - target) + &(target * p),
+            None => ones.clone(),
+        };
+
+        let loss = &(&(&ones - target) * output) + &(&log_weight * &(-output).softplus());
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a cross-entropy loss closure with label smoothing, which mixes the target with a uniform distribution over
+/// the classes, weighted by `smoothing`. The target holds the probability of each class, such as a one-hot encoding,
+/// with the dimensions `[.., classes]`, and the loss is computed for each sample.
+///
+/// The output holds logits if `from_logits`, and probabilities, such as those of a softmax, otherwise.
+pub fn label_smoothing_cross_entropy(
+    smoothing: Float,
+    from_logits: bool,
+    reduction: Reduction,
+) -> CostFunction {
+    Box::new(move |output, target| {
+        let class_count = *output.dimensions().last().unwrap();
+        let uniform = &ones_like(target) * (smoothing / class_count as Float);
+        let smoothed = &((1.0 - smoothing) * target) + &uniform;
+
+        let loss = &(-&smoothed) * &log_probabilities(output, from_logits);
+        reduce(&sum_classes(&loss), reduction)
+    })
+}
+
+/// Creates a focal loss closure, which scales the cross-entropy of each class by `(1 - p)^gamma`, so well-classified
+/// samples contribute less to the loss. The target is as for `label_smoothing_cross_entropy`, and the optional
+/// `alpha` scales the loss of each class, with one value for each class.
+///
+/// The output holds logits if `from_logits`, and probabilities, such as those of a softmax, otherwise.
+pub fn focal(
+    gamma: Float,
+    alpha: Option<Array>,
+    from_logits: bool,
+    reduction: Reduction,
+) -> CostFunction {
+    Box::new(move |output, target| {
+        let log_probabilities = log_probabilities(output, from_logits);
+        let mut loss = &(-target) * &log_probabilities;
+
+        if gamma != 0.0 {
+            let probabilities = log_probabilities.exp();
+            // clamping avoids an infinite gradient for certain predictions when gamma is less than one
+            let modulating = (&ones_like(&probabilities) - &probabilities)
+                .clamp(Float::MIN_POSITIVE, 1.0)
+                .powf(gamma);
+            loss = &loss * &modulating;
+        }
+
+        if let Some(alpha) = &alpha {
+            loss = &loss * alpha;
+        }
+
+        reduce(&sum_classes(&loss), reduction)
+    })
+}
+
+/// Creates a Connectionist Temporal Classification (CTC) loss closure, for labelling sequences without an alignment
+/// between the time steps, and the labels.
+///
+/// The output holds log-probabilities, such as those of `Array::log_softmax`, with the dimensions
+/// `[time, batch, classes]`. The target holds the labels of each sample, with the dimensions `[batch, labels]`,
+/// padded with the `blank` class index, which cannot be a label. The loss of each sample is the negative
+/// log-likelihood of its labels, and is infinite, with no gradient, if the labels cannot be aligned.
+pub fn ctc(blank: usize, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let dimensions = output.dimensions();
+        assert!(
+            dimensions.len() == 3,
+            "error: the output dimensions {:?} must be time steps, by batch size, by classes",
+            dimensions
+        );
+
+        let (time_count, batch_size, class_count) = (dimensions[0], dimensions[1], dimensions[2]);
+        assert!(
+            time_count > 0 && batch_size > 0,
+            "error: the output dimensions {:?} must have at least one time step, and one sample",
+            dimensions
+        );
+        assert!(
+            blank < class_count && target.values().len() % batch_size == 0,
+            "error: the target dimensions {:?} must hold the labels of each of the {} samples",
+            target.dimensions(),
+            batch_size
+        );
+
+        let label_count = target.values().len() / batch_size;
+        let values = output.values();
+
+        let mut losses = Vec::with_capacity(batch_size);
+        let mut gradient = vec![0.0; values.len()];
+        for b in 0..batch_size {
+            let labels: Vec<usize> = target.values()[b * label_count..(b + 1) * label_count]
+                .iter()
+                .map(|&l| class_index(l, class_count))
+                .filter(|&l| l != blank)
+                .collect();
+
+            let log_probability =
+                |t: usize, k: usize| values[(t * batch_size + b) * class_count + k];
+            let (loss, sample_gradient) =
+                ctc_sample(log_probability, time_count, class_count, &labels, blank);
+            losses.push(loss);
+
+            for t in 0..time_count {
+                let offset = (t * batch_size + b) * class_count;
+                gradient[offset..offset + class_count]
+                    .copy_from_slice(&sample_gradient[t * class_count..(t + 1) * class_count]);
+            }
+        }
+
+        let losses = Rc::new(losses);
+        let gradient = Rc::new(gradient);
+
+        let op: ForwardOp = Rc::new(move |_| Array::from((vec![batch_size], Rc::clone(&losses))));
+        let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+            let values: Vec<Float> = gradient
+                .chunks(class_count)
+                .enumerate()
+                .flat_map(|(i, g)| {
+                    let delta = x.values()[i % batch_size];
+                    g.iter().map(move |g| g * delta)
+                })
+                .collect();
+
+            vec![
+                if t[0] {
+                    Some(Array::from((c[0].dimensions().to_vec(), values)))
+                } else {
+                    None
+                },
+                None,
+            ]
+        });
+
+        let loss = Array::named_op("ctc", &[output, target], op, Some(backward_op));
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a Kullback-Leibler divergence loss closure, where the output holds log-probabilities, such as those of
+/// `Array::ln` applied to a softmax, and the target holds probabilities. The loss is zero where the target is zero.
+pub fn kl_div(reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        assert!(
+            output.dimensions() == target.dimensions(),
+            "error: the output dimensions {:?}, and target dimensions {:?} must match",
+            output.dimensions(),
+            target.dimensions()
+        );
+
+        let op: ForwardOp = Rc::new(|x: &[&Array]| {
+            let values = x[0]
+                .values()
+                .iter()
+                .zip(x[1].values())
+                .map(|(&o, &t)| if t > 0.0 { t * (t.ln() - o) } else { 0.0 })
+                .collect::<Vec<Float>>();
+            Array::from((x[0].dimensions().to_vec(), values))
+        });
+
+        let backward_op: BackwardOp = Rc::new(|c, t, x| {
+            let output_delta = c[1]
+                .values()
+                .iter()
+                .zip(x.values())
+                .map(|(t, d)| -t * d)
+                .collect::<Vec<Float>>();
+            let target_delta = c[0]
+                .values()
+                .iter()
+                .zip(c[1].values())
+                .zip(x.values())
+                .map(|((&o, &t), d)| if t > 0.0 { (t.ln() + 1.0 - o) * d } else { 0.0 })
+                .collect::<Vec<Float>>();
+
+            vec![
+                if t[0] {
+                    Some(Array::from((c[0].dimensions().to_vec(), output_delta)))
+                } else {
+                    None
+                },
+                if t[1] {
+                    Some(Array::from((c[1].dimensions().to_vec(), target_delta)))
+                } else {
+                    None
+                },
+            ]
+        });
+
+        let loss = Array::named_op("kl_div", &[output, target], op, Some(backward_op));
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a negative log-likelihood loss closure, where the output holds log-probabilities with the dimensions
+/// `[.., classes]`, and the target holds the index of the target class of each sample.
+///
+/// The optional `weights` scale the loss of each class, with one value for each class. For `Reduction::Mean`, the
+/// loss is divided by the sum of the weights of the target classes.
+pub fn nll_loss(weights: Option<Array>, reduction: Reduction) -> CostFunction {
+    let weights: Option<Rc<Vec<Float>>> = weights.map(|w| Rc::new(w.values().to_vec()));
+    Box::new(move |output, target| {
+        let class_weights = weights.clone();
+        let weight = move |class: usize| class_weights.as_ref().map_or(1.0, |w| w[class]);
+
+        let sample_weight = weight.clone();
+        let loss = class_op(
+            "nll_loss",
+            output,
+            target,
+            Rc::new(move |o, class| {
+                let mut gradient = vec![0.0; o.len()];
+                gradient[class] = -sample_weight(class);
+                (-sample_weight(class) * o[class], gradient)
+            }),
+        );
+
+        match reduction {
+            Reduction::Mean => {
+                let class_count = *output.dimensions().last().unwrap();
+                let total_weight: Float = target
+                    .values()
+                    .iter()
+                    .map(|&t| weight(class_index(t, class_count)))
+                    .sum();
+                (1.0 / total_weight) * &reduce(&loss, Reduction::Sum)
+            }
+            _ => reduce(&loss, reduction),
+        }
     })
 }
+
+/// Creates a multi-class hinge loss closure, where the output holds a score for each class, with the dimensions
+/// `[.., classes]`, and the target holds the index of the target class of each sample.
+///
+/// The loss of each sample is the sum of `max(0, margin - x[y] + x[j])` for each class j other than the target
+/// class y, divided by the number of classes.
+pub fn hinge(margin: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = class_op(
+            "hinge",
+            output,
+            target,
+            Rc::new(move |o, class| {
+                let class_count = o.len() as Float;
+                let mut loss = 0.0;
+                let mut gradient = vec![0.0; o.len()];
+                for (j, x) in o.iter().enumerate().filter(|(j, _)| *j != class) {
+                    let violation = margin - o[class] + x;
+                    if violation > 0.0 {
+                        loss += violation / class_count;
+                        gradient[j] += 1.0 / class_count;
+                        gradient[class] -= 1.0 / class_count;
+                    }
+                }
+
+                (loss, gradient)
+            }),
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a triplet margin loss closure, where the output stacks the anchor, positive, and negative embeddings as
+/// `[3, batch, ..]`, such as by `Array::stack`. The target is unused.
+///
+/// The loss of each sample is `max(0, d(a, p) - d(a, n) + margin)`, where d is the Euclidean distance.
+pub fn triplet_margin(margin: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, _| {
+        let loss = embedding_op(
+            "triplet_margin",
+            output,
+            None,
+            3,
+            Rc::new(move |e, _| {
+                let (anchor, positive, negative) = (e[0], e[1], e[2]);
+                let positive_distance = distance(anchor, positive);
+                let negative_distance = distance(anchor, negative);
+                let loss = positive_distance - negative_distance + margin;
+
+                let length = anchor.len();
+                if loss <= 0.0 {
+                    return (0.0, vec![vec![0.0; length]; 3]);
+                }
+
+                // the derivative of the distance with respect to the first vector, which is zero if they are equal
+                let derivative = |x: &[Float], y: &[Float], d: Float| -> Vec<Float> {
+                    x.iter()
+                        .zip(y)
+                        .map(|(a, b)| if d > 0.0 { (a - b) / d } else { 0.0 })
+                        .collect()
+                };
+                let positive_gradient = derivative(anchor, positive, positive_distance);
+                let negative_gradient = derivative(anchor, negative, negative_distance);
+                let anchor_gradient = positive_gradient
+                    .iter()
+                    .zip(&negative_gradient)
+                    .map(|(p, n)| p - n)
+                    .collect();
+
+                (
+                    loss,
+                    vec![
+                        anchor_gradient,
+                        positive_gradient.iter().map(|g| -g).collect(),
+                        negative_gradient,
+                    ],
+                )
+            }),
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a contrastive loss closure, where the output stacks pairs of embeddings as `[2, batch, ..]`, such as by
+/// `Array::stack`, and the target holds 1 for each similar pair, and 0 for each dissimilar pair.
+///
+/// The loss of each sample is `d^2 / 2` for similar pairs, and `max(0, margin - d)^2 / 2` for dissimilar pairs,
+/// where d is the Euclidean distance.
+pub fn contrastive(margin: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = embedding_op(
+            "contrastive",
+            output,
+            Some(target),
+            2,
+            Rc::new(move |e, label| {
+                let d = distance(e[0], e[1]);
+                let hinge = (margin - d).max(0.0);
+                let loss = 0.5 * (label * d * d + (1.0 - label) * hinge * hinge);
+
+                // the derivative of the loss with respect to the difference of the embeddings
+                let scale = label
+                    - if d > 0.0 {
+                        (1.0 - label) * hinge / d
+                    } else {
+                        0.0
+                    };
+                let gradient: Vec<Float> = e[0]
+                    .iter()
+                    .zip(e[1])
+                    .map(|(x, y)| scale * (x - y))
+                    .collect();
+
+                (
+                    loss,
+                    vec![gradient.clone(), gradient.iter().map(|g| -g).collect()],
+                )
+            }),
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Creates a cosine embedding loss closure, where the output stacks pairs of embeddings as `[2, batch, ..]`, such as
+/// by `Array::stack`, and the target holds 1 for each similar pair, and -1 for each dissimilar pair.
+///
+/// The loss of each sample is `1 - cos(x, y)` for similar pairs, and `max(0, cos(x, y) - margin)` for dissimilar
+/// pairs.
+pub fn cosine_embedding(margin: Float, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = embedding_op(
+            "cosine_embedding",
+            output,
+            Some(target),
+            2,
+            Rc::new(move |e, label| {
+                let (x, y) = (e[0], e[1]);
+                // the squared norms are offset to avoid dividing by zero
+                let x_norm = x.iter().map(|v| v * v).sum::<Float>() + 1e-12;
+                let y_norm = y.iter().map(|v| v * v).sum::<Float>() + 1e-12;
+                let norm = (x_norm * y_norm).sqrt();
+                let dot: Float = x.iter().zip(y).map(|(a, b)| a * b).sum();
+                let cos = dot / norm;
+
+                // the derivative of the cosine similarity with respect to x, given y
+                let derivative = |x: &[Float], y: &[Float], x_norm: Float| -> Vec<Float> {
+                    x.iter()
+                        .zip(y)
+                        .map(|(a, b)| b / norm - cos * a / x_norm)
+                        .collect()
+                };
+
+                let (loss, scale) = if label > 0.0 {
+                    (1.0 - cos, -1.0)
+                } else if cos > margin {
+                    (cos - margin, 1.0)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                let gradients = vec![
+                    derivative(x, y, x_norm).iter().map(|g| scale * g).collect(),
+                    derivative(y, x, y_norm).iter().map(|g| scale * g).collect(),
+                ];
+                (loss, gradients)
+            }),
+        );
+        reduce(&loss, reduction)
+    })
+}
+
+/// Wraps a cost function, scaling the loss of each class by a weight, which is useful for imbalanced datasets. The
+/// weights have one value for each class, and the wrapped cost function must not reduce its loss, so should have a
+/// `Reduction` of `None`. The weighted loss is then reduced by `reduction`.
+///
+/// An element-wise loss, with the dimensions of the output `[.., classes]`, is scaled along its last dimension. A loss
+/// for each sample, such as that of `nll_loss`, is scaled by the weight of the target class of the sample, where the
+/// target holds either class indices, or the probability of each class, such as a one-hot encoding.
+pub fn class_weighted(cost: CostFunction, weights: Array, reduction: Reduction) -> CostFunction {
+    Box::new(move |output, target| {
+        let loss = cost(output, target);
+        let class_count = weights.values().len();
+        assert!(
+            *output.dimensions().last().unwrap() == class_count,
+            "error: the output dimensions {:?} must end with the {} class weights",
+            output.dimensions(),
+            class_count
+        );
+
+        if loss.dimensions() == output.dimensions() {
+            return reduce(&(&loss * &weights), reduction);
+        }
+
+        assert!(
+            loss.values().len() * class_count == output.values().len(),
+            "error: the loss dimensions {:?} must be those of the output {:?}, or have a loss for each sample, so the \
+             wrapped loss must not be reduced",
+            loss.dimensions(),
+            output.dimensions()
+        );
+        let sample_weights: Vec<Float> = if target.dimensions() == output.dimensions() {
+            target
+                .values()
+                .chunks(class_count)
+                .map(|t| t.iter().zip(weights.values()).map(|(t, w)| t * w).sum())
+                .collect()
+        } else {
+            target
+                .values()
+                .iter()
+                .map(|&t| weights.values()[class_index(t, class_count)])
+                .collect()
+        };
+        let sample_weights = Array::from((loss.dimensions().to_vec(), sample_weights));
+
+        reduce(&(&loss * &sample_weights), reduction)
+    })
+}
+
+/// Scales an element-wise loss by a weight for each sample, where the weights have one value for each entry of the
+/// first (batch) dimension of the loss, and reduces the weighted loss by `reduction`. The loss must not already be
+/// reduced.
+pub fn sample_weighted(loss: &Array, weights: &Array, reduction: Reduction) -> Array {
+    assert!(
+        loss.dimensions()[0] == weights.values().len(),
+        "error: the loss dimensions {:?} must start with the {} sample weights, so the loss must not be reduced",
+        loss.dimensions(),
+        weights.values().len()
+    );
+
+    let mut weight_dimensions = vec![1; loss.dimensions().len()];
+    weight_dimensions[0] = weights.values().len();
+    reduce(&(loss * &weights.reshape(weight_dimensions)), reduction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the gradient of a cost function with respect to the output against a numerical gradient.
+    fn check_gradient(cost: CostFunction, output: Array, target: Array) {
+        #[cfg(feature = "f32")]
+        let (epsilon, tolerance) = (1e-2, 1e-1);
+        #[cfg(not(feature = "f32"))]
+        let (epsilon, tolerance) = (1e-6, 1e-5);
+
+        let output = output.tracked();
+        cost(&output, &target).backward(None);
+        let gradient = output.gradient().to_owned().unwrap();
+
+        for i in 0..output.values().len() {
+            let mut delta = vec![0.0; output.values().len()];
+            delta[i] = epsilon;
+            let delta = Array::from((output.dimensions().to_vec(), delta));
+
+            let error_plus = cost(&(&output + &delta), &target).sum_all();
+            let error_minus = cost(&(&output - &delta), &target).sum_all();
+            let numerical_gradient = (error_plus - error_minus) / (2.0 * epsilon);
+            assert!((gradient[i] - numerical_gradient).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn test_reduce() {
+        let loss = arr![arr![1.0, 2.0], arr![3.0, 6.0]];
+        assert_eq!(reduce(&loss, Reduction::None), loss);
+        assert_eq!(reduce(&loss, Reduction::Sum), arr![12.0]);
+        assert_eq!(reduce(&loss, Reduction::Mean), arr![3.0]);
+    }
+
+    #[test]
+    fn test_l1() {
+        let output = arr![arr![1.0, -2.0], arr![3.0, 0.5]].tracked();
+        let target = arr![arr![2.0, -2.0], arr![1.0, 0.0]].tracked();
+
+        let result = l1(Reduction::Mean)(&output, &target);
+        assert_eq!(result, arr![0.875]);
+
+        result.backward(None);
+        assert_eq!(
+            output.gradient().to_owned().unwrap(),
+            arr![arr![-0.25, 0.0], arr![0.25, 0.25]]
+        );
+        assert_eq!(
+            target.gradient().to_owned().unwrap(),
+            arr![arr![0.25, 0.0], arr![-0.25, -0.25]]
+        );
+    }
+
+    #[test]
+    fn test_huber() {
+        let output = arr![0.5, 3.0, -4.0].tracked();
+        let target = arr![0.0, 0.0, 0.0];
+
+        let result = huber(2.0, Reduction::None)(&output, &target);
+        assert_eq!(result, arr![0.125, 4.0, 6.0]);
+
+        result.backward(None);
+        assert_eq!(output.gradient().to_owned().unwrap(), arr![0.5, 2.0, -2.0]);
+
+        let result = smooth_l1(2.0, Reduction::Sum)(&output, &target);
+        assert_eq!(result, arr![0.0625 + 2.0 + 3.0]);
+    }
+
+    #[test]
+    fn test_log_cosh() {
+        let output = arr![0.5, 1000.0].tracked();
+        let target = arr![0.0, 0.0];
+
+        let result = log_cosh(Reduction::None)(&output, &target);
+        assert_relative_eq!(
+            result,
+            arr![(0.5 as Float).cosh().ln(), 1000.0 - (2.0 as Float).ln()]
+        );
+
+        result.backward(None);
+        assert_relative_eq!(
+            output.gradient().to_owned().unwrap(),
+            arr![(0.5 as Float).tanh(), 1.0]
+        );
+    }
+
+    #[test]
+    fn test_quantile() {
+        let output = arr![1.0, 3.0].tracked();
+        let target = arr![2.0, 2.0];
+
+        let result = quantile(0.75, Reduction::None)(&output, &target);
+        assert_eq!(result, arr![0.75, 0.25]);
+
+        result.backward(None);
+        assert_eq!(output.gradient().to_owned().unwrap(), arr![-0.75, 0.25]);
+    }
+
+    #[test]
+    fn test_reductions() {
+        let output = arr![arr![0.25, 0.75], arr![0.5, 0.5]];
+        let target = arr![arr![0.0, 1.0], arr![1.0, 0.0]];
+
+        let result = mse(Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![arr![0.0625, 0.0625], arr![0.25, 0.25]]);
+        let result = mse(Reduction::Mean)(&output, &target);
+        assert_relative_eq!(result, arr![0.625 / 4.0]);
+
+        let ln = |x: Float| x.ln();
+        let result = cross_entropy(Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![-ln(0.75), -ln(0.5)], max_relative = 1e-6);
+        let result = cross_entropy(Reduction::Mean)(&output, &target);
+        assert_relative_eq!(
+            result,
+            arr![-(ln(0.75) + ln(0.5)) / 2.0],
+            max_relative = 1e-6
+        );
+
+        let result = binary_cross_entropy(Reduction::Sum)(&output, &target);
+        assert_relative_eq!(
+            result,
+            arr![-2.0 * (ln(0.75) + ln(0.5))],
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_label_smoothing() {
+        let logits = arr![arr![1.0, 2.0, 0.5], arr![-1.0, 3.0, 0.0]];
+        let target = arr![arr![0.0, 1.0, 0.0], arr![1.0, 0.0, 0.0]];
+
+        let expect = cross_entropy(Reduction::Sum)(&logits.softmax(), &target)[0];
+        let result = label_smoothing_cross_entropy(0.0, true, Reduction::Sum)(&logits, &target);
+        assert_relative_eq!(result, arr![expect], max_relative = 1e-6);
+
+        let result =
+            label_smoothing_cross_entropy(0.0, false, Reduction::Sum)(&logits.softmax(), &target);
+        assert_relative_eq!(result, arr![expect], max_relative = 1e-6);
+
+        // the smoothed target is [0.1, 0.8, 0.1]
+        let result = label_smoothing_cross_entropy(0.3, true, Reduction::None)(&logits, &target);
+        let log_softmax = logits.log_softmax();
+        assert_relative_eq!(
+            result[0],
+            -(0.1 * log_softmax[0] + 0.8 * log_softmax[1] + 0.1 * log_softmax[2]),
+            max_relative = 1e-6
+        );
+
+        check_gradient(
+            label_smoothing_cross_entropy(0.3, true, Reduction::Mean),
+            logits,
+            target,
+        );
+    }
+
+    #[test]
+    fn test_focal() {
+        let logits = arr![arr![1.0, 2.0, 0.5], arr![-1.0, 3.0, 0.0]];
+        let target = arr![arr![0.0, 1.0, 0.0], arr![1.0, 0.0, 0.0]];
+
+        let expect = label_smoothing_cross_entropy(0.0, true, Reduction::None)(&logits, &target);
+        let result = focal(0.0, None, true, Reduction::None)(&logits, &target);
+        assert_relative_eq!(result, expect);
+
+        let result = focal(2.0, Some(arr![0.5, 1.0, 1.0]), true, Reduction::None)(&logits, &target);
+        let probabilities = logits.softmax();
+        assert_relative_eq!(
+            result,
+            arr![
+                (1.0 - probabilities[1]).powi(2) * expect[0],
+                0.5 * (1.0 - probabilities[3]).powi(2) * expect[1]
+            ],
+            max_relative = 1e-6
+        );
+
+        check_gradient(focal(2.0, None, true, Reduction::Mean), logits, target);
+    }
+
+    #[test]
+    fn test_focal_probabilities() {
+        let output = arr![arr![0.0, 1.0], arr![0.5, 0.5]].tracked();
+        let target = arr![arr![1.0, 0.0], arr![1.0, 0.0]];
+
+        let result = focal(0.5, None, false, Reduction::Mean)(&output, &target);
+        assert!(result[0].is_finite());
+
+        result.backward(None);
+        assert!(output
+            .gradient()
+            .to_owned()
+            .unwrap()
+            .values()
+            .iter()
+            .all(|g| g.is_finite()));
+    }
+
+    #[test]
+    fn test_ctc() {
+        // the classes are the blank, a, and b, for two time steps
+        let probabilities = arr![arr![arr![0.5, 0.3, 0.2]], arr![arr![0.1, 0.6, 0.3]]];
+        let output = probabilities.ln();
+
+        // the paths for "a" are "aa", "a-", and "-a"
+        let result = ctc(0, Reduction::None)(&output, &arr![arr![1.0]]);
+        let expect = 0.3 * 0.6 + 0.3 * 0.1 + 0.5 * 0.6;
+        assert_relative_eq!(result, arr![-(expect as Float).ln()], max_relative = 1e-6);
+
+        // the only path for "ab" is "ab"
+        let result = ctc(0, Reduction::None)(&output, &arr![arr![1.0, 2.0]]);
+        assert_relative_eq!(
+            result,
+            arr![-(0.3 * 0.3 as Float).ln()],
+            max_relative = 1e-6
+        );
+
+        // "aa" requires a blank between the labels, so cannot be aligned in two time steps
+        let result = ctc(0, Reduction::None)(&output, &arr![arr![1.0, 1.0]]);
+        assert_eq!(result, arr![Float::INFINITY]);
+    }
+
+    #[test]
+    fn test_ctc_gradient() {
+        let logits = Array::from((
+            vec![5, 2, 4],
+            (0..40)
+                .map(|x| ((x * 7) % 11) as Float / 4.0 - 1.0)
+                .collect::<Vec<Float>>(),
+        ));
+        let target = arr![arr![1.0, 2.0, 2.0], arr![3.0, 0.0, 0.0]];
+
+        let cost: CostFunction =
+            Box::new(|output, target| ctc(0, Reduction::Mean)(&output.log_softmax(), target));
+        check_gradient(cost, logits, target);
+    }
+
+    #[test]
+    fn test_kl_div() {
+        let target = arr![arr![0.25, 0.75], arr![1.0, 0.0]];
+        let output = arr![arr![0.5, 0.5], arr![0.5, 0.5]].ln();
+
+        let result = kl_div(Reduction::Sum)(&output, &target);
+        let expect = 0.25 * (0.5 as Float).ln() + 0.75 * (1.5 as Float).ln() + (2.0 as Float).ln();
+        assert_relative_eq!(result, arr![expect]);
+
+        check_gradient(kl_div(Reduction::Mean), output, target);
+    }
+
+    #[test]
+    fn test_nll_loss() {
+        let output = arr![arr![-1.0, -2.0, -3.0], arr![-4.0, -5.0, -6.0]].tracked();
+        let target = arr![2.0, 0.0];
+
+        let result = nll_loss(None, Reduction::None)(&output, &target);
+        assert_eq!(result, arr![3.0, 4.0]);
+
+        let result = nll_loss(Some(arr![1.0, 2.0, 3.0]), Reduction::Mean)(&output, &target);
+        assert_eq!(result, arr![13.0 / 4.0]);
+
+        result.backward(None);
+        assert_eq!(
+            output.gradient().to_owned().unwrap(),
+            arr![arr![0.0, 0.0, -0.75], arr![-0.25, 0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_nll_loss_invalid() {
+        nll_loss(None, Reduction::None)(&arr![arr![-1.0, -2.0]], &arr![2.0]);
+    }
+
+    #[test]
+    fn test_hinge() {
+        let output = arr![arr![1.0, 2.0, 0.5], arr![3.0, 0.0, 0.0]];
+        let target = arr![0.0, 0.0];
+
+        let result = hinge(1.0, Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![2.5 / 3.0, 0.0]);
+
+        check_gradient(hinge(1.0, Reduction::Mean), output, target);
+    }
+
+    #[test]
+    fn test_triplet_margin() {
+        let anchor = arr![arr![0.0, 0.0], arr![1.0, 1.0]];
+        let positive = arr![arr![3.0, 4.0], arr![1.0, 2.0]];
+        let negative = arr![arr![0.0, 1.0], arr![4.0, 5.0]];
+        let output = Array::stack(&[&anchor, &positive, &negative]);
+
+        let result = triplet_margin(1.0, Reduction::None)(&output, &arr![0.0]);
+        assert_relative_eq!(result, arr![5.0, 0.0]);
+
+        check_gradient(triplet_margin(1.0, Reduction::Sum), output, arr![0.0]);
+    }
+
+    #[test]
+    fn test_contrastive() {
+        let x = arr![arr![0.0, 0.0], arr![1.0, 1.0], arr![0.0, 0.5]];
+        let y = arr![arr![3.0, 4.0], arr![1.0, 2.0], arr![0.0, 0.0]];
+        let output = Array::stack(&[&x, &y]);
+        let target = arr![1.0, 0.0, 0.0];
+
+        let result = contrastive(2.0, Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![12.5, 0.5, 1.125]);
+
+        check_gradient(contrastive(2.0, Reduction::Mean), output, target);
+    }
+
+    #[test]
+    fn test_cosine_embedding() {
+        let x = arr![arr![1.0, 0.0], arr![1.0, 1.0], arr![1.0, 0.0]];
+        let y = arr![arr![0.0, 2.0], arr![2.0, 2.0], arr![-1.0, 1.0]];
+        let output = Array::stack(&[&x, &y]);
+        let target = arr![1.0, -1.0, -1.0];
+
+        let result = cosine_embedding(0.5, Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![1.0, 0.5, 0.0], epsilon = 1e-6);
+
+        check_gradient(cosine_embedding(0.5, Reduction::Mean), output, target);
+    }
+
+    #[test]
+    fn test_bce_with_logits() {
+        let output = arr![arr![-2.0, 0.5], arr![3.0, 1.0]].tracked();
+        let target = arr![arr![0.0, 1.0], arr![1.0, 0.0]];
+
+        let expect = binary_cross_entropy(Reduction::Mean)(&output.sigmoid(), &target);
+        let result = bce_with_logits(None, Reduction::Mean)(&output, &target);
+        assert_relative_eq!(result, expect, max_relative = 1e-6);
+
+        result.backward(None);
+        let gradient_expect = 0.25 * &(&output.sigmoid() - &target);
+        assert_relative_eq!(
+            output.gradient().to_owned().unwrap(),
+            gradient_expect,
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_binary_cross_entropy_saturated() {
+        let output = arr![arr![0.0, 1.0], arr![1.0, 0.0]].tracked();
+        let target = arr![arr![0.0, 1.0], arr![0.0, 1.0]];
+
+        let result = binary_cross_entropy(Reduction::None)(&output, &target);
+        assert!(result.values().iter().all(|l| l.is_finite()));
+
+        result.backward(None);
+        let gradient = output.gradient().to_owned().unwrap();
+        assert!(gradient.values().iter().all(|g| g.is_finite()));
+    }
+
+    #[test]
+    fn test_bce_with_logits_stable() {
+        let output = arr![arr![1000.0, -1000.0]];
+        let target = arr![arr![1.0, 0.0]];
+
+        let result = bce_with_logits(None, Reduction::None)(&output, &target);
+        assert_relative_eq!(result, arr![arr![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_pos_weight() {
+        let output = arr![arr![0.0, 0.0]];
+        let target = arr![arr![1.0, 0.0]];
+
+        let result = bce_with_logits(Some(arr![3.0, 3.0]), Reduction::None)(&output, &target);
+        let ln_2 = (2.0 as Float).ln();
+        assert_relative_eq!(result, arr![arr![3.0 * ln_2, ln_2]]);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let output = arr![arr![0.25, 0.75], arr![0.5, 0.5]];
+        let target = arr![arr![0.0, 1.0], arr![1.0, 0.0]];
+
+        let unweighted = cross_entropy(Reduction::None)(&output, &target);
+        let result = class_weighted(
+            cross_entropy(Reduction::None),
+            arr![2.0, 1.0],
+            Reduction::None,
+        )(&output, &target);
+        assert_relative_eq!(result, &unweighted * &arr![1.0, 2.0]);
+
+        let result = sample_weighted(&unweighted, &arr![0.0, 4.0], Reduction::None);
+        assert_relative_eq!(result, arr![0.0, 4.0 * unweighted[1]]);
+    }
+
+    #[test]
+    fn test_weighted_mean() {
+        let output = arr![arr![1.0, 2.0], arr![3.0, 5.0]];
+        let target = arr![arr![0.0, 0.0], arr![1.0, 1.0]];
+
+        let result =
+            class_weighted(l1(Reduction::None), arr![2.0, 1.0], Reduction::Mean)(&output, &target);
+        assert_relative_eq!(result, arr![(2.0 + 2.0 + 4.0 + 4.0) / 4.0]);
+
+        let loss = l1(Reduction::None)(&output, &target);
+        let result = sample_weighted(&loss, &arr![0.0, 4.0], Reduction::Mean);
+        assert_relative_eq!(result, arr![(8.0 + 16.0) / 4.0]);
+    }
+
+    #[test]
+    fn test_weighted_classes() {
+        let output = arr![arr![0.25, 0.75], arr![0.5, 0.5], arr![0.1, 0.9]].ln();
+        let target = arr![1.0, 0.0, 0.0];
+
+        // a loss for each sample is scaled by the weight of its target class
+        let result = class_weighted(
+            nll_loss(None, Reduction::None),
+            arr![2.0, 1.0],
+            Reduction::None,
+        )(&output, &target);
+        let expect = nll_loss(Some(arr![2.0, 1.0]), Reduction::None)(&output, &target);
+        assert_relative_eq!(result, expect);
+        assert_relative_eq!(
+            result,
+            arr![
+                -(0.75 as Float).ln(),
+                -2.0 * (0.5 as Float).ln(),
+                -2.0 * (0.1 as Float).ln()
+            ],
+            max_relative = 1e-6
+        );
+
+        let result = class_weighted(
+            nll_loss(None, Reduction::None),
+            arr![2.0, 1.0],
+            Reduction::Sum,
+        )(&output, &target);
+        let expect = nll_loss(Some(arr![2.0, 1.0]), Reduction::Sum)(&output, &target);
+        assert_relative_eq!(result, expect);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_weighted_reduced() {
+        let output = arr![arr![1.0, 2.0], arr![3.0, 5.0]];
+        let target = arr![arr![0.0, 0.0], arr![1.0, 1.0]];
+        class_weighted(l1(Reduction::Mean), arr![2.0, 1.0], Reduction::Mean)(&output, &target);
+    }
+}
@@ -1,16 +1,17 @@
 //! Initializers initialize the parameters of a model.
 
 use crate::numbers::*;
+use crate::random;
 
 use rand::Rng;
 
 /// A parameter initializer, which intializes parameters based on the input size.
 pub type Initializer = Box<dyn Fn(Float) -> Float>;
 
-/// Creates a He initializer closure.
+/// Creates a He initializer closure, which draws from the generator of the thread.
 pub fn he() -> Initializer {
     Box::new(|x| {
         let stddev = (2.0 / x).sqrt();
-        rand::thread_rng().gen_range(-stddev..=stddev)
+        random::with_generator(|g| g.gen_range(-stddev..=stddev))
     })
 }
@@ -0,0 +1,275 @@
+//! A multi-head attention layer, which computes scaled dot-product attention over several heads in parallel.
+
+use crate::array::*;
+use crate::initializer::Initializer;
+use crate::layer::dense::Dense;
+use crate::layer::positional::RotaryEmbedding;
+use crate::layer::{prefixed, Layer};
+use crate::numbers::*;
+
+/// The value added to the scores of masked positions, which is large enough for their attention weights to vanish.
+const MASK_VALUE: Float = -1e9;
+
+/// A multi-head attention layer, storing the query, key, value, and output projections.
+///
+/// The inputs have the dimensions batch size by sequence length by model size, and the model size is split evenly
+/// between the heads.
+pub struct MultiHeadAttention {
+    model_size: usize,
+    head_count: usize,
+    rotary: Option<RotaryEmbedding>,
+    query: Dense,
+    key: Dense,
+    value: Dense,
+    output: Dense,
+}
+
+impl MultiHeadAttention {
+    /// Constructs a new multi-head attention layer, with a given model size, and number of heads.
+    pub fn new(
+        model_size: usize,
+        head_count: usize,
+        initializer: &Initializer,
+    ) -> MultiHeadAttention {
+        assert!(
+            model_size.checked_rem(head_count) == Some(0),
+            "error: the model size {} must be divisible by the head count {}",
+            model_size,
+            head_count
+        );
+
+        MultiHeadAttention {
+            model_size,
+            head_count,
+            rotary: None,
+            query: Dense::new(model_size, model_size, initializer, None),
+            key: Dense::new(model_size, model_size, initializer, None),
+            value: Dense::new(model_size, model_size, initializer, None),
+            output: Dense::new(model_size, model_size, initializer, None),
+        }
+    }
+
+    /// Applies a rotary positional embedding to the queries, and keys of each head after their projections, so that
+    /// the attention scores depend on the relative positions of the queries, and keys. The head size must be even.
+    pub fn with_rotary(mut self) -> MultiHeadAttention {
+        self.rotary = Some(RotaryEmbedding::new(self.model_size / self.head_count));
+        self
+    }
+
+    /// Computes attention of the queries over the keys, and values.
+    ///
+    /// If `is_causal`, each query position only attends to key positions at, or before it. The `key_padding` has the
+    /// dimensions batch size by key length, and is nonzero for padded key positions, which are not attended to.
+    pub fn forward_masked(
+        &self,
+        query: &Array,
+        key: &Array,
+        value: &Array,
+        is_causal: bool,
+        key_padding: Option<&Array>,
+    ) -> Array {
+        let (query_dimensions, key_dimensions) = (query.dimensions(), key.dimensions());
+        assert!(
+            query_dimensions.len() == 3
+                && key_dimensions.len() == 3
+                && key_dimensions == value.dimensions()
+                && query_dimensions[0] == key_dimensions[0],
+            "error: the query {:?}, key {:?}, and value {:?} dimensions must be batch size by sequence length by \
+             model size",
+            query_dimensions,
+            key_dimensions,
+            value.dimensions()
+        );
+
+        let (batch_size, query_length, key_length) =
+            (query_dimensions[0], query_dimensions[1], key_dimensions[1]);
+        let mask = attention_mask(batch_size, query_length, key_length, is_causal, key_padding);
+
+        let queries = self.query.forward(query.clone()).split(self.head_count);
+        let keys = self.key.forward(key.clone()).split(self.head_count);
+        let values = self.value.forward(value.clone()).split(self.head_count);
+
+        let head_size = *queries[0].dimensions().last().unwrap();
+        let scale = 1.0 / (head_size as Float).sqrt();
+        let heads: Vec<Array> = queries
+            .iter()
+            .zip(&keys)
+            .zip(&values)
+            .map(|((q, k), v)| {
+                let (q, k) = match &self.rotary {
+                    Some(rotary) => (rotary.forward(q.clone()), rotary.forward(k.clone())),
+                    None => (q.clone(), k.clone()),
+                };
+
+                let mut scores = &Array::matmul((&q, false), (&k, true), None) * scale;
+                if let Some(mask) = &mask {
+                    scores = &scores + mask;
+                }
+
+                let weights = scores.log_softmax().exp();
+                Array::matmul((&weights, false), (v, false), None)
+            })
+            .collect();
+
+        self.output
+            .forward(Array::concat(&heads.iter().collect::<Vec<&Array>>()))
+    }
+}
+
+/// Constructs the mask added to the attention scores, which is zero for attended positions.
+fn attention_mask(
+    batch_size: usize,
+    query_length: usize,
+    key_length: usize,
+    is_causal: bool,
+    key_padding: Option<&Array>,
+) -> Option<Array> {
+    let causal_mask = if is_causal {
+        let values = (0..query_length * key_length)
+            .map(|i| {
+                if i % key_length > i / key_length {
+                    MASK_VALUE
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<Float>>();
+        Some(Array::from((vec![query_length, key_length], values)))
+    } else {
+        None
+    };
+
+    let padding_mask = key_padding.map(|padding| {
+        assert!(
+            padding.dimensions() == [batch_size, key_length],
+            "error: the key padding dimensions {:?} must be {:?}",
+            padding.dimensions(),
+            [batch_size, key_length]
+        );
+
+        let values = padding
+            .values()
+            .iter()
+            .map(|&x| if x != 0.0 { MASK_VALUE } else { 0.0 })
+            .collect::<Vec<Float>>();
+        Array::from((vec![batch_size, 1, key_length], values))
+    });
+
+    match (causal_mask, padding_mask) {
+        (Some(causal), Some(padding)) => Some(&causal + &padding),
+        (causal, padding) => causal.or(padding),
+    }
+}
+
+impl Layer for MultiHeadAttention {
+    fn forward(&self, input: Array) -> Array {
+        self.forward_masked(&input, &input, &input, false, None)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        let mut parameters = self.query.parameters();
+        parameters.append(&mut self.key.parameters());
+        parameters.append(&mut self.value.parameters());
+        parameters.append(&mut self.output.parameters());
+        parameters
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        let mut parameters = prefixed("query", self.query.named_parameters());
+        parameters.append(&mut prefixed("key", self.key.named_parameters()));
+        parameters.append(&mut prefixed("value", self.value.named_parameters()));
+        parameters.append(&mut prefixed("output", self.output.named_parameters()));
+        parameters
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.len() == 3,
+            "error: the input dimensions {:?} must be batch size by sequence length by model size",
+            input_shape
+        );
+
+        self.query.output_shape(input_shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(attention: &mut MultiHeadAttention, size: usize) {
+        let identity = Array::from((
+            vec![size, size],
+            (0..size * size)
+                .map(|i| if i / size == i % size { 1.0 } else { 0.0 })
+                .collect::<Vec<Float>>(),
+        ))
+        .tracked();
+
+        for (i, parameter) in attention.parameters().into_iter().enumerate() {
+            *parameter = if i % 2 == 0 {
+                identity.clone()
+            } else {
+                Array::from(vec![size]).tracked()
+            };
+        }
+    }
+
+    #[test]
+    fn test_attention() {
+        let mut l1 = MultiHeadAttention::new(2, 1, &crate::initializer::he());
+        identity(&mut l1, 2);
+
+        // the first query matches only the first key
+        let query = arr![arr![arr![100.0, 0.0], arr![0.0, 0.0]]];
+        let key = arr![arr![arr![1.0, 0.0], arr![0.0, 1.0]]];
+        let value = arr![arr![arr![1.0, 2.0], arr![3.0, 4.0]]];
+
+        let result = l1.forward_masked(&query, &key, &value, false, None);
+        assert_relative_eq!(
+            result,
+            arr![arr![arr![1.0, 2.0], arr![2.0, 3.0]]],
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_masks() {
+        let mut l1 = MultiHeadAttention::new(2, 2, &crate::initializer::he());
+        identity(&mut l1, 2);
+
+        let input = arr![
+            arr![arr![1.0, 2.0], arr![3.0, 4.0], arr![5.0, 6.0]],
+            arr![arr![0.0, 0.0], arr![0.0, 0.0], arr![0.0, 0.0]]
+        ];
+
+        // the first position may only attend to itself
+        let result = l1.forward_masked(&input, &input, &input, true, None);
+        assert_relative_eq!(result[0], 1.0);
+        assert_relative_eq!(result[1], 2.0);
+
+        // the padded positions are never attended to
+        let padding = arr![arr![0.0, 1.0, 1.0], arr![0.0, 0.0, 1.0]];
+        let result = l1.forward_masked(&input, &input, &input, false, Some(&padding));
+        for i in 0..3 {
+            assert_relative_eq!(result[2 * i], 1.0);
+            assert_relative_eq!(result[2 * i + 1], 2.0);
+        }
+    }
+
+    #[test]
+    fn test_rotary() {
+        let mut l1 = MultiHeadAttention::new(2, 1, &crate::initializer::he()).with_rotary();
+        identity(&mut l1, 2);
+
+        // without rotation, every query would attend equally to the identical keys, but each query now prefers the
+        // keys nearest to it
+        let input = arr![arr![arr![1.0, 0.0], arr![1.0, 0.0]]];
+        let value = arr![arr![arr![1.0, 0.0], arr![0.0, 1.0]]];
+
+        let result = l1.forward_masked(&input, &input, &value, false, None);
+        assert!(result[0] > result[1]);
+        assert_relative_eq!(result[0], result[3], max_relative = 1e-6);
+        assert_relative_eq!(result[1], result[2], max_relative = 1e-6);
+    }
+}
@@ -0,0 +1,297 @@
+//! A batch normalization layer, which normalizes each feature by statistics over the batch, and applies
+//! y = gamma * x + beta.
+
+use crate::array::*;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A batch normalization layer, storing the parameters of the layer, and the running statistics used for inference.
+///
+/// The input has the dimensions batch size by features, or batch size by depth by image rows by image columns, where
+/// each feature, or depth, is normalized over the batch, and any image dimensions.
+pub struct BatchNorm {
+    gamma: Array,
+    beta: Array,
+    running_mean: RefCell<Array>,
+    running_variance: RefCell<Array>,
+    momentum: Float,
+    epsilon: Float,
+    is_training: bool,
+}
+
+impl BatchNorm {
+    /// Constructs a new batch normalization layer, for a given number of features.
+    ///
+    /// The running statistics are updated by `running = (1 - momentum) * running + momentum * batch`, and `epsilon`
+    /// is added to the variance to avoid dividing by zero.
+    pub fn new(feature_count: usize, momentum: Float, epsilon: Float) -> BatchNorm {
+        BatchNorm {
+            gamma: Array::from((vec![feature_count], vec![1.0; feature_count])).tracked(),
+            beta: Array::from(vec![feature_count]).tracked(),
+            running_mean: RefCell::new(Array::from(vec![feature_count])),
+            running_variance: RefCell::new(Array::from((
+                vec![feature_count],
+                vec![1.0; feature_count],
+            ))),
+            momentum,
+            epsilon,
+            is_training: true,
+        }
+    }
+
+    /// Computes the forward pass using the statistics of the batch, and updates the running statistics.
+    fn forward_training(&self, input: &Array) -> Array {
+        let dimensions = input.dimensions();
+        let feature_count = dimensions[1];
+        let image_size: usize = dimensions[2..].iter().product();
+        let count = input.values().len() / feature_count;
+        let feature = move |i: usize| (i / image_size) % feature_count;
+
+        let mut mean = vec![0.0; feature_count];
+        for (i, x) in input.values().iter().enumerate() {
+            mean[feature(i)] += x / count as Float;
+        }
+
+        let mut variance = vec![0.0; feature_count];
+        for (i, x) in input.values().iter().enumerate() {
+            variance[feature(i)] += (x - mean[feature(i)]).powi(2) / count as Float;
+        }
+
+        // the running variance is unbiased
+        let correction = if count > 1 {
+            count as Float / (count - 1) as Float
+        } else {
+            1.0
+        };
+
+        let momentum = self.momentum;
+        let update = |running: &RefCell<Array>, batch: &[Float], scale: Float| {
+            let values = running
+                .borrow()
+                .values()
+                .iter()
+                .zip(batch)
+                .map(|(r, b)| (1.0 - momentum) * r + momentum * scale * b)
+                .collect::<Vec<Float>>();
+            running.replace(Array::from((vec![feature_count], values)));
+        };
+
+        update(&self.running_mean, &mean, 1.0);
+        update(&self.running_variance, &variance, correction);
+
+        let inverse_deviations: Rc<Vec<Float>> = Rc::new(
+            variance
+                .iter()
+                .map(|v| 1.0 / (v + self.epsilon).sqrt())
+                .collect(),
+        );
+        let normalized: Rc<Vec<Float>> = Rc::new(
+            input
+                .values()
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (x - mean[feature(i)]) * inverse_deviations[feature(i)])
+                .collect(),
+        );
+
+        let cached = Rc::clone(&normalized);
+        let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+            let (gamma, beta) = (x[1].values(), x[2].values());
+            let values = cached
+                .iter()
+                .enumerate()
+                .map(|(i, n)| gamma[feature(i)] * n + beta[feature(i)])
+                .collect::<Vec<Float>>();
+            Array::from((x[0].dimensions().to_vec(), values))
+        });
+
+        let backward_op: BackwardOp = Rc::new(move |c, t, x| {
+            let delta = x.values();
+            let mut gamma_delta = vec![0.0; feature_count];
+            let mut beta_delta = vec![0.0; feature_count];
+            for (i, (d, n)) in delta.iter().zip(normalized.iter()).enumerate() {
+                gamma_delta[feature(i)] += d * n;
+                beta_delta[feature(i)] += d;
+            }
+
+            let gamma = c[1].values();
+            let input_delta = delta
+                .iter()
+                .zip(normalized.iter())
+                .enumerate()
+                .map(|(i, (d, n))| {
+                    let k = feature(i);
+                    gamma[k] * inverse_deviations[k] / count as Float
+                        * (count as Float * d - beta_delta[k] - n * gamma_delta[k])
+                })
+                .collect::<Vec<Float>>();
+
+            vec![
+                if t[0] {
+                    Some(Array::from((c[0].dimensions().to_vec(), input_delta)))
+                } else {
+                    None
+                },
+                if t[1] {
+                    Some(Array::from((vec![feature_count], gamma_delta)))
+                } else {
+                    None
+                },
+                if t[2] {
+                    Some(Array::from((vec![feature_count], beta_delta)))
+                } else {
+                    None
+                },
+            ]
+        });
+
+        Array::named_op(
+            "batch_norm",
+            &[input, &self.gamma, &self.beta],
+            op,
+            Some(backward_op),
+        )
+    }
+
+    /// Computes the forward pass using the running statistics.
+    fn forward_inference(&self, input: &Array) -> Array {
+        let dimensions = input.dimensions();
+        // broadcast each feature over any image dimensions
+        let feature_dimensions: Vec<usize> = vec![dimensions[1]]
+            .into_iter()
+            .chain(vec![1; dimensions.len() - 2])
+            .collect();
+
+        let mean = self
+            .running_mean
+            .borrow()
+            .reshape(feature_dimensions.clone());
+        let inverse_deviations = Array::from((
+            feature_dimensions.clone(),
+            self.running_variance
+                .borrow()
+                .values()
+                .iter()
+                .map(|v| 1.0 / (v + self.epsilon).sqrt())
+                .collect::<Vec<Float>>(),
+        ));
+
+        let normalized = &(input - &mean) * &inverse_deviations;
+        &(&normalized * &self.gamma.reshape(feature_dimensions.clone()))
+            + &self.beta.reshape(feature_dimensions)
+    }
+}
+
+impl Layer for BatchNorm {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        if self.is_training {
+            self.forward_training(&input)
+        } else {
+            self.forward_inference(&input)
+        }
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["gamma", "beta"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.len() >= 2 && input_shape[1] == self.gamma.dimensions()[0],
+            "error: the input dimensions {:?} must be batch size by {} features",
+            input_shape,
+            self.gamma.dimensions()[0]
+        );
+
+        input_shape.to_vec()
+    }
+
+    fn buffers(&mut self) -> Vec<&mut Array> {
+        vec![self.running_mean.get_mut(), self.running_variance.get_mut()]
+    }
+
+    fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["running_mean", "running_variance"], self.buffers())
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.is_training = is_training;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training() {
+        let mut l1 = BatchNorm::new(2, 0.5, 0.0);
+        let input = arr![arr![1.0, 10.0], arr![3.0, 20.0], arr![5.0, 30.0]];
+
+        let result = l1.forward(input);
+        let deviation = (8.0 as Float / 3.0).sqrt();
+        assert_relative_eq!(
+            result,
+            arr![
+                arr![
+                    -2.0 / deviation,
+                    -1.0 / (200.0 as Float / 3.0).sqrt() * 10.0
+                ],
+                arr![0.0, 0.0],
+                arr![2.0 / deviation, 1.0 / (200.0 as Float / 3.0).sqrt() * 10.0]
+            ],
+            max_relative = 1e-6
+        );
+
+        let buffers = l1.buffers();
+        assert_relative_eq!(*buffers[0], arr![1.5, 10.0]);
+        assert_relative_eq!(*buffers[1], arr![2.5, 50.5]);
+    }
+
+    #[test]
+    fn test_inference() {
+        let mut l1 = BatchNorm::new(2, 0.1, 0.0);
+        *l1.buffers()[0] = arr![1.0, -1.0];
+        *l1.buffers()[1] = arr![4.0, 0.25];
+        l1.set_training(false);
+
+        let input = arr![arr![arr![arr![3.0]], arr![arr![0.0]]]];
+        let result = l1.forward(input);
+        assert_eq!(result, arr![arr![arr![arr![1.0]], arr![arr![2.0]]]]);
+
+        // inference does not update the running statistics
+        assert_eq!(*l1.buffers()[0], arr![1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_image() {
+        let l1 = BatchNorm::new(2, 0.1, 1e-5);
+        let input = Array::from((
+            vec![2, 2, 2, 3],
+            (0..24)
+                .map(|x| (x * x % 7) as Float)
+                .collect::<Vec<Float>>(),
+        ));
+
+        let result = l1.forward(input);
+        for k in 0..2 {
+            let values: Vec<Float> = (0..24)
+                .filter(|i| (i / 6) % 2 == k)
+                .map(|i| result[i])
+                .collect();
+            let mean = values.iter().sum::<Float>() / 12.0;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<Float>() / 12.0;
+            assert_abs_diff_eq!(mean, 0.0, epsilon = 1e-6);
+            assert_abs_diff_eq!(variance, 1.0, epsilon = 1e-4);
+        }
+    }
+}
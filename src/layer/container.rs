@@ -0,0 +1,291 @@
+//! Container layers, which compose other layers into non-sequential models, such as skip connections, and branches.
+//!
+//! Since containers are layers themselves, they can be nested, and their parameters include those of every inner
+//! layer.
+
+use crate::array::*;
+use crate::layer::{prefixed, Layer};
+use crate::random::Generator;
+
+/// A merge function, which combines the outputs of the branches of a parallel layer into a single output.
+pub type Merge = Box<dyn Fn(&[Array]) -> Array>;
+
+/// Creates a merge function closure, which sums the outputs of the branches.
+pub fn sum() -> Merge {
+    Box::new(|outputs| {
+        let (first, outputs) = outputs
+            .split_first()
+            .expect("error: cannot merge zero outputs");
+        outputs.iter().fold(first.clone(), |sum, x| &sum + x)
+    })
+}
+
+/// Creates a merge function closure, which concatenates the outputs of the branches along the last dimension.
+pub fn concat() -> Merge {
+    Box::new(|outputs| Array::concat(&outputs.iter().collect::<Vec<&Array>>()))
+}
+
+/// A sequential layer, which applies the inner layers in order.
+pub struct Sequential {
+    layers: Vec<Box<dyn Layer>>,
+}
+
+impl Sequential {
+    /// Constructs a new sequential layer, given the inner layers.
+    pub fn new(layers: Vec<Box<dyn Layer>>) -> Sequential {
+        Sequential { layers }
+    }
+}
+
+impl Layer for Sequential {
+    fn forward(&self, input: Array) -> Array {
+        self.layers
+            .iter()
+            .fold(input, |input, layer| layer.forward(input))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.parameters())
+            .collect()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.layers
+            .iter()
+            .fold(input_shape.to_vec(), |shape, layer| {
+                layer.output_shape(&shape)
+            })
+    }
+
+    fn buffers(&mut self) -> Vec<&mut Array> {
+        self.layers.iter_mut().flat_map(|l| l.buffers()).collect()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, l)| prefixed(&format!("layers.{}", i), l.named_parameters()))
+            .collect()
+    }
+
+    fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, l)| prefixed(&format!("layers.{}", i), l.named_buffers()))
+            .collect()
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.generators())
+            .collect()
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.set_training(is_training);
+        }
+    }
+}
+
+/// A residual layer, which applies y = x + f(x) for an inner layer f, whose output has the dimensions of its input.
+pub struct Residual {
+    inner: Box<dyn Layer>,
+}
+
+impl Residual {
+    /// Constructs a new residual layer, given the inner layer.
+    pub fn new(inner: Box<dyn Layer>) -> Residual {
+        Residual { inner }
+    }
+}
+
+impl Layer for Residual {
+    fn forward(&self, input: Array) -> Array {
+        let output = self.inner.forward(input.clone());
+        assert!(
+            output.dimensions() == input.dimensions(),
+            "error: the residual output dimensions {:?} must match the input dimensions {:?}",
+            output.dimensions(),
+            input.dimensions()
+        );
+
+        &input + &output
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.inner.parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let output_shape = self.inner.output_shape(input_shape);
+        assert!(
+            output_shape == input_shape,
+            "error: the residual output dimensions {:?} must match the input dimensions {:?}",
+            output_shape,
+            input_shape
+        );
+
+        output_shape
+    }
+
+    fn buffers(&mut self) -> Vec<&mut Array> {
+        self.inner.buffers()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        prefixed("inner", self.inner.named_parameters())
+    }
+
+    fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        prefixed("inner", self.inner.named_buffers())
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        self.inner.generators()
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.inner.set_training(is_training);
+    }
+}
+
+/// A parallel layer, which applies each branch to the same input, and merges their outputs.
+pub struct Parallel {
+    branches: Vec<Box<dyn Layer>>,
+    merge: Merge,
+}
+
+impl Parallel {
+    /// Constructs a new parallel layer, given the branches, and the merge function of their outputs.
+    pub fn new(branches: Vec<Box<dyn Layer>>, merge: Merge) -> Parallel {
+        assert!(
+            !branches.is_empty(),
+            "error: a parallel layer must have at least one branch"
+        );
+
+        Parallel { branches, merge }
+    }
+}
+
+impl Layer for Parallel {
+    fn forward(&self, input: Array) -> Array {
+        let outputs: Vec<Array> = self
+            .branches
+            .iter()
+            .map(|b| b.forward(input.clone()))
+            .collect();
+        (self.merge)(&outputs)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.branches
+            .iter_mut()
+            .flat_map(|b| b.parameters())
+            .collect()
+    }
+
+    /// Computes the output dimensions by merging zero arrays with the output dimensions of each branch.
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let outputs: Vec<Array> = self
+            .branches
+            .iter()
+            .map(|b| Array::from(b.output_shape(input_shape)))
+            .collect();
+        (self.merge)(&outputs).dimensions().to_vec()
+    }
+
+    fn buffers(&mut self) -> Vec<&mut Array> {
+        self.branches.iter_mut().flat_map(|b| b.buffers()).collect()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.branches
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, b)| prefixed(&format!("branches.{}", i), b.named_parameters()))
+            .collect()
+    }
+
+    fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        self.branches
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, b)| prefixed(&format!("branches.{}", i), b.named_buffers()))
+            .collect()
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        self.branches
+            .iter_mut()
+            .flat_map(|b| b.generators())
+            .collect()
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        for branch in self.branches.iter_mut() {
+            branch.set_training(is_training);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::dense::Dense;
+    use crate::layer::shape::Flatten;
+
+    fn ones() -> crate::initializer::Initializer {
+        Box::new(|_| 1.0)
+    }
+
+    #[test]
+    fn test_residual() {
+        let l1 = Residual::new(Box::new(Dense::new(2, 2, &ones(), None)));
+        assert_eq!(l1.forward(arr![arr![1.0, 2.0]]), arr![arr![5.0, 6.0]]);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let l1 = Parallel::new(
+            vec![
+                Box::new(Dense::new(2, 1, &ones(), None)),
+                Box::new(Flatten::new()),
+            ],
+            concat(),
+        );
+        assert_eq!(l1.forward(arr![arr![1.0, 2.0]]), arr![arr![4.0, 1.0, 2.0]]);
+
+        let l2 = Parallel::new(
+            vec![Box::new(Flatten::new()), Box::new(Flatten::new())],
+            sum(),
+        );
+        assert_eq!(l2.forward(arr![arr![1.0, 2.0]]), arr![arr![2.0, 4.0]]);
+    }
+
+    #[test]
+    fn test_nested_parameters() {
+        let mut l1 = Sequential::new(vec![
+            Box::new(Dense::new(2, 2, &ones(), None)),
+            Box::new(Residual::new(Box::new(Parallel::new(
+                vec![
+                    Box::new(Dense::new(2, 2, &ones(), None)),
+                    Box::new(Dense::new(2, 2, &ones(), None)),
+                ],
+                sum(),
+            )))),
+        ]);
+        assert_eq!(l1.parameters().len(), 6);
+
+        // the first layer outputs 4, and each branch outputs 4 + 4 + 1, which are summed, and added to the input
+        assert_eq!(
+            l1.forward(arr![arr![1.0, 2.0]]),
+            arr![arr![4.0 + 2.0 * 9.0, 4.0 + 2.0 * 9.0]]
+        );
+    }
+}
@@ -1,14 +1,19 @@
-//! A convolutional neural network layer, which applies y = activation(x.conv(filters) + b).
+//! Convolutional neural network layers, which apply y = activation(x.conv(filters) + b) over one, two, or three
+//! spatial dimensions.
 
 use crate::activation::Activation;
 use crate::array::*;
 use crate::initializer::Initializer;
-use crate::layer::Layer;
+use crate::layer::{named, Layer};
 use crate::numbers::*;
+use crate::onnx::{Attribute, Graph};
+use crate::serialize::invalid_data;
+
+use std::io::Result;
 
 /// A convolutional neural network layer, storing the parameters of the layer.
 pub struct Conv {
-    stride_dimensions: (usize, usize),
+    strides: Vec<usize>,
     filters: Array,
     biases: Array,
     activation: Option<Activation>,
@@ -24,13 +29,36 @@ impl Conv {
         activation: Option<Activation>,
     ) -> Conv {
         let (filter_count, image_depth, filter_rows, filter_cols) = filter_dimensions;
+        let (stride_rows, stride_cols) = stride_dimensions;
 
-        let filter_dimensions = vec![filter_count, image_depth, filter_rows, filter_cols];
+        Conv::with_dimensions(
+            vec![filter_count, image_depth, filter_rows, filter_cols],
+            vec![stride_rows, stride_cols],
+            initializer,
+            activation,
+        )
+    }
+
+    /// Constructs a new convolutional layer over as many spatial dimensions as there are strides, where the filter
+    /// dimensions are filter count by image depth followed by the spatial dimensions of the filter.
+    fn with_dimensions(
+        filter_dimensions: Vec<usize>,
+        strides: Vec<usize>,
+        initializer: &Initializer,
+        activation: Option<Activation>,
+    ) -> Conv {
+        let filter_count = filter_dimensions[0];
         let filter_size = filter_dimensions.iter().product();
-        let input_size = image_depth * filter_rows * filter_cols;
+        let input_size = filter_size / filter_count;
+
+        // the biases are broadcast over the spatial dimensions of the output
+        let bias_dimensions = vec![filter_count]
+            .into_iter()
+            .chain(vec![1; strides.len()])
+            .collect();
 
         Conv {
-            stride_dimensions,
+            strides,
             filters: Array::from((
                 filter_dimensions,
                 (0..filter_size)
@@ -39,7 +67,7 @@ impl Conv {
             ))
             .tracked(),
             biases: Array::from((
-                vec![filter_count, 1, 1],
+                bias_dimensions,
                 (0..filter_count)
                     .map(|_| (*initializer)(input_size as Float))
                     .collect::<Vec<Float>>(),
@@ -52,9 +80,11 @@ impl Conv {
 
 impl Layer for Conv {
     fn forward(&self, input: Array) -> Array {
-        let result = &input.conv(&self.filters, self.stride_dimensions) + &self.biases;
+        self.output_shape(input.dimensions());
+
+        let result = &input.conv_nd(&self.filters, &self.strides) + &self.biases;
         match &self.activation {
-            Some(f) => f(result),
+            Some(f) => f.apply(result),
             None => result,
         }
     }
@@ -62,6 +92,176 @@ impl Layer for Conv {
     fn parameters(&mut self) -> Vec<&mut Array> {
         vec![&mut self.filters, &mut self.biases]
     }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["filters", "biases"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let spatial_count = self.strides.len();
+        let dimension_count = input_shape.len();
+        let filter_dimensions = self.filters.dimensions();
+        assert!(
+            dimension_count > spatial_count
+                && input_shape[dimension_count - spatial_count - 1] == filter_dimensions[1]
+                && filter_dimensions[2..]
+                    .iter()
+                    .zip(&input_shape[dimension_count - spatial_count..])
+                    .all(|(f, i)| f <= i),
+            "error: the input dimensions {:?} must be an optional batch size by {} channels by {} spatial \
+             dimensions of at least {:?}",
+            input_shape,
+            filter_dimensions[1],
+            spatial_count,
+            &filter_dimensions[2..]
+        );
+
+        input_shape[0..dimension_count - spatial_count - 1]
+            .iter()
+            .copied()
+            .chain(vec![filter_dimensions[0]])
+            .chain(
+                input_shape[dimension_count - spatial_count..]
+                    .iter()
+                    .zip(&filter_dimensions[2..])
+                    .zip(&self.strides)
+                    .map(|((i, f), s)| (i - f) / s + 1),
+            )
+            .collect()
+    }
+
+    fn to_onnx(&self, graph: &mut Graph, input: &str, input_shape: &[usize]) -> Result<String> {
+        if input_shape.len() != self.strides.len() + 2 {
+            return Err(invalid_data(format!(
+                "a convolutional layer can only be exported for batched inputs, but got {:?}",
+                input_shape
+            )));
+        }
+
+        let to_ints = |values: &[usize]| values.iter().map(|&v| v as i64).collect();
+        let filter_dimensions = self.filters.dimensions();
+        let filters = graph.initializer("filters", filter_dimensions, self.filters.values());
+        // the biases are one-dimensional in ONNX, rather than broadcast over the spatial dimensions
+        let biases = graph.initializer("biases", &filter_dimensions[0..1], self.biases.values());
+        let output = graph.node(
+            "Conv",
+            &[input, &filters, &biases],
+            &[
+                (
+                    "kernel_shape",
+                    Attribute::Ints(to_ints(&filter_dimensions[2..])),
+                ),
+                ("strides", Attribute::Ints(to_ints(&self.strides))),
+            ],
+        );
+        graph.activation(self.activation.as_ref(), &output)
+    }
+}
+
+/// A one-dimensional convolutional layer, for inputs of dimensions channels by length, such as time series.
+pub struct Conv1d {
+    conv: Conv,
+}
+
+impl Conv1d {
+    /// Constructs a new one-dimensional convolutional layer, with given dimensions.
+    /// The filter dimensions are filter count by channels by filter length.
+    pub fn new(
+        filter_dimensions: (usize, usize, usize),
+        stride: usize,
+        initializer: &Initializer,
+        activation: Option<Activation>,
+    ) -> Conv1d {
+        let (filter_count, channels, filter_length) = filter_dimensions;
+
+        Conv1d {
+            conv: Conv::with_dimensions(
+                vec![filter_count, channels, filter_length],
+                vec![stride],
+                initializer,
+                activation,
+            ),
+        }
+    }
+}
+
+impl Layer for Conv1d {
+    fn forward(&self, input: Array) -> Array {
+        self.conv.forward(input)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.conv.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.conv.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.conv.output_shape(input_shape)
+    }
+
+    fn to_onnx(&self, graph: &mut Graph, input: &str, input_shape: &[usize]) -> Result<String> {
+        self.conv.to_onnx(graph, input, input_shape)
+    }
+}
+
+/// A three-dimensional convolutional layer, for inputs of dimensions channels by depth by rows by columns, such as
+/// volumetric images.
+pub struct Conv3d {
+    conv: Conv,
+}
+
+impl Conv3d {
+    /// Constructs a new three-dimensional convolutional layer, with given dimensions.
+    /// The filter dimensions are filter count by channels by filter depth by filter rows by filter columns.
+    pub fn new(
+        filter_dimensions: (usize, usize, usize, usize, usize),
+        stride_dimensions: (usize, usize, usize),
+        initializer: &Initializer,
+        activation: Option<Activation>,
+    ) -> Conv3d {
+        let (filter_count, channels, filter_depth, filter_rows, filter_cols) = filter_dimensions;
+        let (stride_depth, stride_rows, stride_cols) = stride_dimensions;
+
+        Conv3d {
+            conv: Conv::with_dimensions(
+                vec![
+                    filter_count,
+                    channels,
+                    filter_depth,
+                    filter_rows,
+                    filter_cols,
+                ],
+                vec![stride_depth, stride_rows, stride_cols],
+                initializer,
+                activation,
+            ),
+        }
+    }
+}
+
+impl Layer for Conv3d {
+    fn forward(&self, input: Array) -> Array {
+        self.conv.forward(input)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.conv.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.conv.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.conv.output_shape(input_shape)
+    }
+
+    fn to_onnx(&self, graph: &mut Graph, input: &str, input_shape: &[usize]) -> Result<String> {
+        self.conv.to_onnx(graph, input, input_shape)
+    }
 }
 
 #[cfg(test)]
@@ -126,4 +326,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_dimensions() {
+        let initializer = initializer::he();
+        let l1 = Conv1d::new((4, 2, 3), 2, &initializer, None);
+        assert_eq!(
+            l1.forward(Array::from(vec![5, 2, 9])).dimensions(),
+            &[5, 4, 4]
+        );
+
+        let l2 = Conv3d::new((3, 2, 2, 2, 3), (1, 2, 1), &initializer, None);
+        assert_eq!(
+            l2.forward(Array::from(vec![2, 2, 4, 5, 6])).dimensions(),
+            &[2, 3, 3, 2, 4]
+        );
+    }
 }
@@ -3,24 +3,28 @@
 use crate::activation::Activation;
 use crate::array::*;
 use crate::initializer::Initializer;
-use crate::layer::Layer;
+use crate::layer::{named, Layer};
 use crate::numbers::*;
+use crate::onnx::{Attribute, Graph};
+use crate::serialize::invalid_data;
+
+use std::io::Result;
 
 /// A fully-connected neural network layer, storing the parameters of the layer.
-pub struct Dense<'a> {
+pub struct Dense {
     weights: Array,
     biases: Array,
-    activation: Option<&'a Activation>,
+    activation: Option<Activation>,
 }
 
-impl<'a> Dense<'a> {
+impl Dense {
     /// Constructs a new dense layer, with a given input, and output size.
     pub fn new(
         input_size: usize,
         output_size: usize,
-        initializer: &'_ Initializer,
-        activation: Option<&'a Activation>,
-    ) -> Dense<'a> {
+        initializer: &Initializer,
+        activation: Option<Activation>,
+    ) -> Dense {
         Dense {
             weights: Array::from((
                 vec![output_size, input_size],
@@ -41,11 +45,13 @@ impl<'a> Dense<'a> {
     }
 }
 
-impl Layer for Dense<'_> {
+impl Layer for Dense {
     fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
         let result = Array::matmul((&input, false), (&self.weights, true), Some(&self.biases));
         match &self.activation {
-            Some(f) => f(result),
+            Some(f) => f.apply(result),
             None => result,
         }
     }
@@ -53,6 +59,44 @@ impl Layer for Dense<'_> {
     fn parameters(&mut self) -> Vec<&mut Array> {
         vec![&mut self.weights, &mut self.biases]
     }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["weights", "biases"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let (output_size, input_size) =
+            (self.weights.dimensions()[0], self.weights.dimensions()[1]);
+        assert!(
+            input_shape.last() == Some(&input_size),
+            "error: the input dimensions {:?} must end with the input size {}",
+            input_shape,
+            input_size
+        );
+
+        let mut output_shape = input_shape.to_vec();
+        *output_shape.last_mut().unwrap() = output_size;
+        output_shape
+    }
+
+    fn to_onnx(&self, graph: &mut Graph, input: &str, input_shape: &[usize]) -> Result<String> {
+        if input_shape.len() != 2 {
+            return Err(invalid_data(format!(
+                "a dense layer can only be exported for inputs of batch size by input size, but got {:?}",
+                input_shape
+            )));
+        }
+
+        let weights =
+            graph.initializer("weights", self.weights.dimensions(), self.weights.values());
+        let biases = graph.initializer("biases", self.biases.dimensions(), self.biases.values());
+        let output = graph.node(
+            "Gemm",
+            &[input, &weights, &biases],
+            &[("transB", Attribute::Int(1))],
+        );
+        graph.activation(self.activation.as_ref(), &output)
+    }
 }
 
 #[cfg(test)]
@@ -72,8 +116,12 @@ mod tests {
         let hidden_size = 16;
         let output_size = 1;
         let initializer = initializer::he();
-        let sigmoid = activation::sigmoid();
-        let mut l1 = Dense::new(input_size, hidden_size, &initializer, Some(&sigmoid));
+        let mut l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::sigmoid()),
+        );
         let mut l2 = Dense::new(hidden_size, output_size, &initializer, None);
 
         for _ in 0..8 {
@@ -0,0 +1,341 @@
+//! Dropout layers, which randomly drop values during training to regularise the network, and pass the input through
+//! during inference.
+
+use crate::array::*;
+use crate::layer::Layer;
+use crate::numbers::*;
+use crate::onnx::Graph;
+
+use crate::random::{self, Generator};
+
+use rand::Rng;
+
+use std::cell::RefCell;
+use std::io::Result;
+
+/// The negative saturation value of the SELU activation, which alpha dropout sets dropped values to.
+const SELU_SATURATION: Float = -1.758_099_340_847_376_6;
+
+/// Constructs the random number generator of a dropout layer, from a seed if specified, or from the generator of the
+/// thread otherwise.
+fn seeded_rng(seed: Option<u64>) -> RefCell<Generator> {
+    RefCell::new(random::generator(seed))
+}
+
+/// Samples whether each of `count` values is kept, where each value is dropped with the given probability.
+fn sample_mask(rng: &RefCell<Generator>, count: usize, probability: Float) -> Vec<bool> {
+    let mut rng = rng.borrow_mut();
+    (0..count)
+        .map(|_| rng.gen::<Float>() >= probability)
+        .collect()
+}
+
+/// Asserts that the probability of dropping a value is within [0, 1).
+fn assert_probability(probability: Float) {
+    assert!(
+        (0.0..1.0).contains(&probability),
+        "error: the dropout probability {} must be at least 0, and less than 1",
+        probability
+    );
+}
+
+/// A dropout layer, which zeroes each value with a given probability during training, and scales the kept values by
+/// 1 / (1 - probability), so that inference does not need to rescale.
+pub struct Dropout {
+    probability: Float,
+    rng: RefCell<Generator>,
+    is_training: bool,
+}
+
+impl Dropout {
+    /// Constructs a new dropout layer, dropping values with the given probability.
+    ///
+    /// The mask is sampled from a random number generator seeded by `seed` if specified, for reproducible runs.
+    pub fn new(probability: Float, seed: Option<u64>) -> Dropout {
+        assert_probability(probability);
+        Dropout {
+            probability,
+            rng: seeded_rng(seed),
+            is_training: true,
+        }
+    }
+}
+
+impl Layer for Dropout {
+    fn forward(&self, input: Array) -> Array {
+        if !self.is_training || self.probability == 0.0 {
+            return input;
+        }
+
+        let scale = 1.0 / (1.0 - self.probability);
+        let mask: Vec<Float> = sample_mask(&self.rng, input.values().len(), self.probability)
+            .into_iter()
+            .map(|keep| if keep { scale } else { 0.0 })
+            .collect();
+
+        &input * &Array::from((input.dimensions().to_vec(), mask))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        input_shape.to_vec()
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        vec![self.rng.get_mut()]
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.is_training = is_training;
+    }
+
+    fn to_onnx(&self, _graph: &mut Graph, input: &str, _input_shape: &[usize]) -> Result<String> {
+        Ok(input.to_string())
+    }
+}
+
+/// A spatial dropout layer, which zeroes entire channels of each sample with a given probability during training,
+/// and scales the kept channels by 1 / (1 - probability).
+///
+/// The input has the dimensions batch size by channels, followed by any image dimensions.
+pub struct Dropout2d {
+    probability: Float,
+    rng: RefCell<Generator>,
+    is_training: bool,
+}
+
+impl Dropout2d {
+    /// Constructs a new spatial dropout layer, dropping channels with the given probability.
+    ///
+    /// The mask is sampled from a random number generator seeded by `seed` if specified, for reproducible runs.
+    pub fn new(probability: Float, seed: Option<u64>) -> Dropout2d {
+        assert_probability(probability);
+        Dropout2d {
+            probability,
+            rng: seeded_rng(seed),
+            is_training: true,
+        }
+    }
+}
+
+impl Layer for Dropout2d {
+    fn forward(&self, input: Array) -> Array {
+        let dimensions = self.output_shape(input.dimensions());
+
+        if !self.is_training || self.probability == 0.0 {
+            return input;
+        }
+
+        // broadcast the mask of each channel over any image dimensions
+        let mask_dimensions: Vec<usize> = dimensions[0..2]
+            .iter()
+            .copied()
+            .chain(vec![1; dimensions.len() - 2])
+            .collect();
+
+        let scale = 1.0 / (1.0 - self.probability);
+        let mask: Vec<Float> =
+            sample_mask(&self.rng, dimensions[0] * dimensions[1], self.probability)
+                .into_iter()
+                .map(|keep| if keep { scale } else { 0.0 })
+                .collect();
+
+        &input * &Array::from((mask_dimensions, mask))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.len() >= 2,
+            "error: the input dimensions {:?} must be batch size by channels",
+            input_shape
+        );
+
+        input_shape.to_vec()
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        vec![self.rng.get_mut()]
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.is_training = is_training;
+    }
+
+    fn to_onnx(&self, _graph: &mut Graph, input: &str, _input_shape: &[usize]) -> Result<String> {
+        Ok(input.to_string())
+    }
+}
+
+/// An alpha dropout layer for self-normalizing networks, which sets each value to the negative saturation of SELU
+/// with a given probability during training, and applies an affine transformation to keep the mean, and variance of
+/// the input.
+pub struct AlphaDropout {
+    probability: Float,
+    rng: RefCell<Generator>,
+    is_training: bool,
+}
+
+impl AlphaDropout {
+    /// Constructs a new alpha dropout layer, dropping values with the given probability.
+    ///
+    /// The mask is sampled from a random number generator seeded by `seed` if specified, for reproducible runs.
+    pub fn new(probability: Float, seed: Option<u64>) -> AlphaDropout {
+        assert_probability(probability);
+        AlphaDropout {
+            probability,
+            rng: seeded_rng(seed),
+            is_training: true,
+        }
+    }
+}
+
+impl Layer for AlphaDropout {
+    fn forward(&self, input: Array) -> Array {
+        if !self.is_training || self.probability == 0.0 {
+            return input;
+        }
+
+        let p = self.probability;
+        let a = ((1.0 - p) * (1.0 + p * SELU_SATURATION.powi(2))).powf(-0.5);
+        let b = -a * SELU_SATURATION * p;
+
+        // y = a * (x * mask + saturation * (1 - mask)) + b
+        let (scales, offsets): (Vec<Float>, Vec<Float>) =
+            sample_mask(&self.rng, input.values().len(), p)
+                .into_iter()
+                .map(|keep| {
+                    if keep {
+                        (a, b)
+                    } else {
+                        (0.0, a * SELU_SATURATION + b)
+                    }
+                })
+                .unzip();
+
+        let dimensions = input.dimensions().to_vec();
+        &(&input * &Array::from((dimensions.clone(), scales))) + &Array::from((dimensions, offsets))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        input_shape.to_vec()
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        vec![self.rng.get_mut()]
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.is_training = is_training;
+    }
+
+    fn to_onnx(&self, _graph: &mut Graph, input: &str, _input_shape: &[usize]) -> Result<String> {
+        Ok(input.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_dropout() {
+        let l1 = Dropout::new(0.5, Some(0));
+        let input = Array::from((vec![10, 10], vec![3.0; 100])).tracked();
+
+        let result = l1.forward(input.clone());
+        assert!(result.values().iter().all(|&x| x == 0.0 || x == 6.0));
+        assert!(result.values().contains(&0.0));
+        assert!(result.values().contains(&6.0));
+
+        // the gradient is the mask
+        result.backward(None);
+        let gradient = input.gradient().to_owned().unwrap();
+        for (g, x) in gradient.values().iter().zip(result.values()) {
+            assert_eq!(*g, x / 3.0);
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        let input = Array::from((vec![4, 8], vec![1.0; 32]));
+        let l1 = Dropout::new(0.5, Some(7));
+        let l2 = Dropout::new(0.5, Some(7));
+        assert_eq!(l1.forward(input.clone()), l2.forward(input.clone()));
+        assert_eq!(l1.forward(input.clone()), l2.forward(input.clone()));
+
+        let l3 = AlphaDropout::new(0.5, Some(7));
+        let l4 = AlphaDropout::new(0.5, Some(7));
+        assert_eq!(l3.forward(input.clone()), l4.forward(input));
+    }
+
+    #[test]
+    fn test_inference() {
+        let input = arr![arr![1.0, -2.0], arr![3.0, 4.0]];
+
+        let mut l1 = Dropout::new(0.9, None);
+        l1.set_training(false);
+        assert_eq!(l1.forward(input.clone()), input);
+
+        let mut l2 = Dropout2d::new(0.9, None);
+        l2.set_training(false);
+        assert_eq!(l2.forward(input.clone()), input);
+
+        let mut l3 = AlphaDropout::new(0.9, None);
+        l3.set_training(false);
+        assert_eq!(l3.forward(input.clone()), input);
+    }
+
+    #[test]
+    fn test_dropout2d() {
+        let l1 = Dropout2d::new(0.5, Some(1));
+        let input = Array::from((vec![4, 8, 2, 3], vec![1.0; 192]));
+
+        let result = l1.forward(input);
+        for channel in result.values().chunks(6) {
+            assert!(channel.iter().all(|&x| x == 0.0) || channel.iter().all(|&x| x == 2.0));
+        }
+        assert!(result.values().contains(&0.0));
+        assert!(result.values().contains(&2.0));
+    }
+
+    #[test]
+    fn test_alpha_dropout() {
+        let l1 = AlphaDropout::new(0.2, Some(2));
+        let count = 100000;
+
+        // uniform values with a mean of 0, and a variance of 1
+        let mut rng = StdRng::seed_from_u64(3);
+        let bound = (3.0 as Float).sqrt();
+        let input = Array::from((
+            vec![count],
+            (0..count)
+                .map(|_| rng.gen_range(-bound..bound))
+                .collect::<Vec<Float>>(),
+        ));
+
+        let result = l1.forward(input);
+        let mean = result.values().iter().sum::<Float>() / count as Float;
+        let variance = result
+            .values()
+            .iter()
+            .map(|x| (x - mean).powi(2))
+            .sum::<Float>()
+            / count as Float;
+        assert_abs_diff_eq!(mean, 0.0, epsilon = 0.02);
+        assert_abs_diff_eq!(variance, 1.0, epsilon = 0.02);
+    }
+}
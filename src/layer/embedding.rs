@@ -0,0 +1,245 @@
+//! An embedding layer, which looks up a row of the weights for each integer index in the input.
+
+use crate::array::*;
+use crate::initializer::Initializer;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+use std::rc::Rc;
+
+/// An embedding layer, storing a weight row for each index of the vocabulary.
+///
+/// The input contains integer indices stored as floats, and the output has the dimensions of the input followed by
+/// the embedding dimension.
+pub struct Embedding {
+    weights: Array,
+    padding_index: Option<usize>,
+    max_norm: Option<Float>,
+}
+
+impl Embedding {
+    /// Constructs a new embedding layer, with a given vocabulary size, and embedding dimension.
+    ///
+    /// The row at `padding_index` is initialized to zeros, and is never updated. If `max_norm` is specified, any row
+    /// with a greater L2 norm is scaled down to `max_norm` when it is looked up.
+    pub fn new(
+        vocab_size: usize,
+        dimension: usize,
+        initializer: &Initializer,
+        padding_index: Option<usize>,
+        max_norm: Option<Float>,
+    ) -> Embedding {
+        if let Some(padding_index) = padding_index {
+            assert!(
+                padding_index < vocab_size,
+                "error: the padding index {} must be less than the vocabulary size {}",
+                padding_index,
+                vocab_size
+            );
+        }
+
+        let values = (0..vocab_size * dimension)
+            .map(|i| {
+                if Some(i / dimension) == padding_index {
+                    0.0
+                } else {
+                    (*initializer)(dimension as Float)
+                }
+            })
+            .collect::<Vec<Float>>();
+
+        Embedding {
+            weights: Array::from((vec![vocab_size, dimension], values)).tracked(),
+            padding_index,
+            max_norm,
+        }
+    }
+}
+
+impl Layer for Embedding {
+    fn forward(&self, input: Array) -> Array {
+        let vocab_size = self.weights.dimensions()[0];
+        let dimension = self.weights.dimensions()[1];
+
+        let indices: Rc<Vec<usize>> = Rc::new(
+            input
+                .values()
+                .iter()
+                .map(|&x| {
+                    assert!(
+                        x >= 0.0 && x.fract() == 0.0 && (x as usize) < vocab_size,
+                        "error: the index {} must be an integer less than the vocabulary size {}",
+                        x,
+                        vocab_size
+                    );
+                    x as usize
+                })
+                .collect(),
+        );
+
+        let output_dimensions = self.output_shape(input.dimensions());
+
+        // the scale of each row, which is less than 1 for rows with a norm greater than the max norm
+        let max_norm = self.max_norm;
+        let scales: Rc<Vec<Float>> = Rc::new(
+            self.weights
+                .values()
+                .chunks(dimension)
+                .map(|row| match max_norm {
+                    Some(max_norm) => {
+                        let norm = row.iter().map(|x| x * x).sum::<Float>().sqrt();
+                        if norm > max_norm {
+                            max_norm / norm
+                        } else {
+                            1.0
+                        }
+                    }
+                    None => 1.0,
+                })
+                .collect(),
+        );
+
+        let (forward_indices, forward_scales) = (Rc::clone(&indices), Rc::clone(&scales));
+        let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+            let weights = x[0].values();
+            let values = forward_indices
+                .iter()
+                .flat_map(|&i| {
+                    let scale = forward_scales[i];
+                    weights[i * dimension..(i + 1) * dimension]
+                        .iter()
+                        .map(move |w| w * scale)
+                })
+                .collect::<Vec<Float>>();
+            Array::from((output_dimensions.clone(), values))
+        });
+
+        // accumulate the delta into only the rows which were looked up
+        let padding_index = self.padding_index;
+        let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+            let weights = c[0].values();
+            let mut values = vec![0.0; weights.len()];
+            for (&i, delta) in indices.iter().zip(x.values().chunks(dimension)) {
+                if Some(i) == padding_index {
+                    continue;
+                }
+
+                let row = &weights[i * dimension..(i + 1) * dimension];
+                // for scaled rows, remove the component of the delta along the row, since it only changes the norm
+                let projection = if scales[i] < 1.0 {
+                    let square_norm: Float = row.iter().map(|w| w * w).sum();
+                    row.iter().zip(delta).map(|(w, d)| w * d).sum::<Float>() / square_norm
+                } else {
+                    0.0
+                };
+
+                for ((v, d), w) in values[i * dimension..(i + 1) * dimension]
+                    .iter_mut()
+                    .zip(delta)
+                    .zip(row)
+                {
+                    *v += scales[i] * (d - projection * w);
+                }
+            }
+
+            vec![Some(Array::from((c[0].dimensions().to_vec(), values)))]
+        });
+
+        Array::named_op("embedding", &[&self.weights], op, Some(backward_op))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.weights]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["weights"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        input_shape
+            .iter()
+            .copied()
+            .chain(vec![self.weights.dimensions()[1]])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ones() -> Initializer {
+        Box::new(|_| 1.0)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut l1 = Embedding::new(4, 2, &ones(), None, None);
+        *l1.parameters()[0] = arr![
+            arr![0.0, 1.0],
+            arr![2.0, 3.0],
+            arr![4.0, 5.0],
+            arr![6.0, 7.0]
+        ]
+        .tracked();
+
+        let result = l1.forward(arr![arr![3.0, 1.0, 3.0]]);
+        assert_eq!(
+            result,
+            arr![arr![arr![6.0, 7.0], arr![2.0, 3.0], arr![6.0, 7.0]]]
+        );
+
+        // only the rows which were looked up are accumulated
+        result.backward(None);
+        assert_eq!(
+            l1.parameters()[0].gradient().to_owned().unwrap(),
+            arr![
+                arr![0.0, 0.0],
+                arr![1.0, 1.0],
+                arr![0.0, 0.0],
+                arr![2.0, 2.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut l1 = Embedding::new(3, 2, &ones(), Some(1), None);
+        assert_eq!(
+            *l1.parameters()[0],
+            arr![arr![1.0, 1.0], arr![0.0, 0.0], arr![1.0, 1.0]]
+        );
+
+        let result = l1.forward(arr![1.0, 0.0, 1.0]);
+        assert_eq!(result, arr![arr![0.0, 0.0], arr![1.0, 1.0], arr![0.0, 0.0]]);
+
+        result.backward(None);
+        assert_eq!(
+            l1.parameters()[0].gradient().to_owned().unwrap(),
+            arr![arr![1.0, 1.0], arr![0.0, 0.0], arr![0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_max_norm() {
+        let mut l1 = Embedding::new(2, 2, &ones(), None, Some(1.0));
+        *l1.parameters()[0] = arr![arr![3.0, 4.0], arr![0.6, 0.0]].tracked();
+
+        let result = l1.forward(arr![0.0, 1.0]);
+        assert_relative_eq!(result, arr![arr![0.6, 0.8], arr![0.6, 0.0]]);
+
+        result.backward(None);
+        assert_relative_eq!(
+            l1.parameters()[0].gradient().to_owned().unwrap(),
+            arr![arr![0.032, -0.024], arr![1.0, 1.0]]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_index() {
+        let l1 = Embedding::new(2, 2, &ones(), None, None);
+        l1.forward(arr![2.0]);
+    }
+}
@@ -0,0 +1,120 @@
+//! A group normalization layer, which normalizes each sample over groups of channels, and applies
+//! y = gamma * x + beta.
+
+use crate::array::*;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+/// A group normalization layer, storing the parameters of the layer.
+///
+/// The input has the dimensions batch size by channels, followed by any image dimensions. The channels are split into
+/// groups, and each group of each sample is normalized over its channels, and any image dimensions.
+pub struct GroupNorm {
+    group_count: usize,
+    gamma: Array,
+    beta: Array,
+    epsilon: Float,
+}
+
+impl GroupNorm {
+    /// Constructs a new group normalization layer, splitting the channels into `group_count` groups.
+    ///
+    /// The `epsilon` is added to the variance to avoid dividing by zero.
+    pub fn new(group_count: usize, channel_count: usize, epsilon: Float) -> GroupNorm {
+        assert!(
+            channel_count.checked_rem(group_count) == Some(0),
+            "error: the channel count {} must be divisible by the group count {}",
+            channel_count,
+            group_count
+        );
+
+        GroupNorm {
+            group_count,
+            gamma: Array::from((vec![channel_count], vec![1.0; channel_count])).tracked(),
+            beta: Array::from(vec![channel_count]).tracked(),
+            epsilon,
+        }
+    }
+}
+
+impl Layer for GroupNorm {
+    fn forward(&self, input: Array) -> Array {
+        let dimensions = self.output_shape(input.dimensions());
+        let channel_count = self.gamma.dimensions()[0];
+
+        // each group is flattened into the last dimension
+        let group_size = dimensions[1..].iter().product::<usize>() / self.group_count;
+        let grouped = input.reshape(vec![dimensions[0], self.group_count, group_size]);
+
+        let centered = &grouped - &grouped.mean(1);
+        let variance = centered.powf(2.0).mean(1);
+        let normalized =
+            (&centered * &(&variance + &arr![self.epsilon]).powf(-0.5)).reshape(dimensions.clone());
+
+        // broadcast each channel over any image dimensions
+        let channel_dimensions: Vec<usize> = vec![channel_count]
+            .into_iter()
+            .chain(vec![1; dimensions.len() - 2])
+            .collect();
+
+        &(&normalized * &self.gamma.reshape(channel_dimensions.clone()))
+            + &self.beta.reshape(channel_dimensions)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["gamma", "beta"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let channel_count = self.gamma.dimensions()[0];
+        assert!(
+            input_shape.len() >= 2 && input_shape[1] == channel_count,
+            "error: the input dimensions {:?} must be batch size by {} channels",
+            input_shape,
+            channel_count
+        );
+
+        input_shape.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let l1 = GroupNorm::new(2, 4, 1e-8);
+        let input = arr![arr![1.0, 3.0, 10.0, 10.0], arr![0.0, 0.0, -2.0, 2.0]];
+
+        let result = l1.forward(input);
+        assert_relative_eq!(
+            result,
+            arr![arr![-1.0, 1.0, 0.0, 0.0], arr![0.0, 0.0, -1.0, 1.0]],
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_image() {
+        let l1 = GroupNorm::new(1, 2, 1e-5);
+        let input = Array::from((
+            vec![2, 2, 2, 3],
+            (0..24)
+                .map(|x| (x * x % 7) as Float)
+                .collect::<Vec<Float>>(),
+        ));
+
+        let result = l1.forward(input);
+        for values in result.values().chunks(12) {
+            let mean = values.iter().sum::<Float>() / 12.0;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<Float>() / 12.0;
+            assert_abs_diff_eq!(mean, 0.0, epsilon = 1e-6);
+            assert_abs_diff_eq!(variance, 1.0, epsilon = 1e-4);
+        }
+    }
+}
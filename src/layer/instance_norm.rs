@@ -0,0 +1,68 @@
+//! An instance normalization layer, which normalizes each channel of each sample over its image dimensions, and
+//! applies y = gamma * x + beta.
+
+use crate::array::*;
+use crate::layer::group_norm::GroupNorm;
+use crate::layer::Layer;
+use crate::numbers::*;
+
+/// An instance normalization layer, which is group normalization with a group for each channel.
+///
+/// The input has the dimensions batch size by channels by any image dimensions.
+pub struct InstanceNorm {
+    group_norm: GroupNorm,
+}
+
+impl InstanceNorm {
+    /// Constructs a new instance normalization layer, for a given number of channels.
+    ///
+    /// The `epsilon` is added to the variance to avoid dividing by zero.
+    pub fn new(channel_count: usize, epsilon: Float) -> InstanceNorm {
+        InstanceNorm {
+            group_norm: GroupNorm::new(channel_count, channel_count, epsilon),
+        }
+    }
+}
+
+impl Layer for InstanceNorm {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+        self.group_norm.forward(input)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.group_norm.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.group_norm.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.len() >= 3,
+            "error: the input dimensions {:?} must have image dimensions",
+            input_shape
+        );
+
+        self.group_norm.output_shape(input_shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let l1 = InstanceNorm::new(2, 1e-8);
+        let input = arr![arr![arr![1.0, 3.0], arr![5.0, 5.0]]];
+
+        let result = l1.forward(input);
+        assert_relative_eq!(
+            result,
+            arr![arr![arr![-1.0, 1.0], arr![0.0, 0.0]]],
+            max_relative = 1e-6
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! A layer normalization layer, which normalizes each sample over its last dimensions, and applies
+//! y = gamma * x + beta.
+
+use crate::array::*;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+/// A layer normalization layer, storing the parameters of the layer.
+///
+/// The statistics are computed for each sample over the last dimensions of the input, which match the normalized
+/// dimensions, and so do not depend on the batch.
+pub struct LayerNorm {
+    normalized_dimensions: Vec<usize>,
+    gamma: Array,
+    beta: Array,
+    epsilon: Float,
+}
+
+impl LayerNorm {
+    /// Constructs a new layer normalization layer, normalizing over the given trailing dimensions of the input.
+    ///
+    /// The `epsilon` is added to the variance to avoid dividing by zero.
+    pub fn new(normalized_dimensions: Vec<usize>, epsilon: Float) -> LayerNorm {
+        let size = normalized_dimensions.iter().product();
+        LayerNorm {
+            gamma: Array::from((normalized_dimensions.clone(), vec![1.0; size])).tracked(),
+            beta: Array::from(normalized_dimensions.clone()).tracked(),
+            normalized_dimensions,
+            epsilon,
+        }
+    }
+}
+
+impl Layer for LayerNorm {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let dimension_count = self.normalized_dimensions.len();
+        let centered = &input - &input.mean(dimension_count);
+        let variance = centered.powf(2.0).mean(dimension_count);
+        let normalized = &centered * &(&variance + &arr![self.epsilon]).powf(-0.5);
+
+        &(&normalized * &self.gamma) + &self.beta
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["gamma", "beta"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.ends_with(&self.normalized_dimensions),
+            "error: the input dimensions {:?} must end with the normalized dimensions {:?}",
+            input_shape,
+            self.normalized_dimensions
+        );
+
+        input_shape.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let mut l1 = LayerNorm::new(vec![3], 0.0);
+        let input = arr![arr![1.0, 2.0, 3.0], arr![10.0, 30.0, 20.0]];
+
+        let deviation = (2.0 as Float / 3.0).sqrt();
+        let result = l1.forward(input.clone());
+        assert_relative_eq!(
+            result,
+            arr![
+                arr![-1.0 / deviation, 0.0, 1.0 / deviation],
+                arr![-1.0 / deviation, 1.0 / deviation, 0.0]
+            ],
+            max_relative = 1e-6
+        );
+
+        *l1.parameters()[0] = arr![2.0, 2.0, 2.0];
+        *l1.parameters()[1] = arr![1.0, 0.0, -1.0];
+        let result = l1.forward(input);
+        assert_relative_eq!(
+            result,
+            arr![
+                arr![1.0 - 2.0 / deviation, 0.0, 2.0 / deviation - 1.0],
+                arr![1.0 - 2.0 / deviation, 2.0 / deviation, -1.0]
+            ],
+            max_relative = 1e-6
+        );
+    }
+}
@@ -1,9 +1,29 @@
 //! Implementations of neural network layers.
 
+pub mod attention;
+pub mod batch_norm;
+pub mod container;
 pub mod conv;
 pub mod dense;
+pub mod dropout;
+pub mod embedding;
+pub mod group_norm;
+pub mod instance_norm;
+pub mod layer_norm;
+pub mod pool;
+pub mod positional;
+pub mod recurrent;
+pub mod rms_norm;
+pub mod shape;
+pub mod transformer;
+pub mod upsample;
 
 use crate::array::*;
+use crate::onnx::Graph;
+use crate::random::Generator;
+use crate::serialize::invalid_data;
+
+use std::io::Result;
 
 /// A layer of a neural network, which implements a forward, and backward pass.
 pub trait Layer {
@@ -12,4 +32,88 @@ pub trait Layer {
 
     /// Retrieves the parameters of the layer.
     fn parameters(&mut self) -> Vec<&mut Array>;
+
+    /// Computes the output dimensions of the layer for input dimensions, which include the batch dimension. By
+    /// default, the layer computes the forward pass of an input of zeros, so layers should compute the dimensions
+    /// directly where they can.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer cannot be applied to inputs of the given dimensions.
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let input = Array::from((
+            input_shape.to_vec(),
+            vec![0.0; input_shape.iter().product()],
+        ));
+        self.forward(input).dimensions().to_vec()
+    }
+
+    /// Retrieves the name of the type of the layer.
+    fn name(&self) -> String {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name).to_string()
+    }
+
+    /// Retrieves the non-trainable state of the layer, such as running statistics, which the optimizer does not update.
+    fn buffers(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    /// Retrieves the parameters of the layer in the order of `parameters`, with names which are stable between
+    /// layers of the same configuration. The parameters are named by their index, unless the layer names them.
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        indexed(self.parameters())
+    }
+
+    /// Retrieves the buffers of the layer in the order of `buffers`, with names which are stable between layers of
+    /// the same configuration.
+    fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        indexed(self.buffers())
+    }
+
+    /// Retrieves the random number generators of the layer, such as those sampling dropout masks.
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        Vec::new()
+    }
+
+    /// Sets whether the layer is training, or is being used for inference. Layers are training by default.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// Appends the nodes of the layer to an ONNX graph, given the name, and dimensions of the input, and returns the
+    /// name of the output. Layers cannot be exported, unless they implement the export.
+    fn to_onnx(&self, _graph: &mut Graph, _input: &str, _input_shape: &[usize]) -> Result<String> {
+        Err(invalid_data(format!(
+            "the layer {} cannot be exported to ONNX",
+            self.name()
+        )))
+    }
+}
+
+/// Names arrays by their index.
+fn indexed(arrays: Vec<&mut Array>) -> Vec<(String, &mut Array)> {
+    arrays
+        .into_iter()
+        .enumerate()
+        .map(|(i, a)| (i.to_string(), a))
+        .collect()
+}
+
+/// Names arrays in order, given a name for each array.
+pub(crate) fn named<'a>(
+    names: &[&str],
+    arrays: Vec<&'a mut Array>,
+) -> Vec<(String, &'a mut Array)> {
+    names.iter().map(|n| n.to_string()).zip(arrays).collect()
+}
+
+/// Prefixes the names of the arrays of an inner layer, separating the prefix with a period.
+pub(crate) fn prefixed<'a>(
+    prefix: &str,
+    arrays: Vec<(String, &'a mut Array)>,
+) -> Vec<(String, &'a mut Array)> {
+    arrays
+        .into_iter()
+        .map(|(n, a)| (format!("{}.{}", prefix, n), a))
+        .collect()
 }
@@ -0,0 +1,111 @@
+//! Pooling layers, which downsample the trailing spatial dimensions of the input over non-overlapping windows.
+
+use crate::array::*;
+use crate::layer::Layer;
+
+/// Computes the output dimensions of pooling, where the remainder of each pooled dimension is discarded.
+fn pool_shape(pool_dimensions: &[usize], input_shape: &[usize]) -> Vec<usize> {
+    let leading_count = input_shape.len().saturating_sub(pool_dimensions.len());
+    assert!(
+        input_shape.len() > pool_dimensions.len()
+            && pool_dimensions
+                .iter()
+                .zip(&input_shape[leading_count..])
+                .all(|(&p, &i)| p > 0 && p <= i),
+        "error: cannot pool with dimensions {:?} over the input dimensions {:?}",
+        pool_dimensions,
+        input_shape
+    );
+
+    input_shape[0..leading_count]
+        .iter()
+        .copied()
+        .chain(
+            input_shape[leading_count..]
+                .iter()
+                .zip(pool_dimensions)
+                .map(|(i, p)| i / p),
+        )
+        .collect()
+}
+
+/// A max pooling layer, which takes the max of each window.
+pub struct MaxPool {
+    pool_dimensions: Vec<usize>,
+}
+
+impl MaxPool {
+    /// Constructs a new max pooling layer, pooling over as many trailing dimensions as there are pool dimensions.
+    pub fn new(pool_dimensions: Vec<usize>) -> MaxPool {
+        MaxPool { pool_dimensions }
+    }
+}
+
+impl Layer for MaxPool {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+        input.max_pool(&self.pool_dimensions)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        pool_shape(&self.pool_dimensions, input_shape)
+    }
+}
+
+/// An average pooling layer, which takes the mean of each window.
+pub struct AvgPool {
+    pool_dimensions: Vec<usize>,
+}
+
+impl AvgPool {
+    /// Constructs a new average pooling layer, pooling over as many trailing dimensions as there are pool dimensions.
+    pub fn new(pool_dimensions: Vec<usize>) -> AvgPool {
+        AvgPool { pool_dimensions }
+    }
+}
+
+impl Layer for AvgPool {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+        input.avg_pool(&self.pool_dimensions)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        pool_shape(&self.pool_dimensions, input_shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_3d() {
+        let input = Array::from((
+            vec![1, 2, 2, 2, 4],
+            (0..32)
+                .map(|x| x as crate::numbers::Float)
+                .collect::<Vec<_>>(),
+        ));
+
+        let result = MaxPool::new(vec![2, 2, 2]).forward(input.clone());
+        assert_eq!(
+            result,
+            Array::from((vec![1, 2, 1, 1, 2], vec![13.0, 15.0, 29.0, 31.0]))
+        );
+
+        let result = AvgPool::new(vec![2, 2, 2]).forward(input);
+        assert_eq!(
+            result,
+            Array::from((vec![1, 2, 1, 1, 2], vec![6.5, 8.5, 22.5, 24.5]))
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Positional encodings, which give attention layers information about the position of each element of a sequence.
+//!
+//! The inputs have the dimensions batch size by sequence length by model size.
+
+use crate::array::*;
+use crate::initializer::Initializer;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+use std::rc::Rc;
+
+/// The base of the wavelengths of the sinusoidal, and rotary encodings.
+const WAVELENGTH_BASE: Float = 10000.0;
+
+/// Asserts that the input is batch size by sequence length by model size, with at most `max_length` positions.
+fn assert_sequence(dimensions: &[usize], max_length: usize, dimension: usize) {
+    assert!(
+        dimensions.len() == 3 && dimensions[1] <= max_length && dimensions[2] == dimension,
+        "error: the input dimensions {:?} must be batch size by at most {} positions by {}",
+        dimensions,
+        max_length,
+        dimension
+    );
+}
+
+/// A fixed sinusoidal encoding, which adds sin(p / 10000^(2i / d)) to even, and cos(p / 10000^(2i / d)) to odd
+/// features at each position p.
+pub struct SinusoidalEncoding {
+    encoding: Array,
+}
+
+impl SinusoidalEncoding {
+    /// Constructs a new sinusoidal encoding, for sequences of up to `max_length` positions.
+    pub fn new(max_length: usize, dimension: usize) -> SinusoidalEncoding {
+        let values = (0..max_length * dimension)
+            .map(|i| {
+                let (position, feature) = (i / dimension, i % dimension);
+                let exponent = (feature - feature % 2) as Float / dimension as Float;
+                let angle = position as Float / WAVELENGTH_BASE.powf(exponent);
+                if feature % 2 == 0 {
+                    angle.sin()
+                } else {
+                    angle.cos()
+                }
+            })
+            .collect::<Vec<Float>>();
+
+        SinusoidalEncoding {
+            encoding: Array::from((vec![max_length, dimension], values)),
+        }
+    }
+}
+
+impl Layer for SinusoidalEncoding {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let dimension = self.encoding.dimensions()[1];
+        let length = input.dimensions()[1];
+        let encoding = Array::from((
+            vec![length, dimension],
+            self.encoding.values()[0..length * dimension].to_vec(),
+        ));
+        &input + &encoding
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let (max_length, dimension) =
+            (self.encoding.dimensions()[0], self.encoding.dimensions()[1]);
+        assert_sequence(input_shape, max_length, dimension);
+        input_shape.to_vec()
+    }
+}
+
+/// A learned positional embedding, which adds a trained row of weights to each position.
+pub struct LearnedPositionalEmbedding {
+    weights: Array,
+}
+
+impl LearnedPositionalEmbedding {
+    /// Constructs a new learned positional embedding, for sequences of up to `max_length` positions.
+    pub fn new(
+        max_length: usize,
+        dimension: usize,
+        initializer: &Initializer,
+    ) -> LearnedPositionalEmbedding {
+        LearnedPositionalEmbedding {
+            weights: Array::from((
+                vec![max_length, dimension],
+                (0..max_length * dimension)
+                    .map(|_| (*initializer)(dimension as Float))
+                    .collect::<Vec<Float>>(),
+            ))
+            .tracked(),
+        }
+    }
+}
+
+impl Layer for LearnedPositionalEmbedding {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let dimension = self.weights.dimensions()[1];
+
+        // the rows of the first positions, where the delta of the unused rows is zero
+        let length = input.dimensions()[1] * dimension;
+        let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+            Array::from((
+                vec![length / dimension, dimension],
+                x[0].values()[0..length].to_vec(),
+            ))
+        });
+
+        let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+            let mut values = vec![0.0; c[0].values().len()];
+            values[0..length].copy_from_slice(x.values());
+            vec![Some(Array::from((c[0].dimensions().to_vec(), values)))]
+        });
+
+        &input
+            + &Array::named_op(
+                "positional_embedding",
+                &[&self.weights],
+                op,
+                Some(backward_op),
+            )
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.weights]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["weights"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let (max_length, dimension) = (self.weights.dimensions()[0], self.weights.dimensions()[1]);
+        assert_sequence(input_shape, max_length, dimension);
+        input_shape.to_vec()
+    }
+}
+
+/// A rotary positional embedding, which rotates each pair of features (x_2i, x_2i+1) at position p by the angle
+/// p / 10000^(2i / d), so that the dot product of two rotated vectors depends on their relative position.
+///
+/// The rotation is meant for the queries, and keys of each attention head after their projections, as applied by
+/// `MultiHeadAttention::with_rotary`, since the projections of rotated inputs no longer preserve the rotation.
+pub struct RotaryEmbedding {
+    dimension: usize,
+}
+
+impl RotaryEmbedding {
+    /// Constructs a new rotary embedding, for an even number of features.
+    pub fn new(dimension: usize) -> RotaryEmbedding {
+        assert!(
+            dimension.checked_rem(2) == Some(0),
+            "error: the rotary embedding dimension {} must be even",
+            dimension
+        );
+
+        RotaryEmbedding { dimension }
+    }
+
+    /// Rotates each pair of features of values of dimensions batch size by sequence length by model size, where the
+    /// angles are negated if `is_inverse`.
+    fn rotate(values: &[Float], length: usize, dimension: usize, is_inverse: bool) -> Vec<Float> {
+        let mut result = vec![0.0; values.len()];
+        for (i, (row, output)) in values
+            .chunks(dimension)
+            .zip(result.chunks_mut(dimension))
+            .enumerate()
+        {
+            let position = (i % length) as Float;
+            for k in 0..dimension / 2 {
+                let angle = position / WAVELENGTH_BASE.powf((2 * k) as Float / dimension as Float);
+                let (sin, cos) = angle.sin_cos();
+                let sin = if is_inverse { -sin } else { sin };
+
+                let (x, y) = (row[2 * k], row[2 * k + 1]);
+                output[2 * k] = x * cos - y * sin;
+                output[2 * k + 1] = x * sin + y * cos;
+            }
+        }
+
+        result
+    }
+}
+
+impl Layer for RotaryEmbedding {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let (length, dimension) = (input.dimensions()[1], self.dimension);
+        let op: ForwardOp = Rc::new(move |x: &[&Array]| {
+            Array::from((
+                x[0].dimensions().to_vec(),
+                RotaryEmbedding::rotate(x[0].values(), length, dimension, false),
+            ))
+        });
+
+        // the inverse of a rotation is its transpose, which is the rotation by the negated angle
+        let backward_op: BackwardOp = Rc::new(move |c, _, x| {
+            vec![Some(Array::from((
+                c[0].dimensions().to_vec(),
+                RotaryEmbedding::rotate(x.values(), length, dimension, true),
+            )))]
+        });
+
+        Array::named_op("rotary_embedding", &[&input], op, Some(backward_op))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert_sequence(input_shape, usize::MAX, self.dimension);
+        input_shape.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sinusoidal() {
+        let l1 = SinusoidalEncoding::new(8, 4);
+        let result = l1.forward(Array::from(vec![1, 2, 4]));
+
+        let angle = 1.0 / (100.0 as Float);
+        assert_relative_eq!(
+            result,
+            arr![arr![
+                arr![0.0, 1.0, 0.0, 1.0],
+                arr![
+                    (1.0 as Float).sin(),
+                    (1.0 as Float).cos(),
+                    angle.sin(),
+                    angle.cos()
+                ]
+            ]],
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_learned() {
+        let mut l1 = LearnedPositionalEmbedding::new(3, 2, &crate::initializer::he());
+        *l1.parameters()[0] = arr![arr![1.0, 2.0], arr![3.0, 4.0], arr![5.0, 6.0]].tracked();
+
+        let input = arr![
+            arr![arr![1.0, 1.0], arr![1.0, 1.0]],
+            arr![arr![0.0, 0.0], arr![0.0, 0.0]]
+        ];
+        let result = l1.forward(input);
+        assert_eq!(
+            result,
+            arr![
+                arr![arr![2.0, 3.0], arr![4.0, 5.0]],
+                arr![arr![1.0, 2.0], arr![3.0, 4.0]]
+            ]
+        );
+
+        result.backward(None);
+        assert_eq!(
+            l1.parameters()[0].gradient().to_owned().unwrap(),
+            arr![arr![2.0, 2.0], arr![2.0, 2.0], arr![0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_rotary() {
+        let l1 = RotaryEmbedding::new(2);
+        let input = arr![arr![arr![1.0, 0.0], arr![1.0, 0.0], arr![0.0, 2.0]]].tracked();
+
+        let result = l1.forward(input.clone());
+        let (sin_1, cos_1) = (1.0 as Float).sin_cos();
+        let (sin_2, cos_2) = (2.0 as Float).sin_cos();
+        assert_relative_eq!(
+            result,
+            arr![arr![
+                arr![1.0, 0.0],
+                arr![cos_1, sin_1],
+                arr![-2.0 * sin_2, 2.0 * cos_2]
+            ]]
+        );
+
+        // rotating the delta back gives the gradient
+        result.backward(Some(result.clone()));
+        assert_relative_eq!(input.gradient().to_owned().unwrap(), input);
+    }
+
+    #[test]
+    fn test_rotary_relative() {
+        let l1 = RotaryEmbedding::new(4);
+        let (q, k) = ([1.0, 2.0, -1.0, 0.5], [0.5, -1.0, 2.0, 1.0]);
+        let input = Array::from((vec![1, 6, 4], [q, k, q, q, q, k].concat()));
+
+        // the dot products of positions 0, and 1, and of positions 4, and 5 are equal
+        let result = l1.forward(input);
+        let dot = |i: usize, j: usize| -> Float {
+            (0..4).map(|k| result[i * 4 + k] * result[j * 4 + k]).sum()
+        };
+        assert_relative_eq!(dot(0, 1), dot(4, 5), max_relative = 1e-6);
+    }
+}
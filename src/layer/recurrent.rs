@@ -0,0 +1,550 @@
+//! Recurrent neural network layers, which apply a cell to each time step of a sequence, carrying state between the
+//! time steps.
+//!
+//! The input has the dimensions time by batch size by input size, and the output has the dimensions time by batch
+//! size by the hidden size times the number of directions. The states have the dimensions layer count times the number
+//! of directions by batch size by hidden size, ordered by layer, and then by direction.
+
+use crate::array::*;
+use crate::initializer::Initializer;
+use crate::layer::{named, prefixed, Layer};
+use crate::numbers::*;
+
+/// A recurrent cell, which computes the states of a single time step.
+trait Cell {
+    /// Computes the next states, given the input of the time step, and the previous states. The first state is the
+    /// output of the time step.
+    fn forward(&self, input: &Array, states: &[Array]) -> Vec<Array>;
+
+    /// Retrieves the parameters of the cell.
+    fn parameters(&mut self) -> Vec<&mut Array>;
+}
+
+/// The weights, and biases of a cell, for which each gate has `hidden_size` rows.
+struct Weights {
+    input_weights: Array,
+    hidden_weights: Array,
+    input_biases: Array,
+    hidden_biases: Array,
+}
+
+impl Weights {
+    fn new(
+        input_size: usize,
+        hidden_size: usize,
+        gate_count: usize,
+        initializer: &Initializer,
+    ) -> Weights {
+        let gate_size = gate_count * hidden_size;
+        let array = |dimensions: Vec<usize>| {
+            let length = dimensions.iter().product();
+            Array::from((
+                dimensions,
+                (0..length)
+                    .map(|_| (*initializer)(hidden_size as Float))
+                    .collect::<Vec<Float>>(),
+            ))
+            .tracked()
+        };
+
+        Weights {
+            input_weights: array(vec![gate_size, input_size]),
+            hidden_weights: array(vec![gate_size, hidden_size]),
+            input_biases: array(vec![gate_size]),
+            hidden_biases: array(vec![gate_size]),
+        }
+    }
+
+    /// Computes the gates from the input, and the hidden state, which are split into `gate_count` arrays.
+    fn gates(&self, input: &Array, hidden: &Array, gate_count: usize) -> (Vec<Array>, Vec<Array>) {
+        let input_gates = Array::matmul(
+            (input, false),
+            (&self.input_weights, true),
+            Some(&self.input_biases),
+        );
+        let hidden_gates = Array::matmul(
+            (hidden, false),
+            (&self.hidden_weights, true),
+            Some(&self.hidden_biases),
+        );
+
+        (
+            input_gates.split(gate_count),
+            hidden_gates.split(gate_count),
+        )
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![
+            &mut self.input_weights,
+            &mut self.hidden_weights,
+            &mut self.input_biases,
+            &mut self.hidden_biases,
+        ]
+    }
+}
+
+/// A cell which computes h' = tanh(W_ih x + b_ih + W_hh h + b_hh).
+struct RnnCell(Weights);
+
+impl Cell for RnnCell {
+    fn forward(&self, input: &Array, states: &[Array]) -> Vec<Array> {
+        let (input_gates, hidden_gates) = self.0.gates(input, &states[0], 1);
+        vec![(&input_gates[0] + &hidden_gates[0]).tanh()]
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.0.parameters()
+    }
+}
+
+/// A cell with input, forget, cell, and output gates, which carries both a hidden, and a cell state.
+struct LstmCell(Weights);
+
+impl Cell for LstmCell {
+    fn forward(&self, input: &Array, states: &[Array]) -> Vec<Array> {
+        let (input_gates, hidden_gates) = self.0.gates(input, &states[0], 4);
+        let gates: Vec<Array> = input_gates
+            .iter()
+            .zip(&hidden_gates)
+            .map(|(x, h)| x + h)
+            .collect();
+
+        let (input_gate, forget_gate) = (gates[0].sigmoid(), gates[1].sigmoid());
+        let (cell_gate, output_gate) = (gates[2].tanh(), gates[3].sigmoid());
+
+        let cell = &(&forget_gate * &states[1]) + &(&input_gate * &cell_gate);
+        let hidden = &output_gate * &cell.tanh();
+        vec![hidden, cell]
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.0.parameters()
+    }
+}
+
+/// A cell with reset, update, and new gates.
+struct GruCell(Weights);
+
+impl Cell for GruCell {
+    fn forward(&self, input: &Array, states: &[Array]) -> Vec<Array> {
+        let (input_gates, hidden_gates) = self.0.gates(input, &states[0], 3);
+
+        let reset_gate = (&input_gates[0] + &hidden_gates[0]).sigmoid();
+        let update_gate = (&input_gates[1] + &hidden_gates[1]).sigmoid();
+        let new_gate = (&input_gates[2] + &(&reset_gate * &hidden_gates[2])).tanh();
+
+        // h' = (1 - z) * n + z * h
+        vec![&new_gate + &(&update_gate * &(&states[0] - &new_gate))]
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.0.parameters()
+    }
+}
+
+/// Stacked, and optionally bidirectional layers of recurrent cells.
+struct Recurrent<C: Cell> {
+    input_size: usize,
+    hidden_size: usize,
+    state_count: usize,
+    is_bidirectional: bool,
+    // the cells of each layer, and direction
+    cells: Vec<C>,
+}
+
+impl<C: Cell> Recurrent<C> {
+    fn new<F>(
+        input_size: usize,
+        hidden_size: usize,
+        layer_count: usize,
+        is_bidirectional: bool,
+        state_count: usize,
+        cell: F,
+    ) -> Recurrent<C>
+    where
+        F: Fn(usize) -> C,
+    {
+        assert!(
+            layer_count > 0,
+            "error: a recurrent layer must have at least one layer"
+        );
+
+        let direction_count = if is_bidirectional { 2 } else { 1 };
+        let cells = (0..layer_count * direction_count)
+            .map(|i| {
+                if i < direction_count {
+                    cell(input_size)
+                } else {
+                    cell(hidden_size * direction_count)
+                }
+            })
+            .collect();
+
+        Recurrent {
+            input_size,
+            hidden_size,
+            state_count,
+            is_bidirectional,
+            cells,
+        }
+    }
+
+    /// Computes the outputs of each time step, and the final states, from the initial states, which are zero if
+    /// unspecified.
+    fn forward(&self, input: &Array, initial_states: Option<Vec<&Array>>) -> (Array, Vec<Array>) {
+        let dimensions = input.dimensions();
+        self.output_shape(dimensions);
+
+        let batch_size = dimensions[1];
+        let state_dimensions = vec![self.cells.len(), batch_size, self.hidden_size];
+        // the initial states of each cell, for each kind of state
+        let initial_states: Vec<Vec<Array>> = match initial_states {
+            Some(states) => states
+                .into_iter()
+                .map(|s| {
+                    assert!(
+                        s.dimensions() == state_dimensions.as_slice(),
+                        "error: the state dimensions {:?} must be {:?}",
+                        s.dimensions(),
+                        state_dimensions
+                    );
+                    s.unstack()
+                })
+                .collect(),
+            None => {
+                let zeros = Array::from(vec![batch_size, self.hidden_size]);
+                vec![vec![zeros; self.cells.len()]; self.state_count]
+            }
+        };
+
+        let direction_count = if self.is_bidirectional { 2 } else { 1 };
+        let mut sequence = input.unstack();
+        let mut final_states: Vec<Vec<Array>> = vec![Vec::new(); self.state_count];
+        for (i, cells) in self.cells.chunks(direction_count).enumerate() {
+            let mut direction_outputs = Vec::with_capacity(direction_count);
+            for (direction, cell) in cells.iter().enumerate() {
+                let index = i * direction_count + direction;
+                let mut states: Vec<Array> =
+                    initial_states.iter().map(|s| s[index].clone()).collect();
+
+                let mut outputs = Vec::with_capacity(sequence.len());
+                // the second direction runs from the last time step to the first
+                let time_steps: Vec<usize> = if direction == 0 {
+                    (0..sequence.len()).collect()
+                } else {
+                    (0..sequence.len()).rev().collect()
+                };
+
+                for t in time_steps {
+                    states = cell.forward(&sequence[t], &states);
+                    outputs.push(states[0].clone());
+                }
+
+                if direction == 1 {
+                    outputs.reverse();
+                }
+
+                for (final_state, state) in final_states.iter_mut().zip(states) {
+                    final_state.push(state);
+                }
+                direction_outputs.push(outputs);
+            }
+
+            sequence = if self.is_bidirectional {
+                direction_outputs[0]
+                    .iter()
+                    .zip(&direction_outputs[1])
+                    .map(|(forward, backward)| Array::concat(&[forward, backward]))
+                    .collect()
+            } else {
+                direction_outputs.pop().unwrap()
+            };
+        }
+
+        let outputs = Array::stack(&sequence.iter().collect::<Vec<&Array>>());
+        let final_states = final_states
+            .iter()
+            .map(|s| Array::stack(&s.iter().collect::<Vec<&Array>>()))
+            .collect();
+
+        (outputs, final_states)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.cells.iter_mut().flat_map(|c| c.parameters()).collect()
+    }
+
+    /// Names the parameters of each cell by its index, which is ordered by layer, and then by direction.
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.cells
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, c)| {
+                prefixed(
+                    &format!("cells.{}", i),
+                    named(
+                        &[
+                            "input_weights",
+                            "hidden_weights",
+                            "input_biases",
+                            "hidden_biases",
+                        ],
+                        c.parameters(),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.len() == 3 && input_shape[2] == self.input_size,
+            "error: the input dimensions {:?} must be time by batch size by {}",
+            input_shape,
+            self.input_size
+        );
+
+        let direction_count = if self.is_bidirectional { 2 } else { 1 };
+        vec![
+            input_shape[0],
+            input_shape[1],
+            self.hidden_size * direction_count,
+        ]
+    }
+}
+
+/// An Elman recurrent layer, which computes h' = tanh(W_ih x + b_ih + W_hh h + b_hh) at each time step.
+pub struct Rnn {
+    recurrent: Recurrent<RnnCell>,
+}
+
+impl Rnn {
+    /// Constructs a new recurrent layer, with `layer_count` stacked layers, which are bidirectional if specified.
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        layer_count: usize,
+        is_bidirectional: bool,
+        initializer: &Initializer,
+    ) -> Rnn {
+        Rnn {
+            recurrent: Recurrent::new(
+                input_size,
+                hidden_size,
+                layer_count,
+                is_bidirectional,
+                1,
+                |input_size| RnnCell(Weights::new(input_size, hidden_size, 1, initializer)),
+            ),
+        }
+    }
+
+    /// Computes the outputs of each time step, and the final hidden state, from the initial hidden state, which is
+    /// zero if unspecified.
+    pub fn forward_states(&self, input: &Array, hidden: Option<&Array>) -> (Array, Array) {
+        let (outputs, mut states) = self.recurrent.forward(input, hidden.map(|h| vec![h]));
+        (outputs, states.remove(0))
+    }
+}
+
+impl Layer for Rnn {
+    fn forward(&self, input: Array) -> Array {
+        self.forward_states(&input, None).0
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.recurrent.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.recurrent.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.recurrent.output_shape(input_shape)
+    }
+}
+
+/// A long short-term memory layer, which carries a hidden, and a cell state between time steps.
+pub struct Lstm {
+    recurrent: Recurrent<LstmCell>,
+}
+
+impl Lstm {
+    /// Constructs a new LSTM layer, with `layer_count` stacked layers, which are bidirectional if specified.
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        layer_count: usize,
+        is_bidirectional: bool,
+        initializer: &Initializer,
+    ) -> Lstm {
+        Lstm {
+            recurrent: Recurrent::new(
+                input_size,
+                hidden_size,
+                layer_count,
+                is_bidirectional,
+                2,
+                |input_size| LstmCell(Weights::new(input_size, hidden_size, 4, initializer)),
+            ),
+        }
+    }
+
+    /// Computes the outputs of each time step, and the final hidden, and cell states, from the initial hidden, and
+    /// cell states, which are zero if unspecified.
+    pub fn forward_states(
+        &self,
+        input: &Array,
+        states: Option<(&Array, &Array)>,
+    ) -> (Array, (Array, Array)) {
+        let (outputs, mut states) = self
+            .recurrent
+            .forward(input, states.map(|(h, c)| vec![h, c]));
+        let cell = states.pop().unwrap();
+        (outputs, (states.pop().unwrap(), cell))
+    }
+}
+
+impl Layer for Lstm {
+    fn forward(&self, input: Array) -> Array {
+        self.forward_states(&input, None).0
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.recurrent.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.recurrent.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.recurrent.output_shape(input_shape)
+    }
+}
+
+/// A gated recurrent unit layer, which computes reset, update, and new gates at each time step.
+pub struct Gru {
+    recurrent: Recurrent<GruCell>,
+}
+
+impl Gru {
+    /// Constructs a new GRU layer, with `layer_count` stacked layers, which are bidirectional if specified.
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        layer_count: usize,
+        is_bidirectional: bool,
+        initializer: &Initializer,
+    ) -> Gru {
+        Gru {
+            recurrent: Recurrent::new(
+                input_size,
+                hidden_size,
+                layer_count,
+                is_bidirectional,
+                1,
+                |input_size| GruCell(Weights::new(input_size, hidden_size, 3, initializer)),
+            ),
+        }
+    }
+
+    /// Computes the outputs of each time step, and the final hidden state, from the initial hidden state, which is
+    /// zero if unspecified.
+    pub fn forward_states(&self, input: &Array, hidden: Option<&Array>) -> (Array, Array) {
+        let (outputs, mut states) = self.recurrent.forward(input, hidden.map(|h| vec![h]));
+        (outputs, states.remove(0))
+    }
+}
+
+impl Layer for Gru {
+    fn forward(&self, input: Array) -> Array {
+        self.forward_states(&input, None).0
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.recurrent.parameters()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.recurrent.named_parameters()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.recurrent.output_shape(input_shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(value: Float) -> Initializer {
+        Box::new(move |_| value)
+    }
+
+    #[test]
+    fn test_rnn() {
+        let l1 = Rnn::new(1, 1, 1, false, &constant(0.5));
+        let input = arr![arr![arr![1.0]], arr![arr![-1.0]]];
+
+        let (outputs, hidden) = l1.forward_states(&input, Some(&arr![arr![arr![2.0]]]));
+        let h1 = (0.5 as Float + 1.0 + 1.0).tanh();
+        let h2 = (-0.5 as Float + 0.5 * h1 + 1.0).tanh();
+        assert_relative_eq!(outputs, arr![arr![arr![h1]], arr![arr![h2]]]);
+        assert_relative_eq!(hidden, arr![arr![arr![h2]]]);
+    }
+
+    #[test]
+    fn test_lstm() {
+        let l1 = Lstm::new(1, 1, 1, false, &constant(0.5));
+        let input = arr![arr![arr![1.0]]];
+
+        let (outputs, (hidden, cell)) = l1.forward_states(
+            &input,
+            Some((&arr![arr![arr![1.0]]], &arr![arr![arr![2.0]]])),
+        );
+        let gate = 1.0 / (1.0 + (-2.0 as Float).exp());
+        let c = gate * 2.0 + gate * (2.0 as Float).tanh();
+        let h = gate * c.tanh();
+        assert_relative_eq!(outputs, arr![arr![arr![h]]]);
+        assert_relative_eq!(hidden, arr![arr![arr![h]]]);
+        assert_relative_eq!(cell, arr![arr![arr![c]]]);
+    }
+
+    #[test]
+    fn test_gru() {
+        let l1 = Gru::new(1, 1, 1, false, &constant(0.5));
+        let input = arr![arr![arr![1.0]]];
+
+        let (outputs, hidden) = l1.forward_states(&input, Some(&arr![arr![arr![1.0]]]));
+        let gate = 1.0 / (1.0 + (-2.0 as Float).exp());
+        let n = (1.0 + gate * 1.0).tanh();
+        let h = (1.0 - gate) * n + gate * 1.0;
+        assert_relative_eq!(outputs, arr![arr![arr![h]]]);
+        assert_relative_eq!(hidden, arr![arr![arr![h]]]);
+    }
+
+    #[test]
+    fn test_stacked_bidirectional() {
+        let initializer = crate::initializer::he();
+        let l1 = Lstm::new(3, 4, 2, true, &initializer);
+        let input = Array::from((vec![5, 2, 3], vec![0.5; 30]));
+
+        let (outputs, (hidden, cell)) = l1.forward_states(&input, None);
+        assert_eq!(outputs.dimensions(), &[5, 2, 8]);
+        assert_eq!(hidden.dimensions(), &[4, 2, 4]);
+        assert_eq!(cell.dimensions(), &[4, 2, 4]);
+
+        // the final forward state of the last layer is the output of its last time step, and the final backward state
+        // is the output of its first time step
+        let hidden = hidden.unstack();
+        let outputs = outputs.unstack();
+        let last = outputs[4].split(2);
+        let first = outputs[0].split(2);
+        assert_eq!(hidden[2], last[0]);
+        assert_eq!(hidden[3], first[1]);
+    }
+}
@@ -0,0 +1,82 @@
+//! A root mean square normalization layer, which scales each sample by the root mean square over its last
+//! dimensions, and applies y = gamma * x.
+
+use crate::array::*;
+use crate::layer::{named, Layer};
+use crate::numbers::*;
+
+/// A root mean square normalization layer, storing the parameters of the layer.
+///
+/// Unlike layer normalization, the input is not centered, and there is no bias.
+pub struct RmsNorm {
+    normalized_dimensions: Vec<usize>,
+    gamma: Array,
+    epsilon: Float,
+}
+
+impl RmsNorm {
+    /// Constructs a new root mean square normalization layer, normalizing over the given trailing dimensions of the
+    /// input.
+    ///
+    /// The `epsilon` is added to the mean square to avoid dividing by zero.
+    pub fn new(normalized_dimensions: Vec<usize>, epsilon: Float) -> RmsNorm {
+        let size = normalized_dimensions.iter().product();
+        RmsNorm {
+            gamma: Array::from((normalized_dimensions.clone(), vec![1.0; size])).tracked(),
+            normalized_dimensions,
+            epsilon,
+        }
+    }
+}
+
+impl Layer for RmsNorm {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let mean_square = input.powf(2.0).mean(self.normalized_dimensions.len());
+        let normalized = &input * &(&mean_square + &arr![self.epsilon]).powf(-0.5);
+
+        &normalized * &self.gamma
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        vec![&mut self.gamma]
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        named(&["gamma"], self.parameters())
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert!(
+            input_shape.ends_with(&self.normalized_dimensions),
+            "error: the input dimensions {:?} must end with the normalized dimensions {:?}",
+            input_shape,
+            self.normalized_dimensions
+        );
+
+        input_shape.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let l1 = RmsNorm::new(vec![2], 0.0);
+        let input = arr![arr![3.0, 4.0], arr![-1.0, 1.0]];
+
+        let root_mean_square = (12.5 as Float).sqrt();
+        let result = l1.forward(input);
+        assert_relative_eq!(
+            result,
+            arr![
+                arr![3.0 / root_mean_square, 4.0 / root_mean_square],
+                arr![-1.0, 1.0]
+            ],
+            max_relative = 1e-6
+        );
+    }
+}
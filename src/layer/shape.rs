@@ -0,0 +1,164 @@
+//! Layers which change the dimensions of the input, such as flattening the output of a convolutional layer for a
+//! dense layer.
+//!
+//! The first dimension of the input is the batch dimension, which is always preserved.
+
+use crate::array::*;
+use crate::layer::Layer;
+use crate::onnx::{Attribute, Graph};
+
+use std::io::Result;
+
+/// Asserts that the input has a batch dimension, followed by at least one other dimension.
+fn assert_batch(input_shape: &[usize]) {
+    assert!(
+        input_shape.len() >= 2,
+        "error: the input dimensions {:?} must be batch size followed by the sample dimensions",
+        input_shape
+    );
+}
+
+/// A flatten layer, which flattens the dimensions of each sample into a single dimension.
+#[derive(Default)]
+pub struct Flatten;
+
+impl Flatten {
+    /// Constructs a new flatten layer.
+    pub fn new() -> Flatten {
+        Flatten
+    }
+}
+
+impl Layer for Flatten {
+    fn forward(&self, input: Array) -> Array {
+        input.reshape(self.output_shape(input.dimensions()))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert_batch(input_shape);
+        vec![input_shape[0], input_shape[1..].iter().product()]
+    }
+
+    fn to_onnx(&self, graph: &mut Graph, input: &str, _input_shape: &[usize]) -> Result<String> {
+        Ok(graph.node("Flatten", &[input], &[("axis", Attribute::Int(1))]))
+    }
+}
+
+/// A reshape layer, which reshapes each sample to the given dimensions.
+pub struct Reshape {
+    dimensions: Vec<usize>,
+}
+
+impl Reshape {
+    /// Constructs a new reshape layer, with the dimensions of each sample.
+    pub fn new(dimensions: Vec<usize>) -> Reshape {
+        Reshape { dimensions }
+    }
+}
+
+impl Layer for Reshape {
+    fn forward(&self, input: Array) -> Array {
+        input.reshape(self.output_shape(input.dimensions()))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert_batch(input_shape);
+
+        let sample_size: usize = input_shape[1..].iter().product();
+        assert!(
+            sample_size == self.dimensions.iter().product(),
+            "error: cannot reshape samples of dimensions {:?} to {:?}",
+            &input_shape[1..],
+            self.dimensions
+        );
+
+        vec![input_shape[0]]
+            .into_iter()
+            .chain(self.dimensions.iter().copied())
+            .collect()
+    }
+}
+
+/// A permute layer, which permutes the dimensions of each sample, where dimension `i` of each output sample is
+/// dimension `axes[i]` of the input sample.
+pub struct Permute {
+    axes: Vec<usize>,
+}
+
+impl Permute {
+    /// Constructs a new permute layer, with axes indexing the dimensions of each sample.
+    pub fn new(axes: Vec<usize>) -> Permute {
+        Permute { axes }
+    }
+}
+
+impl Layer for Permute {
+    fn forward(&self, input: Array) -> Array {
+        self.output_shape(input.dimensions());
+
+        let axes: Vec<usize> = vec![0]
+            .into_iter()
+            .chain(self.axes.iter().map(|a| a + 1))
+            .collect();
+        input.permute(&axes)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        assert_batch(input_shape);
+
+        let sample_count = input_shape.len() - 1;
+        let mut axes = self.axes.clone();
+        axes.sort_unstable();
+        assert!(
+            axes.iter().copied().eq(0..sample_count),
+            "error: the axes {:?} must be a permutation of the sample dimensions {:?}",
+            self.axes,
+            &input_shape[1..]
+        );
+
+        vec![input_shape[0]]
+            .into_iter()
+            .chain(self.axes.iter().map(|&a| input_shape[a + 1]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        let input = Array::from(vec![2, 3, 4, 5]);
+
+        assert_eq!(Flatten::new().forward(input.clone()).dimensions(), &[2, 60]);
+        assert_eq!(
+            Reshape::new(vec![12, 5])
+                .forward(input.clone())
+                .dimensions(),
+            &[2, 12, 5]
+        );
+        assert_eq!(
+            Permute::new(vec![2, 0, 1]).forward(input).dimensions(),
+            &[2, 5, 3, 4]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_reshape() {
+        Reshape::new(vec![7]).forward(Array::from(vec![2, 3, 2]));
+    }
+}
@@ -0,0 +1,370 @@
+//! Transformer encoder, and decoder layers, which combine multi-head attention with a feed-forward network, using
+//! residual connections followed by layer normalization.
+
+use crate::array::*;
+use crate::initializer::Initializer;
+use crate::layer::attention::MultiHeadAttention;
+use crate::layer::dense::Dense;
+use crate::layer::dropout::Dropout;
+use crate::layer::layer_norm::LayerNorm;
+use crate::layer::{prefixed, Layer};
+use crate::numbers::*;
+use crate::random::Generator;
+
+/// The epsilon of the layer normalization of the transformer layers.
+const NORM_EPSILON: Float = 1e-5;
+
+/// A position-wise feed-forward network, which applies y = W_2 dropout(relu(W_1 x + b_1)) + b_2.
+struct FeedForward {
+    hidden: Dense,
+    output: Dense,
+    dropout: Dropout,
+}
+
+impl FeedForward {
+    fn new(
+        model_size: usize,
+        feedforward_size: usize,
+        dropout: Float,
+        initializer: &Initializer,
+        seed: Option<u64>,
+    ) -> FeedForward {
+        FeedForward {
+            hidden: Dense::new(model_size, feedforward_size, initializer, None),
+            output: Dense::new(feedforward_size, model_size, initializer, None),
+            dropout: Dropout::new(dropout, seed),
+        }
+    }
+
+    fn forward(&self, input: Array) -> Array {
+        self.output
+            .forward(self.dropout.forward(self.hidden.forward(input).relu()))
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        let mut parameters = self.hidden.parameters();
+        parameters.append(&mut self.output.parameters());
+        parameters
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        let mut parameters = prefixed("hidden", self.hidden.named_parameters());
+        parameters.append(&mut prefixed("output", self.output.named_parameters()));
+        parameters
+    }
+}
+
+/// Derives the seed of each dropout layer from the seed of the transformer layer.
+fn dropout_seed(seed: Option<u64>, index: u64) -> Option<u64> {
+    seed.map(|s| s.wrapping_add(index))
+}
+
+/// A transformer encoder layer, which applies self-attention, and then a feed-forward network.
+///
+/// The input has the dimensions batch size by sequence length by model size.
+pub struct TransformerEncoderLayer {
+    attention: MultiHeadAttention,
+    feedforward: FeedForward,
+    attention_norm: LayerNorm,
+    feedforward_norm: LayerNorm,
+    attention_dropout: Dropout,
+    feedforward_dropout: Dropout,
+}
+
+impl TransformerEncoderLayer {
+    /// Constructs a new transformer encoder layer, with a given model size, number of heads, and hidden size of the
+    /// feed-forward network.
+    ///
+    /// Dropout is applied with the given probability, and its masks are seeded by `seed` if specified.
+    pub fn new(
+        model_size: usize,
+        head_count: usize,
+        feedforward_size: usize,
+        dropout: Float,
+        initializer: &Initializer,
+        seed: Option<u64>,
+    ) -> TransformerEncoderLayer {
+        TransformerEncoderLayer {
+            attention: MultiHeadAttention::new(model_size, head_count, initializer),
+            feedforward: FeedForward::new(
+                model_size,
+                feedforward_size,
+                dropout,
+                initializer,
+                dropout_seed(seed, 0),
+            ),
+            attention_norm: LayerNorm::new(vec![model_size], NORM_EPSILON),
+            feedforward_norm: LayerNorm::new(vec![model_size], NORM_EPSILON),
+            attention_dropout: Dropout::new(dropout, dropout_seed(seed, 1)),
+            feedforward_dropout: Dropout::new(dropout, dropout_seed(seed, 2)),
+        }
+    }
+
+    /// Computes the forward pass, where self-attention is causal if specified, and does not attend to positions
+    /// where the `padding` of dimensions batch size by sequence length is nonzero.
+    pub fn forward_masked(&self, input: Array, is_causal: bool, padding: Option<&Array>) -> Array {
+        let attended = self
+            .attention
+            .forward_masked(&input, &input, &input, is_causal, padding);
+        let input = self
+            .attention_norm
+            .forward(&input + &self.attention_dropout.forward(attended));
+
+        let transformed = self.feedforward.forward(input.clone());
+        self.feedforward_norm
+            .forward(&input + &self.feedforward_dropout.forward(transformed))
+    }
+}
+
+impl Layer for TransformerEncoderLayer {
+    fn forward(&self, input: Array) -> Array {
+        self.forward_masked(input, false, None)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        let mut parameters = self.attention.parameters();
+        parameters.append(&mut self.attention_norm.parameters());
+        parameters.append(&mut self.feedforward.parameters());
+        parameters.append(&mut self.feedforward_norm.parameters());
+        parameters
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.attention.output_shape(input_shape)
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        let mut parameters = prefixed("attention", self.attention.named_parameters());
+        parameters.append(&mut prefixed(
+            "attention_norm",
+            self.attention_norm.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "feedforward",
+            self.feedforward.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "feedforward_norm",
+            self.feedforward_norm.named_parameters(),
+        ));
+        parameters
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        let mut generators = self.feedforward.dropout.generators();
+        generators.append(&mut self.attention_dropout.generators());
+        generators.append(&mut self.feedforward_dropout.generators());
+        generators
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.feedforward.dropout.set_training(is_training);
+        self.attention_dropout.set_training(is_training);
+        self.feedforward_dropout.set_training(is_training);
+    }
+}
+
+/// A transformer decoder layer, which applies causal self-attention, attention over the memory of an encoder, and
+/// then a feed-forward network.
+///
+/// The target, and memory have the dimensions batch size by sequence length by model size.
+pub struct TransformerDecoderLayer {
+    self_attention: MultiHeadAttention,
+    memory_attention: MultiHeadAttention,
+    feedforward: FeedForward,
+    self_attention_norm: LayerNorm,
+    memory_attention_norm: LayerNorm,
+    feedforward_norm: LayerNorm,
+    self_attention_dropout: Dropout,
+    memory_attention_dropout: Dropout,
+    feedforward_dropout: Dropout,
+}
+
+impl TransformerDecoderLayer {
+    /// Constructs a new transformer decoder layer, with a given model size, number of heads, and hidden size of the
+    /// feed-forward network.
+    ///
+    /// Dropout is applied with the given probability, and its masks are seeded by `seed` if specified.
+    pub fn new(
+        model_size: usize,
+        head_count: usize,
+        feedforward_size: usize,
+        dropout: Float,
+        initializer: &Initializer,
+        seed: Option<u64>,
+    ) -> TransformerDecoderLayer {
+        TransformerDecoderLayer {
+            self_attention: MultiHeadAttention::new(model_size, head_count, initializer),
+            memory_attention: MultiHeadAttention::new(model_size, head_count, initializer),
+            feedforward: FeedForward::new(
+                model_size,
+                feedforward_size,
+                dropout,
+                initializer,
+                dropout_seed(seed, 0),
+            ),
+            self_attention_norm: LayerNorm::new(vec![model_size], NORM_EPSILON),
+            memory_attention_norm: LayerNorm::new(vec![model_size], NORM_EPSILON),
+            feedforward_norm: LayerNorm::new(vec![model_size], NORM_EPSILON),
+            self_attention_dropout: Dropout::new(dropout, dropout_seed(seed, 1)),
+            memory_attention_dropout: Dropout::new(dropout, dropout_seed(seed, 2)),
+            feedforward_dropout: Dropout::new(dropout, dropout_seed(seed, 3)),
+        }
+    }
+
+    /// Computes the forward pass over the target, attending causally to the target, and to the memory if specified.
+    ///
+    /// The `target_padding`, and `memory_padding` have the dimensions batch size by sequence length, and are nonzero
+    /// for positions which are not attended to.
+    pub fn forward_memory(
+        &self,
+        target: Array,
+        memory: Option<&Array>,
+        target_padding: Option<&Array>,
+        memory_padding: Option<&Array>,
+    ) -> Array {
+        let attended =
+            self.self_attention
+                .forward_masked(&target, &target, &target, true, target_padding);
+        let mut target = self
+            .self_attention_norm
+            .forward(&target + &self.self_attention_dropout.forward(attended));
+
+        if let Some(memory) = memory {
+            let attended = self.memory_attention.forward_masked(
+                &target,
+                memory,
+                memory,
+                false,
+                memory_padding,
+            );
+            target = self
+                .memory_attention_norm
+                .forward(&target + &self.memory_attention_dropout.forward(attended));
+        }
+
+        let transformed = self.feedforward.forward(target.clone());
+        self.feedforward_norm
+            .forward(&target + &self.feedforward_dropout.forward(transformed))
+    }
+}
+
+impl Layer for TransformerDecoderLayer {
+    /// Computes the forward pass without memory, which only applies causal self-attention, and the feed-forward
+    /// network.
+    fn forward(&self, input: Array) -> Array {
+        self.forward_memory(input, None, None, None)
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        let mut parameters = self.self_attention.parameters();
+        parameters.append(&mut self.self_attention_norm.parameters());
+        parameters.append(&mut self.memory_attention.parameters());
+        parameters.append(&mut self.memory_attention_norm.parameters());
+        parameters.append(&mut self.feedforward.parameters());
+        parameters.append(&mut self.feedforward_norm.parameters());
+        parameters
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.self_attention.output_shape(input_shape)
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        let mut parameters = prefixed("self_attention", self.self_attention.named_parameters());
+        parameters.append(&mut prefixed(
+            "self_attention_norm",
+            self.self_attention_norm.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "memory_attention",
+            self.memory_attention.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "memory_attention_norm",
+            self.memory_attention_norm.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "feedforward",
+            self.feedforward.named_parameters(),
+        ));
+        parameters.append(&mut prefixed(
+            "feedforward_norm",
+            self.feedforward_norm.named_parameters(),
+        ));
+        parameters
+    }
+
+    fn generators(&mut self) -> Vec<&mut Generator> {
+        let mut generators = self.feedforward.dropout.generators();
+        generators.append(&mut self.self_attention_dropout.generators());
+        generators.append(&mut self.memory_attention_dropout.generators());
+        generators.append(&mut self.feedforward_dropout.generators());
+        generators
+    }
+
+    fn set_training(&mut self, is_training: bool) {
+        self.feedforward.dropout.set_training(is_training);
+        self.self_attention_dropout.set_training(is_training);
+        self.memory_attention_dropout.set_training(is_training);
+        self.feedforward_dropout.set_training(is_training);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(dimensions: Vec<usize>) -> Array {
+        let length = dimensions.iter().product();
+        Array::from((
+            dimensions,
+            (0..length)
+                .map(|x| ((x * 7) % 5) as Float - 2.0)
+                .collect::<Vec<Float>>(),
+        ))
+    }
+
+    #[test]
+    fn test_encoder() {
+        let l1 = TransformerEncoderLayer::new(4, 2, 8, 0.0, &crate::initializer::he(), None);
+
+        let result = l1.forward(input(vec![2, 3, 4]));
+        assert_eq!(result.dimensions(), &[2, 3, 4]);
+
+        // each position is layer normalized
+        for row in result.values().chunks(4) {
+            assert_abs_diff_eq!(row.iter().sum::<Float>(), 0.0, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_decoder_causal() {
+        let l1 = TransformerDecoderLayer::new(4, 2, 8, 0.0, &crate::initializer::he(), None);
+        let memory = input(vec![1, 2, 4]);
+
+        // changing a later position of the target does not change the earlier positions
+        let target = input(vec![1, 3, 4]);
+        let mut changed = target.values().to_vec();
+        changed[11] += 1.0;
+        let changed = Array::from((vec![1, 3, 4], changed));
+
+        let result = l1.forward_memory(target, Some(&memory), None, None);
+        let changed_result = l1.forward_memory(changed, Some(&memory), None, None);
+        assert_eq!(result.values()[0..8], changed_result.values()[0..8]);
+        assert_ne!(result.values()[8..12], changed_result.values()[8..12]);
+    }
+
+    #[test]
+    fn test_dropout_seed() {
+        let initializer = crate::initializer::he();
+        let mut l1 = TransformerEncoderLayer::new(4, 2, 8, 0.5, &initializer, Some(3));
+        let input = input(vec![2, 3, 4]);
+
+        let first = l1.forward(input.clone());
+        assert_ne!(first, l1.forward(input.clone()));
+
+        l1.set_training(false);
+        assert_eq!(l1.forward(input.clone()), l1.forward(input));
+    }
+}
@@ -0,0 +1,125 @@
+//! An upsampling layer, which resizes the rows, and columns of the input, complementing the downsampling of strided
+//! convolutions.
+
+use crate::array::*;
+use crate::layer::Layer;
+use crate::numbers::*;
+
+/// An upsampling layer, which resizes the last two dimensions of the input either by a scale factor, or to a fixed
+/// size, using bilinear interpolation if specified, and the nearest value otherwise.
+pub struct Upsample {
+    size: Size,
+    is_bilinear: bool,
+}
+
+/// The size of the output of an upsampling layer.
+enum Size {
+    ScaleFactor(Float),
+    Fixed(usize, usize),
+}
+
+impl Upsample {
+    /// Constructs a new upsampling layer, which scales the rows, and columns by the scale factor, rounding down.
+    pub fn new(scale_factor: Float, is_bilinear: bool) -> Upsample {
+        assert!(
+            scale_factor > 0.0,
+            "error: the scale factor {} must be positive",
+            scale_factor
+        );
+
+        Upsample {
+            size: Size::ScaleFactor(scale_factor),
+            is_bilinear,
+        }
+    }
+
+    /// Constructs a new upsampling layer, which resizes the input to the given rows, and columns.
+    pub fn with_size(size: (usize, usize), is_bilinear: bool) -> Upsample {
+        assert!(
+            size.0 > 0 && size.1 > 0,
+            "error: the size {:?} must be positive",
+            size
+        );
+
+        Upsample {
+            size: Size::Fixed(size.0, size.1),
+            is_bilinear,
+        }
+    }
+}
+
+impl Layer for Upsample {
+    fn forward(&self, input: Array) -> Array {
+        let output_shape = self.output_shape(input.dimensions());
+        let size = (
+            output_shape[output_shape.len() - 2],
+            output_shape[output_shape.len() - 1],
+        );
+
+        if self.is_bilinear {
+            input.upsample_bilinear(size)
+        } else {
+            input.upsample_nearest(size)
+        }
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        Vec::new()
+    }
+
+    fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        let dimension_count = input_shape.len();
+        assert!(
+            dimension_count >= 2,
+            "error: cannot upsample dimensions {:?}",
+            input_shape
+        );
+
+        let (rows, cols) = match self.size {
+            Size::Fixed(rows, cols) => (rows, cols),
+            Size::ScaleFactor(scale_factor) => {
+                let scale = |x: usize| (x as Float * scale_factor).floor() as usize;
+                let (rows, cols) = (
+                    scale(input_shape[dimension_count - 2]),
+                    scale(input_shape[dimension_count - 1]),
+                );
+                assert!(
+                    rows > 0 && cols > 0,
+                    "error: the scale factor {} reduces the dimensions {:?} to zero",
+                    scale_factor,
+                    input_shape
+                );
+
+                (rows, cols)
+            }
+        };
+
+        input_shape[0..dimension_count - 2]
+            .iter()
+            .copied()
+            .chain(vec![rows, cols])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsample() {
+        let input = Array::from(vec![2, 3, 4, 5]);
+
+        let l1 = Upsample::new(2.0, false);
+        assert_eq!(l1.forward(input.clone()).dimensions(), &[2, 3, 8, 10]);
+
+        let l2 = Upsample::with_size((7, 6), true);
+        assert_eq!(l2.forward(input).dimensions(), &[2, 3, 7, 6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_upsample_zero() {
+        Upsample::new(0.1, false).forward(Array::from(vec![1, 1, 4, 5]));
+    }
+}
@@ -23,11 +23,17 @@ pub mod array;
 pub mod activation;
 #[cfg(feature = "blas")]
 pub mod blas;
+pub mod checkpoint;
 pub mod cost;
 pub mod initializer;
 pub mod layer;
 pub mod model;
+pub mod onnx;
 pub mod optimizer;
+pub mod random;
+pub mod safetensors;
+pub mod serialize;
+mod zip;
 
 // #[global_allocator]
 // static GLOBAL: MiMalloc = MiMalloc;
@@ -1,37 +1,142 @@
 //! A supervised neural network model, which computes a forward pass, and updates parameters based on a target.
 
+use crate::activation::Activation;
 use crate::array::*;
+use crate::cost;
 use crate::cost::CostFunction;
+use crate::initializer;
+use crate::initializer::Initializer;
+use crate::layer;
+use crate::layer::conv::Conv;
+use crate::layer::dense::Dense;
+use crate::layer::shape::Flatten;
 use crate::layer::Layer;
 use crate::numbers::*;
 use crate::optimizer::Optimizer;
+use crate::random::Generator;
+use crate::serialize;
+
+use std::io;
+use std::path::Path;
 
 /// A neural network model, containing the layers of the model, and the outputs.
-pub struct Model<'a> {
-    layers: Vec<&'a mut dyn Layer>,
+pub struct Model {
+    layers: Vec<Box<dyn Layer>>,
+    input_shape: Option<Vec<usize>>,
+    is_validated: bool,
     output: Option<Array>,
-    optimizer: &'a dyn Optimizer,
-    cost: &'a CostFunction,
+    optimizer: Box<dyn Optimizer>,
+    cost: CostFunction,
 }
 
-impl<'a> Model<'a> {
+impl Model {
     /// Constructs a new model given the layers.
+    ///
+    /// As the input dimensions are not known, the layers are validated against the dimensions of the first input of
+    /// `forward`, unless `with_input_shape` validates them first.
     pub fn new(
-        layers: Vec<&'a mut dyn Layer>,
-        optimizer: &'a dyn Optimizer,
-        cost: &'a CostFunction,
-    ) -> Model<'a> {
+        layers: Vec<Box<dyn Layer>>,
+        optimizer: Box<dyn Optimizer>,
+        cost: CostFunction,
+    ) -> Model {
         Model {
             layers,
+            input_shape: None,
+            is_validated: false,
             output: None,
             optimizer,
             cost,
         }
     }
 
+    /// Sets the input dimensions of the model, which include the batch dimension, validating that each layer can be
+    /// applied to the output of the previous layer.
+    pub fn with_input_shape(mut self, input_shape: Vec<usize>) -> Model {
+        self.output_shape(&input_shape);
+        self.input_shape = Some(input_shape);
+        self.is_validated = true;
+        self
+    }
+
+    /// Retrieves the input dimensions of the model, which include the batch dimension, if they are set.
+    pub fn input_shape(&self) -> Option<&[usize]> {
+        self.input_shape.as_deref()
+    }
+
+    /// Retrieves the layers of the model.
+    pub fn layers(&self) -> &[Box<dyn Layer>] {
+        &self.layers
+    }
+
+    /// Computes the output dimensions of the model for input dimensions, which include the batch dimension.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any layer cannot be applied to the output of the previous layer.
+    pub fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+        self.layers
+            .iter()
+            .fold(input_shape.to_vec(), |shape, layer| {
+                layer.output_shape(&shape)
+            })
+    }
+
+    /// Summarizes the type, output dimensions, and parameter count of each layer, for the input dimensions of the
+    /// model.
+    pub fn summary(&mut self) -> String {
+        let mut shape = self
+            .input_shape
+            .clone()
+            .expect("error: the input shape of the model must be set to summarize it");
+
+        let mut rows = vec![(
+            "Layer".to_string(),
+            "Output Shape".to_string(),
+            "Parameters".to_string(),
+        )];
+        let mut total = 0;
+        for layer in self.layers.iter_mut() {
+            shape = layer.output_shape(&shape);
+            let count: usize = layer.parameters().iter().map(|p| p.values().len()).sum();
+            total += count;
+            rows.push((layer.name(), format!("{:?}", shape), count.to_string()));
+        }
+
+        let name_width = rows.iter().map(|r| r.0.len()).max().unwrap() + 4;
+        let shape_width = rows.iter().map(|r| r.1.len()).max().unwrap() + 4;
+        let separator = "=".repeat(name_width + shape_width + rows[0].2.len());
+
+        let mut summary = String::new();
+        for (i, (name, shape, count)) in rows.iter().enumerate() {
+            summary += &format!(
+                "{:<name_width$}{:<shape_width$}{}\n",
+                name,
+                shape,
+                count,
+                name_width = name_width,
+                shape_width = shape_width
+            );
+            if i == 0 {
+                summary += &format!("{}\n", separator);
+            }
+        }
+
+        summary + &format!("{}\nTotal parameters: {}\n", separator, total)
+    }
+
     /// Computes the forward pass of a model.
     /// The input should have the dimensions batch size by input size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any layer cannot be applied to the output of the previous layer, which is checked before the first
+    /// forward pass, if the layers have not been validated.
     pub fn forward(&mut self, mut input: Array) -> Array {
+        if !self.is_validated {
+            self.output_shape(input.dimensions());
+            self.is_validated = true;
+        }
+
         for layer in &self.layers {
             input = layer.forward(input)
         }
@@ -41,6 +146,7 @@ impl<'a> Model<'a> {
     }
 
     /// Computes the backward pass of a model, and updates parameters.
+    /// Returns the sum of the loss, which is the loss itself for cost functions with a `Reduction` of `Mean`, or `Sum`.
     pub fn backward(&mut self, target: Array) -> Float {
         let output = self.output.as_ref().unwrap();
         let error = (self.cost)(&output, &target);
@@ -49,51 +155,344 @@ impl<'a> Model<'a> {
         error.sum_all()
     }
 
+    /// Computes the backward pass of a model, scaling the loss of each sample in the batch by the given weights.
+    /// The cost function must have a `Reduction` of `None`, and the sum of the weighted loss is returned.
+    pub fn backward_weighted(&mut self, target: Array, sample_weights: &Array) -> Float {
+        let output = self.output.as_ref().unwrap();
+        let error = cost::sample_weighted(
+            &(self.cost)(output, &target),
+            sample_weights,
+            cost::Reduction::Sum,
+        );
+        error.backward(None);
+
+        error.sum_all()
+    }
+
+    /// Sets whether the layers of the model are training, or are being used for inference.
+    pub fn set_training(&mut self, is_training: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.set_training(is_training);
+        }
+    }
+
     /// Updates all parameters of the model.
     pub fn update(&mut self) {
-        let optimizer = self.optimizer;
-        let parameters = self.parameters();
-        optimizer.update(parameters);
+        let parameters = self
+            .layers
+            .iter_mut()
+            .flat_map(|l| l.parameters())
+            .collect();
+        self.optimizer.update(parameters);
     }
 
     /// Retrieves the parameters of every layer in the model.
-    fn parameters(&mut self) -> Vec<&mut Array> {
+    pub fn parameters(&mut self) -> Vec<&mut Array> {
         self.layers
             .iter_mut()
             .map(|l| l.parameters())
             .flatten()
             .collect()
     }
+
+    /// Retrieves the buffers of every layer in the model, such as running statistics.
+    pub fn buffers(&mut self) -> Vec<&mut Array> {
+        self.layers.iter_mut().flat_map(|l| l.buffers()).collect()
+    }
+
+    /// Retrieves the parameters of every layer in the model, named by the index of the layer, and the name within the
+    /// layer, such as `layers.0.weights`.
+    pub fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, l)| layer::prefixed(&format!("layers.{}", i), l.named_parameters()))
+            .collect()
+    }
+
+    /// Retrieves the buffers of every layer in the model, named in the same way as the parameters.
+    pub fn named_buffers(&mut self) -> Vec<(String, &mut Array)> {
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, l)| layer::prefixed(&format!("layers.{}", i), l.named_buffers()))
+            .collect()
+    }
+
+    /// Retrieves the random number generators of every layer in the model.
+    pub fn generators(&mut self) -> Vec<&mut Generator> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.generators())
+            .collect()
+    }
+
+    /// Retrieves the optimizer of the model.
+    pub fn optimizer(&mut self) -> &mut dyn Optimizer {
+        self.optimizer.as_mut()
+    }
+
+    /// Retrieves copies of the parameters, followed by the buffers of the model.
+    pub(crate) fn arrays(&mut self) -> Vec<Array> {
+        let mut arrays: Vec<Array> = self.parameters().into_iter().map(|p| p.clone()).collect();
+        arrays.extend(self.buffers().into_iter().map(|b| b.clone()));
+        arrays
+    }
+
+    /// Checks that the number, and dimensions of the arrays match the parameters, followed by the buffers of the model.
+    pub(crate) fn check_arrays(&mut self, arrays: &[Array]) -> io::Result<()> {
+        let mut expected: Vec<Vec<usize>> = self
+            .parameters()
+            .iter()
+            .map(|p| p.dimensions().to_vec())
+            .collect();
+        expected.extend(self.buffers().iter().map(|b| b.dimensions().to_vec()));
+        if arrays.len() != expected.len() {
+            return Err(serialize::invalid_data(format!(
+                "the file contains {} arrays, but the model has {}",
+                arrays.len(),
+                expected.len()
+            )));
+        }
+
+        for (i, (array, dimensions)) in arrays.iter().zip(&expected).enumerate() {
+            if array.dimensions() != dimensions.as_slice() {
+                return Err(serialize::invalid_data(format!(
+                    "the dimensions {:?} of array {} do not match the model dimensions {:?}",
+                    array.dimensions(),
+                    i,
+                    dimensions
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores the parameters, followed by the buffers of the model, leaving the model unchanged if the number, or
+    /// dimensions of the arrays do not match.
+    pub(crate) fn set_arrays(&mut self, arrays: Vec<Array>) -> io::Result<()> {
+        self.check_arrays(&arrays)?;
+
+        let mut arrays = arrays.into_iter();
+        for parameter in self.parameters() {
+            *parameter = arrays.next().unwrap().tracked();
+        }
+        for buffer in self.buffers() {
+            *buffer = arrays.next().unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Saves the parameters, followed by the buffers of the model to a file, in the format of `serialize`.
+    pub fn save(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let arrays = self.arrays();
+        serialize::save(path, &arrays.iter().collect::<Vec<&Array>>())
+    }
+
+    /// Loads the parameters, and buffers of the model from a file saved by a model with the same architecture.
+    ///
+    /// Returns an error of kind `InvalidData` if the number, or dimensions of the saved arrays do not match the model,
+    /// in which case the model is unchanged.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.set_arrays(serialize::load(path)?)
+    }
+}
+
+/// A builder of a model, which infers the input size of each layer from the output dimensions of the previous layer.
+///
+/// # Examples
+///
+/// ```
+/// # use corgi::{activation, cost};
+/// # use corgi::model::ModelBuilder;
+/// # use corgi::optimizer::gd::GradientDescent;
+/// let model = ModelBuilder::new(vec![1, 8, 8])
+///     .conv(4, (3, 3), (1, 1), Some(activation::relu()))
+///     .flatten()
+///     .dense(10, Some(activation::softmax()))
+///     .optimizer(Box::new(GradientDescent::new(0.01)))
+///     .cost(cost::cross_entropy(cost::Reduction::Mean))
+///     .build();
+/// ```
+pub struct ModelBuilder {
+    input_dimensions: Vec<usize>,
+    dimensions: Vec<usize>,
+    layers: Vec<Box<dyn Layer>>,
+    initializer: Initializer,
+    optimizer: Option<Box<dyn Optimizer>>,
+    cost: Option<CostFunction>,
+}
+
+impl ModelBuilder {
+    /// Constructs a new model builder, given the dimensions of each input sample, excluding the batch dimension.
+    /// Parameters are initialized using He initialization, unless another initializer is specified.
+    pub fn new(input_dimensions: Vec<usize>) -> ModelBuilder {
+        ModelBuilder {
+            dimensions: input_dimensions.clone(),
+            input_dimensions,
+            layers: Vec::new(),
+            initializer: initializer::he(),
+            optimizer: None,
+            cost: None,
+        }
+    }
+
+    /// Sets the initializer of the parameters of the layers added after it.
+    pub fn initializer(mut self, initializer: Initializer) -> ModelBuilder {
+        self.initializer = initializer;
+        self
+    }
+
+    /// Adds a dense layer with the given output size, whose input size is the last dimension of the previous output.
+    pub fn dense(self, output_size: usize, activation: Option<Activation>) -> ModelBuilder {
+        let input_size = *self
+            .dimensions
+            .last()
+            .expect("error: a dense layer cannot follow a scalar output");
+
+        let layer = Dense::new(input_size, output_size, &self.initializer, activation);
+        self.layer(Box::new(layer))
+    }
+
+    /// Adds a convolutional layer with the given filter count, filter dimensions, and stride dimensions, whose image
+    /// depth is the depth of the previous output.
+    pub fn conv(
+        self,
+        filter_count: usize,
+        filter_dimensions: (usize, usize),
+        stride_dimensions: (usize, usize),
+        activation: Option<Activation>,
+    ) -> ModelBuilder {
+        assert!(
+            self.dimensions.len() == 3,
+            "error: a convolutional layer requires an output of depth by rows by columns, but got {:?}",
+            self.dimensions
+        );
+
+        let (filter_rows, filter_cols) = filter_dimensions;
+        let layer = Conv::new(
+            (filter_count, self.dimensions[0], filter_rows, filter_cols),
+            stride_dimensions,
+            &self.initializer,
+            activation,
+        );
+        self.layer(Box::new(layer))
+    }
+
+    /// Adds a flatten layer, which flattens each sample into a single dimension.
+    pub fn flatten(self) -> ModelBuilder {
+        self.layer(Box::new(Flatten::new()))
+    }
+
+    /// Adds any layer, validating that it can be applied to the previous output.
+    pub fn layer(mut self, layer: Box<dyn Layer>) -> ModelBuilder {
+        let input_shape: Vec<usize> = vec![1]
+            .into_iter()
+            .chain(self.dimensions.iter().copied())
+            .collect();
+
+        self.dimensions = layer.output_shape(&input_shape)[1..].to_vec();
+        self.layers.push(layer);
+        self
+    }
+
+    /// Sets the optimizer of the model.
+    pub fn optimizer(mut self, optimizer: Box<dyn Optimizer>) -> ModelBuilder {
+        self.optimizer = Some(optimizer);
+        self
+    }
+
+    /// Sets the cost function of the model.
+    pub fn cost(mut self, cost: CostFunction) -> ModelBuilder {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Retrieves the dimensions of each output sample of the layers added so far.
+    pub fn output_dimensions(&self) -> &[usize] {
+        &self.dimensions
+    }
+
+    /// Builds the model, which requires the optimizer, and cost function to be set. The input dimensions of the
+    /// model are the input dimensions of the builder, with a batch size of 1.
+    pub fn build(self) -> Model {
+        let input_shape = vec![1]
+            .into_iter()
+            .chain(self.input_dimensions.iter().copied())
+            .collect();
+
+        Model::new(
+            self.layers,
+            self.optimizer
+                .expect("error: the optimizer of the model must be set"),
+            self.cost
+                .expect("error: the cost function of the model must be set"),
+        )
+        .with_input_shape(input_shape)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::layer::conv::Conv;
+    use crate::layer::attention::MultiHeadAttention;
+    use crate::layer::batch_norm::BatchNorm;
+    use crate::layer::container::{self, Parallel, Residual, Sequential};
+    use crate::layer::conv::{Conv, Conv1d, Conv3d};
     use crate::layer::dense::Dense;
+    use crate::layer::embedding::Embedding;
+    use crate::layer::group_norm::GroupNorm;
+    use crate::layer::instance_norm::InstanceNorm;
+    use crate::layer::layer_norm::LayerNorm;
+    use crate::layer::pool::{AvgPool, MaxPool};
+    use crate::layer::positional::LearnedPositionalEmbedding;
+    use crate::layer::recurrent::{Gru, Lstm, Rnn};
+    use crate::layer::rms_norm::RmsNorm;
+    use crate::layer::shape::{Flatten, Permute, Reshape};
+    use crate::layer::transformer::{TransformerDecoderLayer, TransformerEncoderLayer};
+    use crate::layer::upsample::Upsample;
     use crate::optimizer::gd::GradientDescent;
     use crate::{activation, cost, initializer};
 
     use rand::Rng;
 
-    fn test_gradient(mut model: Model<'_>, cost: &CostFunction, input: Array, target: Array) {
+    fn test_gradient(model: Model, input: Array, target: Array) {
+        test_gradient_where(model, input, target, |_| true);
+    }
+
+    /// Checks the gradients of the parameters whose names satisfy `is_checked`, such as to skip parameters which are
+    /// not used by the forward pass.
+    fn test_gradient_where<F>(mut model: Model, input: Array, target: Array, is_checked: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        // the step of the finite differences, and the tolerance of the relative error
         #[cfg(feature = "f32")]
-        let epsilon = 0.1;
+        let (step, epsilon) = (1e-2, 1e-1);
         #[cfg(not(feature = "f32"))]
-        let epsilon = 1e-7;
+        let (step, epsilon) = (1e-5, 1e-7);
 
         model.forward(input.clone());
         model.backward(target.clone());
 
+        let names: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
         // due to borrow checking, we need to keep re-borrowing, and dropping the parameters
-        let parameters = model.parameters();
-        let length = parameters.len();
-        std::mem::drop(parameters);
-        for i in 0..length {
+        for (i, name) in names.iter().enumerate() {
+            if !is_checked(name) {
+                continue;
+            }
+
             let parameters = model.parameters();
             let value_length = parameters[i].values().len();
             let dimensions = parameters[i].dimensions().to_vec();
-            let gradient = parameters[i].gradient().to_owned().unwrap().clone();
+            let gradient = parameters[i].gradient().to_owned().unwrap();
             std::mem::drop(parameters);
 
             let mut numerator = 0.0;
@@ -101,7 +500,7 @@ mod tests {
 
             for j in 0..value_length {
                 let mut delta = vec![0.0; value_length];
-                delta[j] = epsilon;
+                delta[j] = step;
                 let delta = Array::from((dimensions.clone(), delta));
 
                 let mut parameters = model.parameters();
@@ -112,10 +511,10 @@ mod tests {
                 std::mem::drop(parameters);
 
                 let result_plus = model.forward(input.clone());
-                let error_plus = (cost)(&result_plus, &target.clone()).sum_all();
+                let error_plus = (model.cost)(&result_plus, &target.clone()).sum_all();
 
                 let mut delta = vec![0.0; value_length];
-                delta[j] = -2.0 * epsilon;
+                delta[j] = -2.0 * step;
                 let delta = Array::from((dimensions.clone(), delta));
 
                 let mut parameters = model.parameters();
@@ -126,10 +525,10 @@ mod tests {
                 std::mem::drop(parameters);
 
                 let result_minus = model.forward(input.clone());
-                let error_minus = (cost)(&result_minus, &target).sum_all();
+                let error_minus = (model.cost)(&result_minus, &target).sum_all();
 
                 let mut delta = vec![0.0; value_length];
-                delta[j] = epsilon;
+                delta[j] = step;
                 let delta = Array::from((dimensions.clone(), delta));
 
                 let mut parameters = model.parameters();
@@ -139,7 +538,7 @@ mod tests {
                 parameter.start_tracking();
                 std::mem::drop(parameters);
 
-                let numerical_gradient = (error_plus - error_minus) / (2.0 * epsilon);
+                let numerical_gradient = (error_plus - error_minus) / (2.0 * step);
                 numerator += ((gradient[j] - numerical_gradient).abs()).powf(2.0);
                 denominator += ((gradient[j] + numerical_gradient).abs()).powf(2.0);
             }
@@ -161,16 +560,494 @@ mod tests {
         let hidden_size = 16;
         let output_size = 2;
         let initializer = initializer::he();
-        let sigmoid = activation::sigmoid();
-        let softmax = activation::softmax();
-        let cross_entropy = cost::cross_entropy();
+        let cross_entropy = cost::cross_entropy(cost::Reduction::Mean);
         let gd = GradientDescent::new(learning_rate);
-        let mut l1 = Dense::new(input_size, hidden_size, &initializer, Some(&sigmoid));
-        let mut l2 = Dense::new(hidden_size, output_size, &initializer, Some(&softmax));
-        let model = Model::new(vec![&mut l1, &mut l2], &gd, &cross_entropy);
+        let l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = Dense::new(
+            hidden_size,
+            output_size,
+            &initializer,
+            Some(activation::softmax()),
+        );
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2)],
+            Box::new(gd),
+            cross_entropy,
+        );
 
         let (x, y, z, w) = (0.5, -0.25, 0.0, 1.0);
-        test_gradient(model, &cross_entropy, arr![x, y], arr![z, w]);
+        test_gradient(model, arr![x, y], arr![z, w]);
+    }
+
+    #[test]
+    fn test_bce_with_logits_gradient() {
+        let learning_rate = 0.0;
+        let input_size = 2;
+        let hidden_size = 16;
+        let output_size = 2;
+        let initializer = initializer::he();
+        let bce = cost::class_weighted(
+            cost::bce_with_logits(Some(arr![2.0, 0.5]), cost::Reduction::None),
+            arr![1.0, 3.0],
+            cost::Reduction::None,
+        );
+        let gd = GradientDescent::new(learning_rate);
+        let l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = Dense::new(hidden_size, output_size, &initializer, None);
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), bce);
+
+        let input = arr![arr![0.5, -0.25], arr![-1.0, 0.75]];
+        let target = arr![arr![1.0, 0.0], arr![1.0, 1.0]];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_huber_gradient() {
+        let learning_rate = 0.0;
+        let input_size = 2;
+        let hidden_size = 16;
+        let output_size = 2;
+        let initializer = initializer::he();
+        let huber = cost::huber(0.5, cost::Reduction::Mean);
+        let gd = GradientDescent::new(learning_rate);
+        let l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = Dense::new(hidden_size, output_size, &initializer, None);
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), huber);
+
+        let input = arr![arr![0.5, -0.25], arr![-1.0, 0.75]];
+        let target = arr![arr![2.0, 0.0], arr![-1.0, 0.25]];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_batch_norm_gradient() {
+        let learning_rate = 0.0;
+        let input_size = 2;
+        let hidden_size = 4;
+        let output_size = 2;
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(learning_rate);
+        let l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = BatchNorm::new(hidden_size, 0.1, 1e-5);
+        let l3 = Dense::new(hidden_size, output_size, &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        let input = arr![
+            arr![2.0, -1.0],
+            arr![-4.0, 3.0],
+            arr![1.0, 2.0],
+            arr![4.0, -4.0]
+        ];
+        let target = arr![
+            arr![2.0, 0.0],
+            arr![-1.0, 0.25],
+            arr![0.5, 0.5],
+            arr![1.0, 1.0]
+        ];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_layer_norm_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Dense::new(2, 4, &initializer, Some(activation::sigmoid()));
+        let l2 = LayerNorm::new(vec![4], 1e-5);
+        let l3 = Dense::new(4, 2, &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        let input = arr![arr![2.0, -1.0], arr![-4.0, 3.0], arr![1.0, 2.0]];
+        let target = arr![arr![2.0, 0.0], arr![-1.0, 0.25], arr![0.5, 0.5]];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_group_norm_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Dense::new(2, 8, &initializer, Some(activation::sigmoid()));
+        let l2 = GroupNorm::new(2, 8, 1e-5);
+        let l3 = Dense::new(8, 2, &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        let input = arr![arr![2.0, -1.0], arr![-4.0, 3.0], arr![1.0, 2.0]];
+        let target = arr![arr![2.0, 0.0], arr![-1.0, 0.25], arr![0.5, 0.5]];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_instance_norm_gradient() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Conv::new(
+            (2, 1, 2, 2),
+            (1, 1),
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = InstanceNorm::new(2, 1e-5);
+        let l3 = Conv::new((1, 2, 2, 2), (1, 1), &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        let input = Array::from((
+            vec![2, 1, 4, 4],
+            (0..32)
+                .map(|_| rng.gen_range(-2.0..2.0))
+                .collect::<Vec<Float>>(),
+        ));
+        let target = Array::from((
+            vec![2, 1, 2, 2],
+            (0..8)
+                .map(|_| rng.gen_range(0.0..1.0))
+                .collect::<Vec<Float>>(),
+        ));
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_rms_norm_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Dense::new(2, 4, &initializer, None);
+        let l2 = RmsNorm::new(vec![4], 1e-5);
+        let l3 = Dense::new(4, 2, &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        let input = arr![arr![2.0, -1.0], arr![-4.0, 3.0], arr![1.0, 2.0]];
+        let target = arr![arr![2.0, 0.0], arr![-1.0, 0.25], arr![0.5, 0.5]];
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_embedding_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Embedding::new(5, 3, &initializer, Some(0), Some(1.0));
+        let l2 = Dense::new(3, 2, &initializer, Some(activation::sigmoid()));
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
+
+        let input = arr![4.0, 1.0, 2.0, 4.0];
+        let target = arr![
+            arr![1.0, 0.0],
+            arr![0.0, 0.0],
+            arr![0.5, 0.5],
+            arr![1.0, 0.0]
+        ];
+        test_gradient(model, input, target);
+    }
+
+    fn sequence(dimensions: Vec<usize>) -> Array {
+        let mut rng = rand::thread_rng();
+        let length = dimensions.iter().product();
+        Array::from((
+            dimensions,
+            (0..length)
+                .map(|_| rng.gen_range(-1.0..1.0))
+                .collect::<Vec<Float>>(),
+        ))
+    }
+
+    #[test]
+    fn test_rnn_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Rnn::new(2, 3, 2, false, &initializer);
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        test_gradient(model, sequence(vec![4, 2, 2]), sequence(vec![4, 2, 3]));
+    }
+
+    #[test]
+    fn test_lstm_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Lstm::new(2, 3, 2, true, &initializer);
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        test_gradient(model, sequence(vec![4, 2, 2]), sequence(vec![4, 2, 6]));
+    }
+
+    #[test]
+    fn test_gru_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Gru::new(2, 3, 1, true, &initializer);
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        test_gradient(model, sequence(vec![4, 2, 2]), sequence(vec![4, 2, 6]));
+    }
+
+    #[test]
+    fn test_attention_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = MultiHeadAttention::new(4, 2, &initializer);
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        // the key biases add the same score to every key of a query, which cancels in the softmax
+        test_gradient_where(
+            model,
+            sequence(vec![2, 3, 4]),
+            sequence(vec![2, 3, 4]),
+            |name| !name.ends_with("key.biases"),
+        );
+    }
+
+    #[test]
+    fn test_transformer_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = TransformerEncoderLayer::new(4, 2, 8, 0.0, &initializer, None);
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        test_gradient_where(
+            model,
+            sequence(vec![2, 3, 4]),
+            sequence(vec![2, 3, 4]),
+            |name| !name.ends_with("key.biases"),
+        );
+    }
+
+    /// A decoder which attends to a fixed memory, as the decoder only uses its memory attention when given a memory.
+    struct MemoryDecoder {
+        decoder: TransformerDecoderLayer,
+        memory: Array,
+    }
+
+    impl Layer for MemoryDecoder {
+        fn forward(&self, input: Array) -> Array {
+            self.decoder
+                .forward_memory(input, Some(&self.memory), None, None)
+        }
+
+        fn parameters(&mut self) -> Vec<&mut Array> {
+            self.decoder.parameters()
+        }
+
+        fn output_shape(&self, input_shape: &[usize]) -> Vec<usize> {
+            self.decoder.output_shape(input_shape)
+        }
+
+        fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+            self.decoder.named_parameters()
+        }
+    }
+
+    #[test]
+    fn test_decoder_memory_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = MemoryDecoder {
+            decoder: TransformerDecoderLayer::new(4, 2, 8, 0.0, &initializer, None),
+            memory: sequence(vec![2, 2, 4]),
+        };
+        let model = Model::new(vec![Box::new(l1)], Box::new(gd), mse);
+
+        test_gradient_where(
+            model,
+            sequence(vec![2, 3, 4]),
+            sequence(vec![2, 3, 4]),
+            |name| !name.ends_with("key.biases"),
+        );
+    }
+
+    #[test]
+    fn test_positional_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = LearnedPositionalEmbedding::new(4, 4, &initializer);
+        let l2 = MultiHeadAttention::new(4, 2, &initializer).with_rotary();
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
+
+        test_gradient_where(
+            model,
+            sequence(vec![2, 3, 4]),
+            sequence(vec![2, 3, 4]),
+            |name| !name.ends_with("key.biases"),
+        );
+    }
+
+    #[test]
+    fn test_set_training() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Dense::new(2, 2, &initializer, None);
+        let l2 = BatchNorm::new(2, 0.1, 1e-5);
+        let mut model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
+
+        let input = arr![arr![0.5, -0.25], arr![-1.0, 0.75]];
+        let training = model.forward(input.clone());
+        model.set_training(false);
+        let inference = model.forward(input.clone());
+        assert_ne!(training, inference);
+        assert_eq!(inference, model.forward(input));
+    }
+
+    #[test]
+    fn test_conv_1d_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Conv1d::new((3, 2, 3), 1, &initializer, Some(activation::tanh()));
+        let l2 = MaxPool::new(vec![2]);
+        let l3 = Conv1d::new((1, 3, 2), 2, &initializer, None);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        test_gradient(model, sequence(vec![2, 2, 10]), sequence(vec![2, 1, 2]));
+    }
+
+    #[test]
+    fn test_conv_3d_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Conv3d::new(
+            (2, 1, 2, 2, 2),
+            (1, 1, 1),
+            &initializer,
+            Some(activation::sigmoid()),
+        );
+        let l2 = AvgPool::new(vec![2, 2, 2]);
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
+
+        test_gradient(
+            model,
+            sequence(vec![2, 1, 3, 5, 5]),
+            sequence(vec![2, 2, 1, 2, 2]),
+        );
+    }
+
+    #[test]
+    fn test_upsample_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Conv::new((2, 1, 2, 2), (2, 2), &initializer, None);
+        let l2 = Upsample::new(2.0, true);
+        let l3 = Upsample::with_size((5, 7), false);
+        let model = Model::new(
+            vec![Box::new(l1), Box::new(l2), Box::new(l3)],
+            Box::new(gd),
+            mse,
+        );
+
+        test_gradient(
+            model,
+            sequence(vec![2, 1, 4, 6]),
+            sequence(vec![2, 2, 5, 7]),
+        );
+    }
+
+    #[test]
+    fn test_classifier_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Conv::new((2, 1, 2, 2), (1, 1), &initializer, Some(activation::tanh()));
+        let l2 = Permute::new(vec![1, 2, 0]);
+        let l3 = Flatten::new();
+        let l4 = Dense::new(18, 4, &initializer, None);
+        let l5 = Reshape::new(vec![2, 2]);
+        let model = Model::new(
+            vec![
+                Box::new(l1),
+                Box::new(l2),
+                Box::new(l3),
+                Box::new(l4),
+                Box::new(l5),
+            ],
+            Box::new(gd),
+            mse,
+        );
+
+        test_gradient(model, sequence(vec![3, 1, 4, 4]), sequence(vec![3, 2, 2]));
+    }
+
+    #[test]
+    fn test_container_gradient() {
+        let initializer = initializer::he();
+        let mse = cost::mse(cost::Reduction::Mean);
+        let gd = GradientDescent::new(0.0);
+        let l1 = Residual::new(Box::new(Sequential::new(vec![
+            Box::new(Dense::new(3, 4, &initializer, Some(activation::tanh()))),
+            Box::new(Dense::new(4, 3, &initializer, None)),
+        ])));
+        let l2 = Parallel::new(
+            vec![
+                Box::new(Dense::new(3, 2, &initializer, Some(activation::tanh()))),
+                Box::new(Residual::new(Box::new(Dense::new(
+                    3,
+                    3,
+                    &initializer,
+                    None,
+                )))),
+            ],
+            container::concat(),
+        );
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
+
+        test_gradient(model, sequence(vec![4, 3]), sequence(vec![4, 5]));
     }
 
     #[test]
@@ -187,18 +1064,17 @@ mod tests {
         let output_size = output_dimensions.iter().product();
 
         let initializer = initializer::he();
-        let activation = activation::relu();
-        let mse = cost::mse();
+        let mse = cost::mse(cost::Reduction::Mean);
         let gd = GradientDescent::new(learning_rate);
 
-        let mut l1 = Conv::new(
+        let l1 = Conv::new(
             (16, image_depth, 3, 3),
             (2, 2),
             &initializer,
-            Some(activation),
+            Some(activation::relu()),
         );
-        let mut l2 = Conv::new((1, 16, 2, 2), (2, 2), &initializer, None);
-        let model = Model::new(vec![&mut l1, &mut l2], &gd, &mse);
+        let l2 = Conv::new((1, 16, 2, 2), (2, 2), &initializer, None);
+        let model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
 
         let input = Array::from((
             image_dimensions,
@@ -214,7 +1090,153 @@ mod tests {
                 .collect::<Vec<Float>>(),
         ));
 
-        test_gradient(model, &mse, input, target);
+        test_gradient(model, input, target);
+    }
+
+    #[test]
+    fn test_builder() {
+        let builder = ModelBuilder::new(vec![1, 6, 6])
+            .conv(2, (3, 3), (1, 1), Some(activation::tanh()))
+            .layer(Box::new(MaxPool::new(vec![2, 2])));
+        assert_eq!(builder.output_dimensions(), &[2, 2, 2]);
+
+        let builder = builder.flatten().dense(3, None);
+        assert_eq!(builder.output_dimensions(), &[3]);
+
+        let model = builder
+            .optimizer(Box::new(GradientDescent::new(0.0)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build();
+        test_gradient(model, sequence(vec![2, 1, 6, 6]), sequence(vec![2, 3]));
+    }
+
+    /// Constructs a model in a function, and returns it to the caller.
+    fn regression_model() -> Model {
+        ModelBuilder::new(vec![2])
+            .dense(16, Some(activation::relu()))
+            .dense(2, None)
+            .optimizer(Box::new(GradientDescent::new(0.01)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build()
+    }
+
+    #[test]
+    fn test_builder_owned() {
+        let mut model = regression_model();
+        let input = arr![arr![0.5, -0.25], arr![-1.0, 0.75]];
+        let target = arr![arr![1.0, 0.0], arr![0.0, 1.0]];
+
+        model.forward(input.clone());
+        let loss = model.backward(target.clone());
+        model.update();
+
+        model.forward(input);
+        assert!(model.backward(target) < loss);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut model = ModelBuilder::new(vec![1, 6, 6])
+            .conv(2, (3, 3), (1, 1), Some(activation::relu()))
+            .flatten()
+            .dense(3, None)
+            .optimizer(Box::new(GradientDescent::new(0.0)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build();
+        assert_eq!(model.output_shape(&[4, 1, 6, 6]), vec![4, 3]);
+
+        let summary = model.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[2].starts_with("Conv") && lines[2].contains("[1, 2, 4, 4]"));
+        assert!(lines[2].ends_with("20"));
+        assert!(lines[3].starts_with("Flatten") && lines[3].contains("[1, 32]"));
+        assert!(lines[4].starts_with("Dense") && lines[4].ends_with("99"));
+        assert_eq!(lines[6], "Total parameters: 119");
+    }
+
+    fn normalized_model() -> Model {
+        ModelBuilder::new(vec![4])
+            .dense(3, Some(activation::relu()))
+            .layer(Box::new(BatchNorm::new(3, 0.9, 1e-5)))
+            .dense(2, None)
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build()
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("corgi_save_{}.bin", std::process::id()));
+        let input = sequence(vec![3, 4]);
+
+        // the running statistics are saved with the parameters
+        let mut model = normalized_model();
+        model.forward(input.clone());
+        model.set_training(false);
+        let output = model.forward(input.clone());
+        model.save(&path).unwrap();
+
+        let mut loaded = normalized_model();
+        loaded.load(&path).unwrap();
+        loaded.set_training(false);
+        assert_eq!(loaded.forward(input), output);
+
+        let error = regression_model().load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_builder_mismatch() {
+        ModelBuilder::new(vec![4]).layer(Box::new(Dense::new(3, 2, &initializer::he(), None)));
+    }
+
+    #[test]
+    #[should_panic(expected = "must end with the input size 4")]
+    fn test_new_mismatch() {
+        let initializer = initializer::he();
+        let mut model = Model::new(
+            vec![
+                Box::new(Dense::new(2, 3, &initializer, None)),
+                Box::new(Dense::new(4, 2, &initializer, None)),
+            ],
+            Box::new(GradientDescent::new(0.1)),
+            cost::mse(cost::Reduction::Mean),
+        );
+
+        // the layers are validated before the first forward pass
+        model.forward(arr![arr![1.0, 2.0]]);
+    }
+
+    #[test]
+    fn test_default_output_shape() {
+        /// A layer which does not compute its output dimensions itself.
+        struct Pairs;
+
+        impl Layer for Pairs {
+            fn forward(&self, input: Array) -> Array {
+                let batch_size = input.dimensions()[0];
+                input.reshape(vec![batch_size, input.values().len() / batch_size / 2, 2])
+            }
+
+            fn parameters(&mut self) -> Vec<&mut Array> {
+                Vec::new()
+            }
+        }
+
+        let model = Model::new(
+            vec![
+                Box::new(Pairs),
+                Box::new(Dense::new(2, 3, &initializer::he(), None)),
+            ],
+            Box::new(GradientDescent::new(0.1)),
+            cost::mse(cost::Reduction::Mean),
+        )
+        .with_input_shape(vec![5, 4]);
+        assert_eq!(model.output_shape(&[5, 4]), vec![5, 2, 3]);
     }
 
     #[test]
@@ -226,12 +1248,16 @@ mod tests {
         let hidden_size = 16;
         let output_size = 2;
         let initializer = initializer::he();
-        let relu = activation::relu();
-        let mse = cost::mse();
+        let mse = cost::mse(cost::Reduction::Mean);
         let gd = GradientDescent::new(learning_rate);
-        let mut l1 = Dense::new(input_size, hidden_size, &initializer, Some(&relu));
-        let mut l2 = Dense::new(hidden_size, output_size, &initializer, None);
-        let mut model = Model::new(vec![&mut l1, &mut l2], &gd, &mse);
+        let l1 = Dense::new(
+            input_size,
+            hidden_size,
+            &initializer,
+            Some(activation::relu()),
+        );
+        let l2 = Dense::new(hidden_size, output_size, &initializer, None);
+        let mut model = Model::new(vec![Box::new(l1), Box::new(l2)], Box::new(gd), mse);
 
         for _ in 0..8 {
             let mut input = vec![0.0; input_size * batch_size];
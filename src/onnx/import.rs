@@ -0,0 +1,1061 @@
+//! Import of ONNX models, whose graphs are evaluated with array operations, for inference, and fine-tuning.
+//!
+//! The `Add`, `Conv`, `Flatten`, `Gemm`, `Identity`, `MatMul`, `MaxPool`, `Relu`, `Reshape`, `Sigmoid`, and
+//! `Softmax` operators of the default operator set are supported. Floating point initializers are the parameters of
+//! the imported graph, and integer initializers are the shapes of `Reshape` nodes.
+//!
+//! Convolutions are supported without padding, dilation, or groups, and max pooling is supported over
+//! non-overlapping windows, whose strides are the kernel shape. Softmax is supported along the last dimension.
+//! Axes, and the shapes of `Reshape` nodes, are checked when the model is read, if the graph declares the number of
+//! dimensions of its input, and otherwise when the graph is evaluated.
+
+use super::protobuf::Fields;
+use crate::array::*;
+use crate::layer::Layer;
+use crate::numbers::*;
+use crate::serialize::invalid_data;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+/// The ONNX data type of 32-bit floating point values.
+const FLOAT: i64 = 1;
+/// The ONNX data type of 64-bit integers.
+const INT64: i64 = 7;
+/// The ONNX data type of 64-bit floating point values.
+const DOUBLE: i64 = 11;
+
+/// The operator types which are supported.
+const SUPPORTED_OPERATORS: [&str; 11] = [
+    "Add", "Conv", "Flatten", "Gemm", "Identity", "MatMul", "MaxPool", "Relu", "Reshape",
+    "Sigmoid", "Softmax",
+];
+
+/// An operator of a node, with its attributes.
+enum Operator {
+    Add,
+    Conv {
+        strides: Option<Vec<usize>>,
+    },
+    Flatten {
+        axis: i64,
+    },
+    Gemm {
+        alpha: Float,
+        beta: Float,
+        trans_a: bool,
+        trans_b: bool,
+    },
+    Identity,
+    MatMul,
+    MaxPool {
+        kernel_shape: Vec<usize>,
+    },
+    Relu,
+    Reshape,
+    Sigmoid,
+    Softmax {
+        axis: i64,
+    },
+}
+
+/// A node of the graph, which computes its output from the values of its inputs.
+struct Node {
+    operator: Operator,
+    inputs: Vec<String>,
+    output: String,
+}
+
+/// A graph imported from an ONNX model, which is a layer whose parameters are the floating point initializers of the
+/// graph.
+pub struct ImportedGraph {
+    input: String,
+    output: String,
+    nodes: Vec<Node>,
+    parameters: Vec<(String, Array)>,
+    shapes: HashMap<String, Vec<i64>>,
+}
+
+/// The values of an initializer.
+enum Tensor {
+    Float(Array),
+    Int(Vec<i64>),
+}
+
+/// Converts the little-endian bytes of values, of a given width, to values.
+fn from_le_bytes<T>(bytes: &[u8], width: usize, f: impl Fn(&[u8]) -> T) -> Vec<T> {
+    bytes.chunks_exact(width).map(f).collect()
+}
+
+/// Reads a named tensor, whose values may be raw, or in the typed data fields.
+fn read_tensor(tensor: &Fields) -> Result<(String, Tensor)> {
+    let name = tensor.string(8)?;
+    if tensor.int(14)? == Some(1) {
+        return Err(invalid_data(format!(
+            "the tensor {} is stored externally, which is not supported",
+            name
+        )));
+    }
+
+    let dimensions = tensor.ints(1)?;
+    if dimensions.iter().any(|&d| d <= 0) {
+        return Err(invalid_data(format!(
+            "the tensor {} has the dimensions {:?}, which must be positive",
+            name, dimensions
+        )));
+    }
+    let dimensions: Vec<usize> = dimensions.iter().map(|&d| d as usize).collect();
+    let length = dimensions
+        .iter()
+        .try_fold(1usize, |n, &d| n.checked_mul(d))
+        .ok_or_else(|| {
+            invalid_data(format!(
+                "the tensor {} has the dimensions {:?}, which are too large",
+                name, dimensions
+            ))
+        })?;
+    let raw = tensor.bytes(9)?.last().map(|b| b.to_vec());
+
+    // the count of values is checked before they are shaped into an array
+    let check = |count: usize| {
+        if count == length {
+            Ok(())
+        } else {
+            Err(invalid_data(format!(
+                "the tensor {} has {} values, but the dimensions {:?}",
+                name, count, dimensions
+            )))
+        }
+    };
+    let floats = |values: Vec<Float>| -> Result<Tensor> {
+        check(values.len())?;
+        Ok(Tensor::Float(Array::from((dimensions.clone(), values))))
+    };
+
+    let tensor = match tensor.int(2)?.unwrap_or(0) {
+        FLOAT => floats(from_le_bytes(
+            &raw.map_or_else(|| tensor.fixed(4, 4), Ok)?,
+            4,
+            |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Float,
+        ))?,
+        DOUBLE => floats(from_le_bytes(
+            &raw.map_or_else(|| tensor.fixed(10, 8), Ok)?,
+            8,
+            |b| {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(b);
+                f64::from_le_bytes(bytes) as Float
+            },
+        ))?,
+        INT64 => {
+            let values = match raw {
+                Some(raw) => from_le_bytes(&raw, 8, |b| {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(b);
+                    i64::from_le_bytes(bytes)
+                }),
+                None => tensor.ints(7)?,
+            };
+            check(values.len())?;
+            Tensor::Int(values)
+        }
+        data_type => {
+            return Err(invalid_data(format!(
+                "the tensor {} has the data type {}, which is not supported",
+                name, data_type
+            )))
+        }
+    };
+
+    Ok((name, tensor))
+}
+
+/// The attributes of a node, by name.
+struct Attributes<'a> {
+    node: String,
+    attributes: HashMap<String, Fields<'a>>,
+}
+
+impl<'a> Attributes<'a> {
+    fn read(node: &Fields<'a>, name: &str) -> Result<Attributes<'a>> {
+        let attributes = node
+            .messages(5)?
+            .into_iter()
+            .map(|a| Ok((a.string(1)?, a)))
+            .collect::<Result<HashMap<String, Fields>>>()?;
+
+        Ok(Attributes {
+            node: name.to_string(),
+            attributes,
+        })
+    }
+
+    fn int(&self, name: &str, default: i64) -> Result<i64> {
+        match self.attributes.get(name) {
+            Some(attribute) => Ok(attribute.int(3)?.unwrap_or(0)),
+            None => Ok(default),
+        }
+    }
+
+    fn float(&self, name: &str, default: Float) -> Result<Float> {
+        match self.attributes.get(name) {
+            Some(attribute) => Ok(attribute.float(2)?.unwrap_or(0.0) as Float),
+            None => Ok(default),
+        }
+    }
+
+    fn ints(&self, name: &str) -> Result<Option<Vec<i64>>> {
+        self.attributes.get(name).map(|a| a.ints(8)).transpose()
+    }
+
+    fn string(&self, name: &str) -> Result<String> {
+        match self.attributes.get(name) {
+            Some(attribute) => attribute.string(4),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Retrieves a list of sizes, which must be positive.
+    fn sizes(&self, name: &str) -> Result<Option<Vec<usize>>> {
+        match self.ints(name)? {
+            Some(ints) if ints.iter().any(|&i| i <= 0) => Err(self.unsupported(name, &ints)),
+            Some(ints) => Ok(Some(ints.iter().map(|&i| i as usize).collect())),
+            None => Ok(None),
+        }
+    }
+
+    /// Ensures that an attribute has its default value, or is absent.
+    fn require(&self, name: &str, is_default: impl Fn(&[i64]) -> bool) -> Result<()> {
+        match self.ints(name)? {
+            Some(ints) if !is_default(&ints) => Err(self.unsupported(name, &ints)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ensures that an integer attribute is zero, or is absent.
+    fn require_zero(&self, name: &str) -> Result<()> {
+        match self.int(name, 0)? {
+            0 => Ok(()),
+            value => Err(self.unsupported(name, &value)),
+        }
+    }
+
+    /// Ensures that the padding is absent, which is the only padding supported.
+    fn require_unpadded(&self) -> Result<()> {
+        self.require("pads", |pads| pads.iter().all(|&p| p == 0))?;
+        let auto_pad = self.string("auto_pad")?;
+        if !["", "NOTSET", "VALID"].contains(&auto_pad.as_str()) {
+            return Err(self.unsupported("auto_pad", &auto_pad));
+        }
+
+        Ok(())
+    }
+
+    fn unsupported(&self, name: &str, value: &impl std::fmt::Debug) -> std::io::Error {
+        invalid_data(format!(
+            "the attribute {} of the node {} has the value {:?}, which is not supported",
+            name, self.node, value
+        ))
+    }
+}
+
+/// Reads the operator of a node, whose operator type is supported.
+fn read_operator(op_type: &str, attributes: &Attributes, opset_version: i64) -> Result<Operator> {
+    Ok(match op_type {
+        "Add" => Operator::Add,
+        "Conv" => {
+            attributes.require_unpadded()?;
+            attributes.require("dilations", |d| d.iter().all(|&d| d == 1))?;
+            if attributes.int("group", 1)? != 1 {
+                return Err(attributes.unsupported("group", &attributes.int("group", 1)?));
+            }
+
+            Operator::Conv {
+                strides: attributes.sizes("strides")?,
+            }
+        }
+        "Flatten" => Operator::Flatten {
+            axis: attributes.int("axis", 1)?,
+        },
+        "Gemm" => Operator::Gemm {
+            alpha: attributes.float("alpha", 1.0)?,
+            beta: attributes.float("beta", 1.0)?,
+            trans_a: attributes.int("transA", 0)? != 0,
+            trans_b: attributes.int("transB", 0)? != 0,
+        },
+        "Identity" => Operator::Identity,
+        "MatMul" => Operator::MatMul,
+        "MaxPool" => {
+            attributes.require_unpadded()?;
+            attributes.require("dilations", |d| d.iter().all(|&d| d == 1))?;
+            attributes.require_zero("ceil_mode")?;
+            attributes.require_zero("storage_order")?;
+
+            let kernel_shape = attributes
+                .sizes("kernel_shape")?
+                .ok_or_else(|| attributes.unsupported("kernel_shape", &"None"))?;
+            let strides = attributes
+                .sizes("strides")?
+                .unwrap_or_else(|| vec![1; kernel_shape.len()]);
+            if strides != kernel_shape {
+                return Err(attributes.unsupported("strides", &strides));
+            }
+
+            Operator::MaxPool { kernel_shape }
+        }
+        "Relu" => Operator::Relu,
+        "Reshape" => {
+            attributes.require_zero("allowzero")?;
+            Operator::Reshape
+        }
+        "Sigmoid" => Operator::Sigmoid,
+        "Softmax" => Operator::Softmax {
+            // the default axis is the last dimension since opset 13, and the second dimension before
+            axis: attributes.int("axis", if opset_version >= 13 { -1 } else { 1 })?,
+        },
+        op_type => {
+            return Err(invalid_data(format!(
+                "the operator {} is not supported",
+                op_type
+            )))
+        }
+    })
+}
+
+/// Reads the number of dimensions of an input, or output, if its shape is declared.
+fn declared_rank(value_info: &Fields) -> Result<Option<usize>> {
+    let tensor_type = match value_info.messages(2)?.pop() {
+        Some(value_type) => value_type.messages(1)?.pop(),
+        None => None,
+    };
+    let shape = match tensor_type {
+        Some(tensor_type) => tensor_type.messages(2)?.pop(),
+        None => None,
+    };
+
+    match shape {
+        Some(shape) => Ok(Some(shape.messages(1)?.len())),
+        None => Ok(None),
+    }
+}
+
+/// Computes the number of dimensions of the output of a node, given the number of dimensions of its inputs, if they
+/// are known.
+fn output_rank(
+    operator: &Operator,
+    inputs: &[String],
+    ranks: &HashMap<String, usize>,
+    shapes: &HashMap<String, Vec<i64>>,
+) -> Option<usize> {
+    let rank = |i: usize| inputs.get(i).and_then(|input| ranks.get(input)).copied();
+    match operator {
+        Operator::Add => Some(rank(0)?.max(rank(1)?)),
+        Operator::Flatten { .. } | Operator::Gemm { .. } => Some(2),
+        Operator::MatMul => match (rank(0)?, rank(1)?) {
+            (a, b) if a >= 2 && b >= 2 => Some(a.max(b)),
+            _ => None,
+        },
+        Operator::Reshape => Some(shapes[&inputs[1]].len()),
+        _ => rank(0),
+    }
+}
+
+/// Checks the attributes of a node which depend on the number of dimensions of its first input, if it is known.
+fn check_node(node: &Node, rank: Option<usize>, name: &str) -> Result<()> {
+    let error = |message: String| Err(invalid_data(format!("the node {} {}", name, message)));
+    match (&node.operator, rank) {
+        (Operator::Flatten { axis }, Some(rank))
+            if *axis < -(rank as i64) || *axis > rank as i64 =>
+        {
+            error(format!(
+                "has the axis {}, which is out of range for {} dimensions",
+                axis, rank
+            ))
+        }
+        (Operator::Softmax { axis }, Some(rank)) if *axis != -1 && *axis != rank as i64 - 1 => {
+            error(format!(
+                "has the axis {}, but softmax is only supported along the last of {} dimensions",
+                axis, rank
+            ))
+        }
+        // without the number of dimensions, only the negative axes other than the last can be rejected
+        (Operator::Softmax { axis }, None) if *axis < -1 => error(format!(
+            "has the axis {}, but softmax is only supported along the last dimension",
+            axis
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Normalizes a possibly negative axis of an array with a given number of dimensions.
+fn normalize_axis(axis: i64, dimension_count: usize, maximum: usize) -> usize {
+    let normalized = if axis < 0 {
+        axis + dimension_count as i64
+    } else {
+        axis
+    };
+    assert!(
+        normalized >= 0 && normalized as usize <= maximum,
+        "error: the axis {} is out of range for {} dimensions",
+        axis,
+        dimension_count
+    );
+
+    normalized as usize
+}
+
+/// Reads the graph of an ONNX model.
+pub fn read(bytes: &[u8]) -> Result<ImportedGraph> {
+    let model = Fields::decode(bytes)?;
+    let opset_version = model
+        .messages(8)?
+        .iter()
+        .map(|opset| Ok((opset.string(1)?, opset.int(2)?)))
+        .collect::<Result<Vec<(String, Option<i64>)>>>()?
+        .into_iter()
+        .find(|(domain, _)| domain.is_empty() || domain == "ai.onnx")
+        .and_then(|(_, version)| version)
+        .unwrap_or(super::OPSET_VERSION);
+    let graph = model
+        .messages(7)?
+        .pop()
+        .ok_or_else(|| invalid_data("the model does not contain a graph".to_string()))?;
+
+    let mut parameters = Vec::new();
+    let mut shapes = HashMap::new();
+    for initializer in graph.messages(5)? {
+        match read_tensor(&initializer)? {
+            (name, Tensor::Float(array)) => parameters.push((name, array.tracked())),
+            (name, Tensor::Int(values)) => {
+                shapes.insert(name, values);
+            }
+        }
+    }
+
+    let nodes = graph.messages(1)?;
+    let op_types = nodes
+        .iter()
+        .map(|node| {
+            let domain = node.string(7)?;
+            let op_type = node.string(4)?;
+            Ok(if domain.is_empty() || domain == "ai.onnx" {
+                op_type
+            } else {
+                format!("{}.{}", domain, op_type)
+            })
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    let mut unsupported: Vec<&str> = Vec::new();
+    for op_type in &op_types {
+        if !SUPPORTED_OPERATORS.contains(&op_type.as_str())
+            && !unsupported.contains(&op_type.as_str())
+        {
+            unsupported.push(op_type);
+        }
+    }
+    if !unsupported.is_empty() {
+        return Err(invalid_data(format!(
+            "the graph contains the operators {}, which are not supported",
+            unsupported.join(", ")
+        )));
+    }
+
+    let input_infos = graph.messages(11)?;
+    let mut inputs = input_infos
+        .iter()
+        .map(|input| input.string(1))
+        .collect::<Result<Vec<String>>>()?;
+    // inputs with initializers are constants, rather than inputs of the graph
+    inputs.retain(|i| !shapes.contains_key(i) && parameters.iter().all(|(n, _)| n != i));
+    let outputs = graph
+        .messages(12)?
+        .iter()
+        .map(|output| output.string(1))
+        .collect::<Result<Vec<String>>>()?;
+    if inputs.len() != 1 || outputs.len() != 1 {
+        return Err(invalid_data(format!(
+            "the graph has the inputs {:?}, and the outputs {:?}, but must have a single input, and output",
+            inputs, outputs
+        )));
+    }
+
+    let mut defined: HashSet<String> = parameters.iter().map(|(n, _)| n.clone()).collect();
+    defined.insert(inputs[0].clone());
+
+    // the number of dimensions of each value is known if the shape of the input is declared, and is used to check
+    // the axes of the nodes
+    let mut ranks: HashMap<String, usize> = parameters
+        .iter()
+        .map(|(n, p)| (n.clone(), p.dimensions().len()))
+        .collect();
+    for input in &input_infos {
+        if input.string(1)? == inputs[0] {
+            if let Some(rank) = declared_rank(input)? {
+                ranks.insert(inputs[0].clone(), rank);
+            }
+        }
+    }
+    let mut graph_nodes = Vec::new();
+    for (node, op_type) in nodes.iter().zip(&op_types) {
+        let outputs = node.bytes(2)?;
+        let name = match node.string(3)? {
+            name if name.is_empty() => op_type.clone(),
+            name => name,
+        };
+        let node_inputs = node
+            .bytes(1)?
+            .iter()
+            .map(|i| String::from_utf8(i.to_vec()))
+            .collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|_| invalid_data(format!("the inputs of the node {} are invalid", name)))?;
+
+        if outputs.len() != 1 {
+            return Err(invalid_data(format!(
+                "the node {} has {} outputs, but only a single output is supported",
+                name,
+                outputs.len()
+            )));
+        }
+        for (i, input) in node_inputs.iter().enumerate() {
+            // the shape of a reshape is an integer initializer, and optional inputs are empty
+            let is_shape = op_type == "Reshape" && i == 1 && shapes.contains_key(input);
+            if !(is_shape || input.is_empty() || defined.contains(input)) {
+                return Err(invalid_data(format!(
+                    "the input {} of the node {} is not defined before the node",
+                    input, name
+                )));
+            }
+        }
+        let required_count = match op_type.as_str() {
+            "Add" | "Conv" | "Gemm" | "MatMul" | "Reshape" => 2,
+            _ => 1,
+        };
+        if node_inputs.len() < required_count
+            || node_inputs[..required_count].contains(&String::new())
+        {
+            return Err(invalid_data(format!(
+                "the node {} has the inputs {:?}, but requires {} inputs",
+                name, node_inputs, required_count
+            )));
+        }
+        if op_type == "Reshape" {
+            let shape = shapes.get(&node_inputs[1]).ok_or_else(|| {
+                invalid_data(format!(
+                    "the shape of the reshape node {} must be an integer initializer",
+                    name
+                ))
+            })?;
+            let rank = ranks.get(&node_inputs[0]);
+            if shape.iter().any(|&d| d < -1)
+                || shape.iter().filter(|&&d| d == -1).count() > 1
+                || shape
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &d)| d == 0 && matches!(rank, Some(&r) if i >= r))
+            {
+                return Err(invalid_data(format!(
+                    "the reshape node {} has the shape {:?}, which is invalid",
+                    name, shape
+                )));
+            }
+        }
+
+        let attributes = Attributes::read(node, &name)?;
+        let output = node.string(2)?;
+        let graph_node = Node {
+            operator: read_operator(op_type, &attributes, opset_version)?,
+            inputs: node_inputs,
+            output: output.clone(),
+        };
+        check_node(
+            &graph_node,
+            ranks.get(&graph_node.inputs[0]).copied(),
+            &name,
+        )?;
+        if let Some(rank) = output_rank(&graph_node.operator, &graph_node.inputs, &ranks, &shapes) {
+            ranks.insert(output.clone(), rank);
+        }
+
+        defined.insert(output);
+        graph_nodes.push(graph_node);
+    }
+
+    if !defined.contains(&outputs[0]) {
+        return Err(invalid_data(format!(
+            "the output {} of the graph is not computed by a node",
+            outputs[0]
+        )));
+    }
+
+    Ok(ImportedGraph {
+        input: inputs.remove(0),
+        output: outputs[0].clone(),
+        nodes: graph_nodes,
+        parameters,
+        shapes,
+    })
+}
+
+/// Loads the graph of an ONNX model from a file.
+pub fn load(path: impl AsRef<Path>) -> Result<ImportedGraph> {
+    read(&fs::read(path)?)
+}
+
+impl ImportedGraph {
+    /// Computes the output of a node, given the values of its inputs, which are absent for optional inputs.
+    fn evaluate(&self, node: &Node, inputs: &[Option<&Array>]) -> Array {
+        let input = |i: usize| inputs[i].unwrap();
+        let x = input(0);
+        match &node.operator {
+            Operator::Add => x + input(1),
+            Operator::Conv { strides } => {
+                let filters = input(1);
+                let spatial_count = filters.dimensions().len() - 2;
+                let strides = strides.clone().unwrap_or_else(|| vec![1; spatial_count]);
+
+                let result = x.conv_nd(filters, &strides);
+                match inputs.get(2).copied().flatten() {
+                    // the biases are broadcast over the spatial dimensions of the output
+                    Some(biases) => {
+                        let bias_dimensions = vec![biases.values().len()]
+                            .into_iter()
+                            .chain(vec![1; spatial_count])
+                            .collect();
+                        &result + &biases.reshape(bias_dimensions)
+                    }
+                    None => result,
+                }
+            }
+            Operator::Flatten { axis } => {
+                let dimensions = x.dimensions();
+                let axis = normalize_axis(*axis, dimensions.len(), dimensions.len());
+                x.reshape(vec![
+                    dimensions[..axis].iter().product(),
+                    dimensions[axis..].iter().product(),
+                ])
+            }
+            Operator::Gemm {
+                alpha,
+                beta,
+                trans_a,
+                trans_b,
+            } => {
+                let product = Array::matmul((x, *trans_a), (input(1), *trans_b), None);
+                let product = if *alpha == 1.0 {
+                    product
+                } else {
+                    &product * *alpha
+                };
+                match inputs.get(2).copied().flatten() {
+                    Some(c) if *beta == 1.0 => &product + c,
+                    Some(c) => &product + &(c * *beta),
+                    None => product,
+                }
+            }
+            Operator::Identity => x.clone(),
+            Operator::MatMul => Array::matmul((x, false), (input(1), false), None),
+            Operator::MaxPool { kernel_shape } => x.max_pool(kernel_shape),
+            Operator::Relu => x.relu(),
+            Operator::Reshape => {
+                let shape = &self.shapes[&node.inputs[1]];
+                let mut dimensions: Vec<usize> = shape
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &d)| match d {
+                        0 => x.dimensions()[i],
+                        d if d < 0 => 1,
+                        d => d as usize,
+                    })
+                    .collect();
+                // a single dimension of -1 is inferred from the remaining dimensions
+                if let Some(i) = shape.iter().position(|&d| d == -1) {
+                    dimensions[i] = x.values().len() / dimensions.iter().product::<usize>();
+                }
+
+                assert!(
+                    dimensions.iter().product::<usize>() == x.values().len(),
+                    "error: cannot reshape the dimensions {:?} to {:?}",
+                    x.dimensions(),
+                    shape
+                );
+                x.reshape(dimensions)
+            }
+            Operator::Sigmoid => x.sigmoid(),
+            Operator::Softmax { axis } => {
+                let dimension_count = x.dimensions().len();
+                let axis = normalize_axis(*axis, dimension_count, dimension_count - 1);
+                assert!(
+                    axis == dimension_count - 1,
+                    "error: softmax is only supported along the last dimension, but got axis {}",
+                    axis
+                );
+                x.softmax()
+            }
+        }
+    }
+}
+
+impl Layer for ImportedGraph {
+    fn forward(&self, input: Array) -> Array {
+        let mut values: HashMap<&str, Array> = self
+            .parameters
+            .iter()
+            .map(|(name, parameter)| (name.as_str(), parameter.clone()))
+            .collect();
+        values.insert(&self.input, input);
+
+        for node in &self.nodes {
+            let inputs: Vec<Option<&Array>> =
+                node.inputs.iter().map(|i| values.get(i.as_str())).collect();
+            let output = self.evaluate(node, &inputs);
+            values.insert(&node.output, output);
+        }
+
+        values.remove(self.output.as_str()).unwrap()
+    }
+
+    fn parameters(&mut self) -> Vec<&mut Array> {
+        self.parameters.iter_mut().map(|(_, p)| p).collect()
+    }
+
+    fn named_parameters(&mut self) -> Vec<(String, &mut Array)> {
+        self.parameters
+            .iter_mut()
+            .map(|(name, p)| (name.clone(), p))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::protobuf::Message;
+    use super::*;
+    use crate::layer::dense::Dense;
+    use crate::model::{Model, ModelBuilder};
+    use crate::optimizer::gd::GradientDescent;
+    use crate::{activation, cost, initializer, onnx};
+
+    fn node(
+        op_type: &str,
+        inputs: &[&str],
+        output: &str,
+        attributes: &[(&str, Vec<i64>)],
+    ) -> Message {
+        let mut node = Message::new();
+        for input in inputs {
+            node.string(1, input);
+        }
+        node.string(2, output).string(4, op_type);
+        for (name, ints) in attributes {
+            let mut attribute = Message::new();
+            attribute.string(1, name);
+            for &i in ints {
+                attribute.int(8, i);
+            }
+            node.message(5, attribute.int(20, 7));
+        }
+        node
+    }
+
+    /// Constructs a node with a single integer attribute.
+    fn int_node(op_type: &str, input: &str, output: &str, name: &str, value: i64) -> Message {
+        let mut node = node(op_type, &[input], output, &[]);
+        let mut attribute = Message::new();
+        attribute.string(1, name).int(3, value).int(20, 2);
+        node.message(5, &attribute);
+        node
+    }
+
+    fn tensor(name: &str, dimensions: &[i64], data_type: i64, data: &[u8]) -> Message {
+        let mut tensor = Message::new();
+        for &dimension in dimensions {
+            tensor.int(1, dimension);
+        }
+        tensor.int(2, data_type).string(8, name).bytes(9, data);
+        tensor
+    }
+
+    fn value_info(name: &str) -> Message {
+        let mut value_info = Message::new();
+        value_info.string(1, name);
+        value_info
+    }
+
+    /// Constructs an input, or output, whose shape is declared with the given number of dimensions.
+    fn shaped_value_info(name: &str, rank: usize) -> Message {
+        let mut shape = Message::new();
+        for _ in 0..rank {
+            shape.message(1, Message::new().int(1, 1));
+        }
+        let mut tensor_type = Message::new();
+        tensor_type.int(1, FLOAT).message(2, &shape);
+        let mut value_info = value_info(name);
+        value_info.message(2, Message::new().message(1, &tensor_type));
+        value_info
+    }
+
+    fn model(nodes: &[Message], initializers: &[Message], input: &str, output: &str) -> Vec<u8> {
+        shaped_model(nodes, initializers, value_info(input), output)
+    }
+
+    fn shaped_model(
+        nodes: &[Message],
+        initializers: &[Message],
+        input: Message,
+        output: &str,
+    ) -> Vec<u8> {
+        let mut graph = Message::new();
+        for node in nodes {
+            graph.message(1, node);
+        }
+        for initializer in initializers {
+            graph.message(5, initializer);
+        }
+        graph.message(11, &input).message(12, &value_info(output));
+
+        let mut model = Message::new();
+        model.int(1, 7).message(7, &graph);
+        model.as_bytes().to_vec()
+    }
+
+    fn floats(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut model = ModelBuilder::new(vec![1, 6, 6])
+            .conv(2, (3, 3), (1, 1), Some(activation::relu()))
+            .flatten()
+            .dense(4, Some(activation::sigmoid()))
+            .dense(3, Some(activation::softmax()))
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build();
+        let mut bytes = Vec::new();
+        onnx::write(&model, &mut bytes).unwrap();
+
+        let mut graph = read(&bytes).unwrap();
+        let names: Vec<String> = graph
+            .named_parameters()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        let expected: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(names, expected);
+
+        let input = Array::from((
+            vec![2, 1, 6, 6],
+            (0..72)
+                .map(|i| (i as Float * 0.3).sin())
+                .collect::<Vec<Float>>(),
+        ));
+        assert_eq!(graph.output_shape(&[2, 1, 6, 6]), vec![2, 3]);
+        let output = graph.forward(input.clone());
+        let expected = model.forward(input);
+        for (o, e) in output.values().iter().zip(expected.values()) {
+            assert_relative_eq!(o, e, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        // max pools a 4 by 4 image to 2 by 2, reshapes it to 4 values, and applies an affine transformation
+        let bytes = model(
+            &[
+                node(
+                    "MaxPool",
+                    &["x"],
+                    "pooled",
+                    &[("kernel_shape", vec![2, 2]), ("strides", vec![2, 2])],
+                ),
+                node("Reshape", &["pooled", "shape"], "reshaped", &[]),
+                node("MatMul", &["reshaped", "w"], "product", &[]),
+                node("Add", &["product", "b"], "y", &[]),
+            ],
+            &[
+                tensor(
+                    "shape",
+                    &[2],
+                    INT64,
+                    &[0i64, -1]
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<u8>>(),
+                ),
+                tensor(
+                    "w",
+                    &[4, 2],
+                    FLOAT,
+                    &floats(&[1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0]),
+                ),
+                tensor("b", &[2], FLOAT, &floats(&[0.5, -0.5])),
+            ],
+            "x",
+            "y",
+        );
+        let graph = read(&bytes).unwrap();
+
+        let input = Array::from((
+            vec![1, 1, 4, 4],
+            (0..16).map(|i| i as Float).collect::<Vec<Float>>(),
+        ));
+        // the pooled values are 5, 7, 13, and 15
+        assert_eq!(graph.forward(input), arr![arr![18.5, 21.5]]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let bytes = model(
+            &[
+                node("Relu", &["x"], "a", &[]),
+                node("LSTM", &["a"], "b", &[]),
+                node("Pad", &["b"], "c", &[]),
+                node("LSTM", &["c"], "y", &[]),
+            ],
+            &[],
+            "x",
+            "y",
+        );
+        let error = read(&bytes).err().unwrap().to_string();
+        assert!(error.contains("the operators LSTM, Pad,"), "{}", error);
+
+        let bytes = model(
+            &[node(
+                "Conv",
+                &["x", "w"],
+                "y",
+                &[("pads", vec![1, 1, 1, 1])],
+            )],
+            &[tensor("w", &[1, 1, 1, 1], FLOAT, &floats(&[1.0]))],
+            "x",
+            "y",
+        );
+        let error = read(&bytes).err().unwrap().to_string();
+        assert!(error.contains("pads"), "{}", error);
+
+        let bytes = model(&[node("Relu", &["z"], "y", &[])], &[], "x", "y");
+        assert!(read(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_invalid_tensor() {
+        let invalid = [
+            tensor("w", &[2, 2], FLOAT, &floats(&[1.0, 2.0, 3.0])),
+            tensor("w", &[2, 2], DOUBLE, &[0; 8]),
+            tensor("w", &[1 << 32, 1 << 32], FLOAT, &floats(&[1.0])),
+            tensor("w", &[2], INT64, &[0; 8]),
+        ];
+        for initializer in invalid.iter() {
+            let bytes = model(
+                &[node("MatMul", &["x", "w"], "y", &[])],
+                std::slice::from_ref(initializer),
+                "x",
+                "y",
+            );
+            let error = read(&bytes).err().unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_invalid_axes() {
+        let shape = |values: &[i64]| {
+            tensor(
+                "shape",
+                &[values.len() as i64],
+                INT64,
+                &values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            )
+        };
+
+        // the axes are checked against the declared number of dimensions of the input
+        let invalid = [
+            (int_node("Flatten", "x", "y", "axis", 3), None),
+            (int_node("Softmax", "x", "y", "axis", 0), None),
+            (
+                node("Reshape", &["x", "shape"], "y", &[]),
+                Some(shape(&[1, 1, 0])),
+            ),
+        ];
+        for (node, initializer) in invalid.iter() {
+            let initializers: Vec<Message> = initializer.iter().cloned().collect();
+            let bytes = shaped_model(
+                std::slice::from_ref(node),
+                &initializers,
+                shaped_value_info("x", 2),
+                "y",
+            );
+            assert!(read(&bytes).is_err());
+        }
+
+        let bytes = shaped_model(
+            &[int_node("Softmax", "x", "y", "axis", 1)],
+            &[],
+            shaped_value_info("x", 2),
+            "y",
+        );
+        assert!(read(&bytes).is_ok());
+
+        // without the declared shape, only the axes which are invalid for any number of dimensions are rejected
+        let invalid = [
+            (int_node("Softmax", "x", "y", "axis", -2), None),
+            (
+                node("Reshape", &["x", "shape"], "y", &[]),
+                Some(shape(&[-1, -1])),
+            ),
+            (
+                node("Reshape", &["x", "shape"], "y", &[]),
+                Some(shape(&[-2, 3])),
+            ),
+        ];
+        for (node, initializer) in invalid.iter() {
+            let initializers: Vec<Message> = initializer.iter().cloned().collect();
+            assert!(read(&model(std::slice::from_ref(node), &initializers, "x", "y")).is_err());
+        }
+    }
+
+    #[test]
+    fn test_fine_tune() {
+        let dense = Dense::new(3, 2, &initializer::he(), None);
+        let mut exported = Model::new(
+            vec![Box::new(dense)],
+            Box::new(GradientDescent::new(0.1)),
+            cost::mse(cost::Reduction::Mean),
+        )
+        .with_input_shape(vec![4, 3]);
+        let mut bytes = Vec::new();
+        onnx::write(&exported, &mut bytes).unwrap();
+        let initial = exported.parameters()[0].clone();
+
+        let mut model = Model::new(
+            vec![Box::new(read(&bytes).unwrap())],
+            Box::new(GradientDescent::new(0.1)),
+            cost::mse(cost::Reduction::Mean),
+        );
+        let input = arr![arr![1.0, 0.0, -1.0], arr![0.5, 0.5, 0.5]];
+        let target = arr![arr![1.0, 2.0], arr![-1.0, 0.0]];
+
+        model.forward(input.clone());
+        let first = model.backward(target.clone());
+        model.update();
+        for _ in 0..20 {
+            model.forward(input.clone());
+            model.backward(target.clone());
+            model.update();
+        }
+        model.forward(input);
+        assert!(model.backward(target) < first);
+        assert_ne!(model.parameters()[0].values(), initial.values());
+    }
+}
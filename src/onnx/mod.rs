@@ -0,0 +1,345 @@
+//! Export of models to ONNX, which is used to deploy models with other runtimes, and import of ONNX graphs.
+//!
+//! Each layer appends its nodes to a graph, with its parameters as initializers, which are named in the same way as
+//! `Model::named_parameters`. Dense layers are exported as `Gemm`, convolutional layers as `Conv`, and the ReLU,
+//! sigmoid, hyperbolic tangent, and softmax activations as `Relu`, `Sigmoid`, `Tanh`, and `Softmax`. Dropout layers
+//! are omitted, since the graph is used for inference.
+//!
+//! The model is written as an ONNX protocol buffer, with opset 13, and the batch dimension of the input, and output
+//! named `batch`.
+
+pub mod import;
+mod protobuf;
+
+use crate::activation::Activation;
+use crate::model::Model;
+use crate::numbers::*;
+use crate::serialize::invalid_data;
+
+use protobuf::Message;
+
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path::Path;
+
+/// The version of the ONNX intermediate representation of the written models.
+pub const IR_VERSION: i64 = 7;
+
+/// The version of the default ONNX operator set, which is used by the nodes of the written models.
+pub const OPSET_VERSION: i64 = 13;
+
+/// The ONNX data type of `Float`, which is `FLOAT` for `f32`, and `DOUBLE` for `f64`.
+const DATA_TYPE: i64 = if std::mem::size_of::<Float>() == 4 {
+    1
+} else {
+    11
+};
+
+/// The value of an attribute of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Attribute {
+    /// An integer attribute.
+    Int(i64),
+    /// An attribute of a list of integers.
+    Ints(Vec<i64>),
+}
+
+/// An ONNX graph, to which layers append their nodes, and initializers.
+#[derive(Default)]
+pub struct Graph {
+    prefix: String,
+    nodes: Vec<Message>,
+    initializers: Vec<Message>,
+}
+
+impl Graph {
+    /// Adds an initializer with given dimensions, and values, returning its name, which is prefixed by the name of
+    /// the layer.
+    pub fn initializer(&mut self, name: &str, dimensions: &[usize], values: &[Float]) -> String {
+        let name = format!("{}.{}", self.prefix, name);
+
+        let mut tensor = Message::new();
+        for &dimension in dimensions {
+            tensor.int(1, dimension as i64);
+        }
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        tensor.int(2, DATA_TYPE).string(8, &name).bytes(9, &data);
+
+        self.initializers.push(tensor);
+        name
+    }
+
+    /// Adds a node, returning the name of its output, which is unique within the graph.
+    pub fn node(
+        &mut self,
+        op_type: &str,
+        inputs: &[&str],
+        attributes: &[(&str, Attribute)],
+    ) -> String {
+        let name = format!("{}.{}_{}", self.prefix, op_type, self.nodes.len());
+
+        let mut node = Message::new();
+        for input in inputs {
+            node.string(1, input);
+        }
+        node.string(2, &name).string(3, &name).string(4, op_type);
+        for (attribute_name, value) in attributes {
+            let mut attribute = Message::new();
+            attribute.string(1, attribute_name);
+            match value {
+                Attribute::Int(i) => attribute.int(3, *i).int(20, 2),
+                Attribute::Ints(ints) => {
+                    for &i in ints {
+                        attribute.int(8, i);
+                    }
+                    attribute.int(20, 7)
+                }
+            };
+            node.message(5, &attribute);
+        }
+
+        self.nodes.push(node);
+        name
+    }
+
+    /// Adds the node of an activation, if there is one, returning the name of its output.
+    pub fn activation(&mut self, activation: Option<&Activation>, input: &str) -> Result<String> {
+        match activation {
+            Some(activation) => {
+                let op_type = activation.onnx_op().ok_or_else(|| {
+                    invalid_data("the custom activation cannot be exported to ONNX".to_string())
+                })?;
+                Ok(self.node(op_type, &[input], &[]))
+            }
+            None => Ok(input.to_string()),
+        }
+    }
+}
+
+/// Constructs the name, and type of an input, or output, whose first dimension is the batch dimension.
+fn value_info(name: &str, shape: &[usize]) -> Message {
+    let mut shape_message = Message::new();
+    for (i, &dimension) in shape.iter().enumerate() {
+        let mut dimension_message = Message::new();
+        if i == 0 {
+            dimension_message.string(2, "batch");
+        } else {
+            dimension_message.int(1, dimension as i64);
+        }
+        shape_message.message(1, &dimension_message);
+    }
+
+    let mut tensor_type = Message::new();
+    tensor_type.int(1, DATA_TYPE).message(2, &shape_message);
+    let mut type_message = Message::new();
+    type_message.message(1, &tensor_type);
+
+    let mut value_info = Message::new();
+    value_info.string(1, name).message(2, &type_message);
+    value_info
+}
+
+/// Writes a model as an ONNX model, which requires the input dimensions of the model to be set, and every layer to
+/// be exportable.
+pub fn write(model: &Model, writer: &mut impl Write) -> Result<()> {
+    let input_shape = model.input_shape().ok_or_else(|| {
+        invalid_data("the input dimensions of the model must be set to export it".to_string())
+    })?;
+
+    let mut graph = Graph::default();
+    let mut output = "input".to_string();
+    let mut shape = input_shape.to_vec();
+    for (i, layer) in model.layers().iter().enumerate() {
+        graph.prefix = format!("layers.{}", i);
+        output = layer.to_onnx(&mut graph, &output, &shape)?;
+        shape = layer.output_shape(&shape);
+    }
+
+    // an output must be produced by a node, so a model without nodes passes its input through
+    if graph.nodes.is_empty() {
+        graph.prefix = "output".to_string();
+        output = graph.node("Identity", &[&output], &[]);
+    }
+
+    let mut graph_message = Message::new();
+    for node in &graph.nodes {
+        graph_message.message(1, node);
+    }
+    graph_message.string(2, "corgi");
+    for initializer in &graph.initializers {
+        graph_message.message(5, initializer);
+    }
+    graph_message
+        .message(11, &value_info("input", input_shape))
+        .message(12, &value_info(&output, &shape));
+
+    let mut opset = Message::new();
+    opset.string(1, "").int(2, OPSET_VERSION);
+    let mut model_message = Message::new();
+    model_message
+        .int(1, IR_VERSION)
+        .string(2, "corgi")
+        .message(7, &graph_message)
+        .message(8, &opset);
+
+    writer.write_all(model_message.as_bytes())
+}
+
+/// Saves a model to an ONNX file.
+pub fn save(model: &Model, path: impl AsRef<Path>) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(model, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protobuf::{decode, Value};
+    use super::*;
+    use crate::layer::dense::Dense;
+    use crate::layer::dropout::Dropout;
+    use crate::layer::layer_norm::LayerNorm;
+    use crate::model::ModelBuilder;
+    use crate::optimizer::gd::GradientDescent;
+    use crate::{activation, cost, initializer};
+
+    /// Retrieves the values of a field of a message.
+    fn field(bytes: &[u8], number: u32) -> Vec<Value<'_>> {
+        decode(bytes)
+            .unwrap()
+            .into_iter()
+            .filter(|(n, _)| *n == number)
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    fn messages(bytes: &[u8], number: u32) -> Vec<&[u8]> {
+        field(bytes, number)
+            .into_iter()
+            .map(|v| match v {
+                Value::Bytes(b) => b,
+                v => panic!("expected bytes, but got {:?}", v),
+            })
+            .collect()
+    }
+
+    fn string(bytes: &[u8], number: u32) -> String {
+        String::from_utf8(messages(bytes, number)[0].to_vec()).unwrap()
+    }
+
+    fn ints(bytes: &[u8], number: u32) -> Vec<i64> {
+        field(bytes, number)
+            .into_iter()
+            .map(|v| match v {
+                Value::Varint(i) => i as i64,
+                v => panic!("expected a varint, but got {:?}", v),
+            })
+            .collect()
+    }
+
+    fn builder() -> ModelBuilder {
+        ModelBuilder::new(vec![1, 6, 6])
+            .conv(2, (3, 3), (1, 1), Some(activation::relu()))
+            .flatten()
+            .dense(4, Some(activation::sigmoid()))
+            .layer(Box::new(Dropout::new(0.5, None)))
+            .dense(3, Some(activation::softmax()))
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+    }
+
+    #[test]
+    fn test_write() {
+        let mut model = builder().build();
+        let mut bytes = Vec::new();
+        write(&model, &mut bytes).unwrap();
+
+        assert_eq!(ints(&bytes, 1), vec![IR_VERSION]);
+        let opset = messages(&bytes, 8)[0];
+        assert_eq!(
+            (string(opset, 1), ints(opset, 2)),
+            ("".to_string(), vec![13])
+        );
+
+        let graph = messages(&bytes, 7)[0];
+        let nodes = messages(graph, 1);
+        let op_types: Vec<String> = nodes.iter().map(|n| string(n, 4)).collect();
+        assert_eq!(
+            op_types,
+            vec!["Conv", "Relu", "Flatten", "Gemm", "Sigmoid", "Gemm", "Softmax"]
+        );
+
+        // each node consumes the output of the previous node
+        assert_eq!(string(nodes[0], 1), "input");
+        for pair in nodes.windows(2) {
+            assert_eq!(messages(pair[1], 1)[0], messages(pair[0], 2)[0]);
+        }
+        assert_eq!(
+            messages(nodes[5], 1)[1..],
+            [&b"layers.4.weights"[..], &b"layers.4.biases"[..]]
+        );
+
+        let attributes: Vec<(String, Vec<i64>)> = messages(nodes[0], 5)
+            .into_iter()
+            .map(|a| (string(a, 1), ints(a, 8)))
+            .collect();
+        assert!(attributes.contains(&("kernel_shape".to_string(), vec![3, 3])));
+        assert!(attributes.contains(&("strides".to_string(), vec![1, 1])));
+        let trans_b = messages(nodes[3], 5)[0];
+        assert_eq!(
+            (string(trans_b, 1), ints(trans_b, 3)),
+            ("transB".to_string(), vec![1])
+        );
+
+        let initializers = messages(graph, 5);
+        let names: Vec<String> = initializers.iter().map(|t| string(t, 8)).collect();
+        let expected: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(names, expected);
+        assert_eq!(ints(initializers[0], 1), vec![2, 1, 3, 3]);
+        assert_eq!(ints(initializers[1], 1), vec![2]);
+
+        let weights = model.named_parameters().remove(2).1.clone();
+        assert_eq!(ints(initializers[2], 1), vec![4, 32]);
+        assert_eq!(ints(initializers[2], 2), vec![DATA_TYPE]);
+        let data: Vec<u8> = weights
+            .values()
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(messages(initializers[2], 9)[0], data.as_slice());
+
+        let output = messages(graph, 12)[0];
+        assert_eq!(messages(output, 1)[0], messages(nodes[6], 2)[0]);
+        let tensor_type = messages(messages(output, 2)[0], 1)[0];
+        let dimensions = messages(messages(tensor_type, 2)[0], 1);
+        assert_eq!(string(dimensions[0], 2), "batch");
+        assert_eq!(ints(dimensions[1], 1), vec![3]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let model = builder()
+            .layer(Box::new(LayerNorm::new(vec![3], 1e-5)))
+            .build();
+        assert!(write(&model, &mut Vec::new()).is_err());
+
+        // a custom activation is not exported, even if it matches another activation for some inputs, such as ReLU6
+        let model = ModelBuilder::new(vec![3])
+            .dense(2, Some(Activation::new(|x| x.clamp(0.0, 6.0))))
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build();
+        assert!(write(&model, &mut Vec::new()).is_err());
+
+        let model = Model::new(
+            vec![Box::new(Dense::new(3, 2, &initializer::he(), None))],
+            Box::new(GradientDescent::new(0.1)),
+            cost::mse(cost::Reduction::Mean),
+        );
+        assert!(write(&model, &mut Vec::new()).is_err());
+    }
+}
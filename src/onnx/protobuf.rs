@@ -0,0 +1,304 @@
+//! A minimal encoding, and decoding of protocol buffer messages, which is sufficient for ONNX models.
+//!
+//! Messages are built field by field, in the order the fields are added, and decoded into their fields without a
+//! schema.
+
+use crate::serialize::invalid_data;
+
+use std::convert::TryInto;
+use std::io::Result;
+
+const VARINT: u64 = 0;
+const FIXED_64: u64 = 1;
+const LENGTH_DELIMITED: u64 = 2;
+const FIXED_32: u64 = 5;
+
+/// An encoded protocol buffer message.
+#[derive(Clone, Debug, Default)]
+pub struct Message {
+    bytes: Vec<u8>,
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+impl Message {
+    /// Constructs a new message without fields.
+    pub fn new() -> Message {
+        Message::default()
+    }
+
+    fn key(&mut self, field: u32, wire_type: u64) {
+        write_varint(&mut self.bytes, (field as u64) << 3 | wire_type);
+    }
+
+    /// Adds an integer field, which is encoded as a varint. Negative values are encoded in ten bytes.
+    pub fn int(&mut self, field: u32, value: i64) -> &mut Message {
+        self.key(field, VARINT);
+        write_varint(&mut self.bytes, value as u64);
+        self
+    }
+
+    /// Adds a field of bytes.
+    pub fn bytes(&mut self, field: u32, value: &[u8]) -> &mut Message {
+        self.key(field, LENGTH_DELIMITED);
+        write_varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    /// Adds a string field.
+    pub fn string(&mut self, field: u32, value: &str) -> &mut Message {
+        self.bytes(field, value.as_bytes())
+    }
+
+    /// Adds a field of an embedded message.
+    pub fn message(&mut self, field: u32, value: &Message) -> &mut Message {
+        self.bytes(field, &value.bytes)
+    }
+
+    /// Retrieves the encoded bytes of the message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// The value of a decoded field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// A varint, which holds integers, booleans, and enums.
+    Varint(u64),
+    /// A fixed 64-bit value, such as a double.
+    Fixed64(u64),
+    /// A length-delimited value, such as a string, bytes, an embedded message, or packed repeated values.
+    Bytes(&'a [u8]),
+    /// A fixed 32-bit value, such as a float.
+    Fixed32(u32),
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| invalid_data("the message ends within a varint".to_string()))?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid_data(
+        "a varint is longer than ten bytes".to_string(),
+    ))
+}
+
+fn read_bytes<'a>(bytes: &'a [u8], position: &mut usize, length: usize) -> Result<&'a [u8]> {
+    let value = bytes
+        .get(*position..position.saturating_add(length))
+        .ok_or_else(|| invalid_data("the message ends within a field".to_string()))?;
+    *position += length;
+    Ok(value)
+}
+
+/// Decodes the fields of a message, in the order they are encoded.
+pub fn decode(bytes: &[u8]) -> Result<Vec<(u32, Value<'_>)>> {
+    let mut fields = Vec::new();
+    let mut position = 0;
+    while position < bytes.len() {
+        let key = read_varint(bytes, &mut position)?;
+        let value = match key & 7 {
+            VARINT => Value::Varint(read_varint(bytes, &mut position)?),
+            FIXED_64 => {
+                let value = read_bytes(bytes, &mut position, 8)?;
+                Value::Fixed64(u64::from_le_bytes(value.try_into().unwrap()))
+            }
+            LENGTH_DELIMITED => {
+                let length = read_varint(bytes, &mut position)? as usize;
+                Value::Bytes(read_bytes(bytes, &mut position, length)?)
+            }
+            FIXED_32 => {
+                let value = read_bytes(bytes, &mut position, 4)?;
+                Value::Fixed32(u32::from_le_bytes(value.try_into().unwrap()))
+            }
+            wire_type => {
+                return Err(invalid_data(format!(
+                    "the wire type {} is not supported",
+                    wire_type
+                )))
+            }
+        };
+        fields.push(((key >> 3) as u32, value));
+    }
+
+    Ok(fields)
+}
+
+/// The decoded fields of a message, which are retrieved by their field number. Missing fields have default values.
+pub struct Fields<'a> {
+    fields: Vec<(u32, Value<'a>)>,
+}
+
+fn wire_type_error(number: u32, value: &Value) -> std::io::Error {
+    invalid_data(format!(
+        "the field {} has an unexpected value {:?}",
+        number, value
+    ))
+}
+
+impl<'a> Fields<'a> {
+    /// Decodes the fields of a message.
+    pub fn decode(bytes: &'a [u8]) -> Result<Fields<'a>> {
+        Ok(Fields {
+            fields: decode(bytes)?,
+        })
+    }
+
+    fn values(&self, number: u32) -> impl Iterator<Item = &Value<'a>> {
+        self.fields
+            .iter()
+            .filter(move |(n, _)| *n == number)
+            .map(|(_, v)| v)
+    }
+
+    /// Retrieves every length-delimited value of a field.
+    pub fn bytes(&self, number: u32) -> Result<Vec<&'a [u8]>> {
+        self.values(number)
+            .map(|v| match v {
+                Value::Bytes(bytes) => Ok(*bytes),
+                v => Err(wire_type_error(number, v)),
+            })
+            .collect()
+    }
+
+    /// Retrieves every embedded message of a field.
+    pub fn messages(&self, number: u32) -> Result<Vec<Fields<'a>>> {
+        self.bytes(number)?
+            .into_iter()
+            .map(Fields::decode)
+            .collect()
+    }
+
+    /// Retrieves the last value of a string field.
+    pub fn string(&self, number: u32) -> Result<String> {
+        match self.bytes(number)?.last() {
+            Some(bytes) => String::from_utf8(bytes.to_vec())
+                .map_err(|_| invalid_data(format!("the field {} is not a valid string", number))),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Retrieves every value of a repeated integer field, which may be packed.
+    pub fn ints(&self, number: u32) -> Result<Vec<i64>> {
+        let mut ints = Vec::new();
+        for value in self.values(number) {
+            match value {
+                Value::Varint(i) => ints.push(*i as i64),
+                Value::Bytes(bytes) => {
+                    let mut position = 0;
+                    while position < bytes.len() {
+                        ints.push(read_varint(bytes, &mut position)? as i64);
+                    }
+                }
+                v => return Err(wire_type_error(number, v)),
+            }
+        }
+
+        Ok(ints)
+    }
+
+    /// Retrieves the last value of an integer field.
+    pub fn int(&self, number: u32) -> Result<Option<i64>> {
+        Ok(self.ints(number)?.last().copied())
+    }
+
+    /// Retrieves the last value of a float field.
+    pub fn float(&self, number: u32) -> Result<Option<f32>> {
+        match self.values(number).last() {
+            Some(Value::Fixed32(bits)) => Ok(Some(f32::from_bits(*bits))),
+            Some(v) => Err(wire_type_error(number, v)),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves every value of a repeated 32-bit, or 64-bit field, which may be packed, as little-endian bytes.
+    pub fn fixed(&self, number: u32, width: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for value in self.values(number) {
+            match value {
+                Value::Fixed32(bits) if width == 4 => bytes.extend_from_slice(&bits.to_le_bytes()),
+                Value::Fixed64(bits) if width == 8 => bytes.extend_from_slice(&bits.to_le_bytes()),
+                Value::Bytes(packed) if packed.len() % width == 0 => {
+                    bytes.extend_from_slice(packed)
+                }
+                v => return Err(wire_type_error(number, v)),
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut inner = Message::new();
+        inner.int(1, 150);
+        let mut message = Message::new();
+        message.string(2, "testing").message(3, &inner).int(4, -1);
+
+        let mut expected = vec![0x12, 0x07];
+        expected.extend_from_slice(b"testing");
+        expected.extend_from_slice(&[0x1a, 0x03, 0x08, 0x96, 0x01, 0x20]);
+        expected.extend_from_slice(&[0xff; 9]);
+        expected.push(0x01);
+        assert_eq!(message.as_bytes(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = [
+            0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i', 0x1d, 0, 0, 0x80, 0x3f,
+        ];
+        assert_eq!(
+            decode(&bytes).unwrap(),
+            vec![
+                (1, Value::Varint(150)),
+                (2, Value::Bytes(b"hi")),
+                (3, Value::Fixed32(1.0f32.to_bits())),
+            ]
+        );
+
+        assert!(decode(&[0x08, 0x96]).is_err());
+        assert!(decode(&[0x12, 0x05, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut message = Message::new();
+        message
+            .int(1, 3)
+            .bytes(1, &[4, 0x96, 0x01])
+            .string(2, "first")
+            .string(2, "last")
+            .bytes(3, &2.0f32.to_le_bytes());
+        let fields = Fields::decode(message.as_bytes()).unwrap();
+
+        assert_eq!(fields.ints(1).unwrap(), vec![3, 4, 150]);
+        assert_eq!(fields.int(4).unwrap(), None);
+        assert_eq!(fields.string(2).unwrap(), "last");
+        assert_eq!(fields.string(5).unwrap(), "");
+        assert_eq!(fields.fixed(3, 4).unwrap(), 2.0f32.to_le_bytes().to_vec());
+        assert!(fields.fixed(3, 8).is_err());
+        assert!(fields.float(2).is_err());
+    }
+}
@@ -20,7 +20,7 @@ impl GradientDescent {
 }
 
 impl Optimizer for GradientDescent {
-    fn update(&self, parameters: Vec<&mut Array>) {
+    fn update(&mut self, parameters: Vec<&mut Array>) {
         let mut frozen = Vec::new();
         let mut parameter_values = Vec::new();
         let mut parameter_gradients = Vec::new();
@@ -1,11 +1,33 @@
 //! Implementations of gradient descent optimizers, to optimize the parameters of a model.
 
 pub mod gd;
+pub mod momentum;
 
 use crate::array::Array;
+use crate::serialize::invalid_data;
+
+use std::io::Result;
 
 /// An optimizer, which updates the parameters of a model.
 pub trait Optimizer {
     /// Updates the parameters. It is critical that the order of the parameters remains the same between calls.
-    fn update(&self, parameters: Vec<&mut Array>);
+    fn update(&mut self, parameters: Vec<&mut Array>);
+
+    /// Retrieves the state of the optimizer, such as moment estimates, which is empty for stateless optimizers.
+    fn state(&self) -> Vec<Array> {
+        Vec::new()
+    }
+
+    /// Restores the state of the optimizer, which was retrieved from an optimizer of the same configuration, given
+    /// the dimensions of the parameters it updates. The optimizer is unchanged if an error is returned.
+    fn set_state(&mut self, state: Vec<Array>, _parameter_dimensions: &[Vec<usize>]) -> Result<()> {
+        if state.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_data(format!(
+                "the optimizer has no state, but the file contains {} arrays",
+                state.len()
+            )))
+        }
+    }
 }
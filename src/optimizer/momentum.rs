@@ -0,0 +1,136 @@
+//! A gradient descent optimizer with momentum, which accumulates a velocity from past gradients.
+
+use crate::array::*;
+use crate::numbers::*;
+use crate::optimizer::Optimizer;
+use crate::serialize::invalid_data;
+
+use std::io::Result;
+
+/// A gradient descent optimizer with momentum, which updates v = momentum * v + g, and then p = p - learning_rate * v,
+/// storing the velocity of each parameter.
+pub struct Momentum {
+    learning_rate: Float,
+    momentum: Float,
+    velocities: Vec<Array>,
+}
+
+impl Momentum {
+    /// Creates a new momentum optimizer, which updates based on the learning rate, and the momentum.
+    pub fn new(learning_rate: Float, momentum: Float) -> Momentum {
+        Momentum {
+            learning_rate,
+            momentum,
+            velocities: Vec::new(),
+        }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn update(&mut self, parameters: Vec<&mut Array>) {
+        if self.velocities.is_empty() {
+            self.velocities = parameters
+                .iter()
+                .map(|p| Array::from(p.dimensions().to_vec()))
+                .collect();
+        }
+
+        assert!(
+            self.velocities.len() == parameters.len(),
+            "error: the optimizer was used with {} parameters, but was given {}",
+            self.velocities.len(),
+            parameters.len()
+        );
+
+        let (learning_rate, momentum) = (self.learning_rate, self.momentum);
+        // parameters without gradients are frozen, and keep their velocity
+        for (parameter, velocity) in parameters.into_iter().zip(self.velocities.iter_mut()) {
+            let gradient = match parameter.replace_gradient() {
+                Some(gradient) => gradient,
+                None => continue,
+            };
+
+            let velocity_values: Vec<Float> = velocity
+                .values()
+                .iter()
+                .zip(gradient.values())
+                .map(|(v, g)| momentum * v + g)
+                .collect();
+            let parameter_values: Vec<Float> = parameter
+                .values()
+                .iter()
+                .zip(&velocity_values)
+                .map(|(x, v)| x - learning_rate * v)
+                .collect();
+
+            *velocity = Array::from((velocity.dimensions().to_vec(), velocity_values));
+            *parameter = Array::from((parameter.dimensions().to_vec(), parameter_values)).tracked();
+        }
+    }
+
+    fn state(&self) -> Vec<Array> {
+        self.velocities.clone()
+    }
+
+    fn set_state(&mut self, state: Vec<Array>, parameter_dimensions: &[Vec<usize>]) -> Result<()> {
+        // an optimizer which has not updated yet has no velocities
+        if !state.is_empty() && state.len() != parameter_dimensions.len() {
+            return Err(invalid_data(format!(
+                "the file contains {} velocities, but the model has {} parameters",
+                state.len(),
+                parameter_dimensions.len()
+            )));
+        }
+
+        for (i, (velocity, dimensions)) in state.iter().zip(parameter_dimensions).enumerate() {
+            if velocity.dimensions() != dimensions.as_slice() {
+                return Err(invalid_data(format!(
+                    "the dimensions {:?} of velocity {} do not match the parameter dimensions {:?}",
+                    velocity.dimensions(),
+                    i,
+                    dimensions
+                )));
+            }
+        }
+
+        self.velocities = state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_momentum() {
+        let mut optimizer = Momentum::new(0.5, 0.9);
+        let mut parameter = arr![1.0, 2.0].tracked();
+
+        for _ in 0..2 {
+            (&parameter * &arr![1.0, 2.0]).backward(None);
+            optimizer.update(vec![&mut parameter]);
+        }
+
+        // the velocities are the gradient, and then the gradient plus the decayed velocity
+        assert_relative_eq!(parameter, arr![1.0 - 0.5 * 2.9, 2.0 - 0.5 * 5.8]);
+        assert_relative_eq!(optimizer.state()[0], arr![1.9, 3.8]);
+    }
+
+    #[test]
+    fn test_set_state() {
+        let mut optimizer = Momentum::new(0.5, 0.9);
+        let dimensions = vec![vec![2]];
+
+        assert!(optimizer.set_state(vec![arr![1.0]], &dimensions).is_err());
+        assert!(optimizer
+            .set_state(vec![arr![1.0, 2.0], arr![3.0]], &dimensions)
+            .is_err());
+        assert!(optimizer.state().is_empty());
+
+        optimizer
+            .set_state(vec![arr![1.0, 2.0]], &dimensions)
+            .unwrap();
+        assert_eq!(optimizer.state(), vec![arr![1.0, 2.0]]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Random number generators, whose state may be captured, and restored to reproduce a run exactly.
+//!
+//! Initializers draw from a generator local to each thread, which is seeded from entropy unless seeded explicitly,
+//! and layers such as dropout own their own generators.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use std::cell::RefCell;
+
+/// The random number generator used throughout the crate.
+pub type Generator = ChaCha12Rng;
+
+thread_local! {
+    /// The generator of the thread, which is used by initializers.
+    static GENERATOR: RefCell<Generator> = RefCell::new(Generator::from_entropy());
+}
+
+/// Constructs a generator from a seed if specified, or from the generator of the thread otherwise, so that seeding
+/// the thread makes every generator reproducible.
+pub fn generator(seed: Option<u64>) -> Generator {
+    match seed {
+        Some(seed) => Generator::seed_from_u64(seed),
+        None => with_generator(|g| Generator::from_rng(g).unwrap()),
+    }
+}
+
+/// The state of a generator, which is its key, stream, and position in the stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorState {
+    /// The key of the generator.
+    pub seed: [u8; 32],
+    /// The stream of the generator.
+    pub stream: u64,
+    /// The number of 32-bit words which have been generated.
+    pub word_position: u128,
+}
+
+impl GeneratorState {
+    /// Captures the state of a generator.
+    pub fn capture(generator: &Generator) -> GeneratorState {
+        GeneratorState {
+            seed: generator.get_seed(),
+            stream: generator.get_stream(),
+            word_position: generator.get_word_pos(),
+        }
+    }
+
+    /// Constructs a generator, which continues from the state.
+    pub fn restore(&self) -> Generator {
+        let mut generator = Generator::from_seed(self.seed);
+        generator.set_stream(self.stream);
+        generator.set_word_pos(self.word_position);
+        generator
+    }
+}
+
+/// Seeds the generator of the thread, for reproducible initialization.
+pub fn seed(seed: u64) {
+    GENERATOR.with(|g| *g.borrow_mut() = Generator::seed_from_u64(seed));
+}
+
+/// Calls a function with the generator of the thread.
+pub fn with_generator<T>(f: impl FnOnce(&mut Generator) -> T) -> T {
+    GENERATOR.with(|g| f(&mut g.borrow_mut()))
+}
+
+/// Captures the state of the generator of the thread.
+pub fn thread_state() -> GeneratorState {
+    GENERATOR.with(|g| GeneratorState::capture(&g.borrow()))
+}
+
+/// Restores the state of the generator of the thread.
+pub fn set_thread_state(state: &GeneratorState) {
+    GENERATOR.with(|g| *g.borrow_mut() = state.restore());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+
+    #[test]
+    fn test_restore() {
+        let mut g1 = generator(Some(5));
+        g1.gen::<u64>();
+        g1.gen::<u32>();
+
+        let mut g2 = GeneratorState::capture(&g1).restore();
+        let expected: Vec<u64> = (0..4).map(|_| g1.gen()).collect();
+        assert_eq!((0..4).map(|_| g2.gen()).collect::<Vec<u64>>(), expected);
+    }
+
+    #[test]
+    fn test_thread_state() {
+        seed(1);
+        let state = thread_state();
+        let first: f64 = with_generator(|g| g.gen());
+
+        set_thread_state(&state);
+        assert_eq!(with_generator(|g| g.gen::<f64>()), first);
+    }
+}
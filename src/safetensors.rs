@@ -0,0 +1,631 @@
+//! Reading, and writing of named tensors in the safetensors format, which is used to share weights with other
+//! frameworks.
+//!
+//! A safetensors file consists of the length of the header as a little-endian `u64`, the header, which is a JSON
+//! object mapping each name to its `dtype`, `shape`, and the `data_offsets` of its values within the data, and then
+//! the data. The header may also have a `__metadata__` object of strings, which is ignored.
+//!
+//! The `F16`, `BF16`, `F32`, and `F64` dtypes are read, and are converted to `Float`. Tensors with no values are
+//! skipped when reading, as arrays cannot have empty dimensions. Tensors are written with the dtype of `Float`.
+
+use crate::array::*;
+use crate::model::Model;
+use crate::numbers::*;
+use crate::serialize::invalid_data;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, Result, Write};
+use std::path::Path;
+
+/// The alignment of the header length, and header, so that the data is aligned.
+const HEADER_ALIGNMENT: usize = 8;
+
+/// The name of the metadata entry of the header.
+const METADATA_KEY: &str = "__metadata__";
+
+/// A JSON value, where numbers keep their text, and the values of the literals `true`, `false`, and `null` are not
+/// needed.
+enum Json {
+    Literal,
+    Number(String),
+    Text(String),
+    List(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A parser of the JSON header.
+struct JsonParser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, expected: &str) -> Error {
+        invalid_data(format!(
+            "the safetensors header is malformed at position {}, expected {}",
+            self.position, expected
+        ))
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while matches!(self.text.get(self.position), Some(c) if c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+
+        self.text.get(self.position).copied()
+    }
+
+    fn consume(&mut self, character: u8) -> bool {
+        let is_present = self.peek() == Some(character);
+        if is_present {
+            self.position += 1;
+        }
+
+        is_present
+    }
+
+    fn expect(&mut self, character: u8) -> Result<()> {
+        if self.consume(character) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}'", character as char)))
+        }
+    }
+
+    fn parse_keyword(&mut self, keyword: &str) -> Result<Json> {
+        if self.text[self.position..].starts_with(keyword.as_bytes()) {
+            self.position += keyword.len();
+            Ok(Json::Literal)
+        } else {
+            Err(self.error(keyword))
+        }
+    }
+
+    fn parse_hex(&mut self) -> Result<u32> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("four hexadecimal digits"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn parse_text(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let character = *self
+                .text
+                .get(self.position)
+                .ok_or_else(|| self.error("the end of a string"))?;
+            self.position += 1;
+
+            match character {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self
+                        .text
+                        .get(self.position)
+                        .ok_or_else(|| self.error("an escape"))?;
+                    self.position += 1;
+
+                    let unescaped = match escape {
+                        b'"' | b'\\' | b'/' => escape as char,
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            // characters outside of the basic multilingual plane are escaped as surrogate pairs
+                            let mut code = self.parse_hex()?;
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.text[self.position..].starts_with(b"\\u")
+                            {
+                                self.position += 2;
+                                let low = self.parse_hex()?;
+                                code = 0x10000
+                                    + ((code - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => return Err(self.error("a valid escape")),
+                    };
+
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(unescaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(character),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("a UTF-8 string"))
+    }
+
+    fn parse_value(&mut self) -> Result<Json> {
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut entries = Vec::new();
+                if !self.consume(b'}') {
+                    loop {
+                        let key = self.parse_text()?;
+                        self.expect(b':')?;
+                        entries.push((key, self.parse_value()?));
+                        if !self.consume(b',') {
+                            self.expect(b'}')?;
+                            break;
+                        }
+                    }
+                }
+
+                Ok(Json::Object(entries))
+            }
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if !self.consume(b']') {
+                    loop {
+                        values.push(self.parse_value()?);
+                        if !self.consume(b',') {
+                            self.expect(b']')?;
+                            break;
+                        }
+                    }
+                }
+
+                Ok(Json::List(values))
+            }
+            Some(b'"') => Ok(Json::Text(self.parse_text()?)),
+            Some(b't') => self.parse_keyword("true"),
+            Some(b'f') => self.parse_keyword("false"),
+            Some(b'n') => self.parse_keyword("null"),
+            Some(c) if c == b'-' || c.is_ascii_digit() => {
+                let start = self.position;
+                while matches!(
+                    self.text.get(self.position),
+                    Some(c) if b"+-.eE".contains(c) || c.is_ascii_digit()
+                ) {
+                    self.position += 1;
+                }
+
+                Ok(Json::Number(
+                    String::from_utf8_lossy(&self.text[start..self.position]).into_owned(),
+                ))
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+}
+
+/// Escapes a string as a JSON string.
+fn escape(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped + "\""
+}
+
+/// Converts a half-precision floating point value.
+fn from_f16(bits: u16) -> f32 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => fraction * (2.0f32).powi(-24),
+        0x1f if fraction == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + fraction / 1024.0) * (2.0f32).powi(exponent - 15),
+    }
+}
+
+/// Retrieves the width of the values of a dtype in bytes.
+fn dtype_width(dtype: &str) -> Option<usize> {
+    match dtype {
+        "F16" | "BF16" => Some(2),
+        "F32" => Some(4),
+        "F64" => Some(8),
+        _ => None,
+    }
+}
+
+/// Converts the little-endian bytes of values of a dtype.
+fn convert(dtype: &str, bytes: &[u8]) -> Vec<Float> {
+    let width = dtype_width(dtype).unwrap();
+    bytes
+        .chunks(width)
+        .map(|b| match dtype {
+            "F16" => from_f16(u16::from_le_bytes([b[0], b[1]])) as Float,
+            "BF16" => f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16) as Float,
+            "F32" => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Float,
+            _ => {
+                let mut value = [0; 8];
+                value.copy_from_slice(b);
+                f64::from_le_bytes(value) as Float
+            }
+        })
+        .collect()
+}
+
+/// Parses the description of a tensor, returning its dtype, shape, and data offsets.
+fn parse_tensor(name: &str, description: Json) -> Result<(String, Vec<usize>, (usize, usize))> {
+    let error = || invalid_data(format!("the safetensors tensor {} is malformed", name));
+    let integer = |value: &Json| match value {
+        Json::Number(n) => n.parse::<usize>().map_err(|_| error()),
+        _ => Err(error()),
+    };
+
+    let (mut dtype, mut shape, mut offsets) = (None, None, None);
+    if let Json::Object(entries) = description {
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("dtype", Json::Text(d)) => dtype = Some(d),
+                ("shape", Json::List(s)) => {
+                    shape = Some(s.iter().map(integer).collect::<Result<Vec<usize>>>()?)
+                }
+                ("data_offsets", Json::List(o)) if o.len() == 2 => {
+                    offsets = Some((integer(&o[0])?, integer(&o[1])?))
+                }
+                _ => return Err(error()),
+            }
+        }
+    }
+
+    match (dtype, shape, offsets) {
+        (Some(d), Some(s), Some(o)) => Ok((d, s, o)),
+        _ => Err(error()),
+    }
+}
+
+/// Reads named tensors in the safetensors format, in the order of the header, skipping tensors with no values.
+pub fn read_tensors(bytes: &[u8]) -> Result<Vec<(String, Array)>> {
+    let header_length = bytes
+        .get(0..8)
+        .map(|b| {
+            let mut length = [0; 8];
+            length.copy_from_slice(b);
+            u64::from_le_bytes(length) as usize
+        })
+        .ok_or_else(|| invalid_data("the safetensors file is truncated".to_string()))?;
+    let data_start = header_length
+        .checked_add(8)
+        .filter(|&start| start <= bytes.len())
+        .ok_or_else(|| invalid_data("the safetensors header is truncated".to_string()))?;
+    let (header, data) = (&bytes[8..data_start], &bytes[data_start..]);
+
+    let mut parser = JsonParser {
+        text: header,
+        position: 0,
+    };
+    let entries = match parser.parse_value()? {
+        Json::Object(entries) => entries,
+        _ => return Err(parser.error("an object")),
+    };
+
+    let mut tensors = Vec::new();
+    for (name, description) in entries {
+        if name == METADATA_KEY {
+            continue;
+        }
+
+        let (dtype, shape, (start, end)) = parse_tensor(&name, description)?;
+        let width = dtype_width(&dtype).ok_or_else(|| {
+            invalid_data(format!(
+                "the safetensors dtype {} of tensor {} is not supported, expected F16, BF16, F32, or F64",
+                dtype, name
+            ))
+        })?;
+
+        let length = shape
+            .iter()
+            .try_fold(width, |length, &d| length.checked_mul(d));
+        if length.is_none() || end < start || Some(end - start) != length || end > data.len() {
+            return Err(invalid_data(format!(
+                "the data offsets {:?} of tensor {} do not match its shape {:?}",
+                (start, end),
+                name,
+                shape
+            )));
+        }
+
+        if start == end {
+            continue;
+        }
+
+        tensors.push((
+            name,
+            Array::from((shape, convert(&dtype, &data[start..end]))),
+        ));
+    }
+
+    Ok(tensors)
+}
+
+/// Writes named tensors in the safetensors format.
+pub fn write_tensors(writer: &mut impl Write, tensors: &[(&str, &Array)]) -> Result<()> {
+    let dtype = if std::mem::size_of::<Float>() == 4 {
+        "F32"
+    } else {
+        "F64"
+    };
+
+    let mut offset = 0;
+    let mut descriptions = Vec::new();
+    for (name, array) in tensors {
+        let length = std::mem::size_of_val(array.values());
+        descriptions.push(format!(
+            "{}:{{\"dtype\":\"{}\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+            escape(name),
+            dtype,
+            array
+                .dimensions()
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            offset,
+            offset + length
+        ));
+        offset += length;
+    }
+
+    // the header is padded with spaces, so that the data is aligned
+    let mut header = format!("{{{}}}", descriptions.join(","));
+    header += &" ".repeat((HEADER_ALIGNMENT - header.len() % HEADER_ALIGNMENT) % HEADER_ALIGNMENT);
+
+    writer.write_all(&(header.len() as u64).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for (_, array) in tensors {
+        for value in array.values() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Saves the named parameters, and buffers of a model to a safetensors file.
+pub fn save(model: &mut Model, path: impl AsRef<Path>) -> Result<()> {
+    let mut tensors: Vec<(String, Array)> = model
+        .named_parameters()
+        .into_iter()
+        .map(|(n, p)| (n, p.clone()))
+        .collect();
+    tensors.extend(
+        model
+            .named_buffers()
+            .into_iter()
+            .map(|(n, b)| (n, b.clone())),
+    );
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_tensors(
+        &mut writer,
+        &tensors
+            .iter()
+            .map(|(n, a)| (n.as_str(), a))
+            .collect::<Vec<(&str, &Array)>>(),
+    )?;
+    writer.flush()
+}
+
+/// Loads the parameters, and buffers of a model by name from a safetensors file, such as pretrained weights.
+///
+/// Returns an error of kind `InvalidData` if a tensor of the model is missing, the file has tensors which the model
+/// does not, or the dimensions of a tensor do not match, in which case the model is unchanged.
+pub fn load(model: &mut Model, path: impl AsRef<Path>) -> Result<()> {
+    let mut tensors: HashMap<String, Array> = read_tensors(&fs::read(path)?)?.into_iter().collect();
+
+    let mut expected: Vec<(String, Vec<usize>)> = model
+        .named_parameters()
+        .into_iter()
+        .map(|(n, p)| (n, p.dimensions().to_vec()))
+        .collect();
+    expected.extend(
+        model
+            .named_buffers()
+            .into_iter()
+            .map(|(n, b)| (n, b.dimensions().to_vec())),
+    );
+
+    for (name, dimensions) in &expected {
+        match tensors.get(name) {
+            None => return Err(invalid_data(format!("the tensor {} is missing", name))),
+            Some(tensor) if tensor.dimensions() != dimensions.as_slice() => {
+                return Err(invalid_data(format!(
+                    "the dimensions {:?} of tensor {} do not match the model dimensions {:?}",
+                    tensor.dimensions(),
+                    name,
+                    dimensions
+                )))
+            }
+            Some(_) => (),
+        }
+    }
+
+    if tensors.len() != expected.len() {
+        let mut unexpected: Vec<&String> = tensors
+            .keys()
+            .filter(|n| !expected.iter().any(|(e, _)| e == *n))
+            .collect();
+        unexpected.sort();
+        return Err(invalid_data(format!(
+            "the tensors {:?} do not belong to the model",
+            unexpected
+        )));
+    }
+
+    for (name, parameter) in model.named_parameters() {
+        *parameter = tensors.remove(&name).unwrap().tracked();
+    }
+    for (name, buffer) in model.named_buffers() {
+        *buffer = tensors.remove(&name).unwrap();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::batch_norm::BatchNorm;
+    use crate::layer::container::Residual;
+    use crate::layer::dense::Dense;
+    use crate::model::ModelBuilder;
+    use crate::optimizer::gd::GradientDescent;
+    use crate::{activation, cost};
+
+    fn model() -> Model {
+        ModelBuilder::new(vec![1, 4, 4])
+            .conv(2, (3, 3), (1, 1), Some(activation::relu()))
+            .flatten()
+            .dense(3, None)
+            .layer(Box::new(BatchNorm::new(3, 0.9, 1e-5)))
+            .layer(Box::new(Residual::new(Box::new(Dense::new(
+                3,
+                3,
+                &crate::initializer::he(),
+                None,
+            )))))
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build()
+    }
+
+    #[test]
+    fn test_names() {
+        let mut model = model();
+        let names: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "layers.0.filters",
+                "layers.0.biases",
+                "layers.2.weights",
+                "layers.2.biases",
+                "layers.3.gamma",
+                "layers.3.beta",
+                "layers.4.inner.weights",
+                "layers.4.inner.biases",
+            ]
+        );
+
+        let names: Vec<String> = model.named_buffers().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(
+            names,
+            vec!["layers.3.running_mean", "layers.3.running_variance"]
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("corgi_{}.safetensors", std::process::id()));
+        let input = Array::from((
+            vec![2, 1, 4, 4],
+            (0..32).map(|x| x as Float / 8.0).collect::<Vec<Float>>(),
+        ));
+
+        let mut saved = model();
+        saved.set_training(false);
+        let output = saved.forward(input.clone());
+        save(&mut saved, &path).unwrap();
+
+        let mut loaded = model();
+        load(&mut loaded, &path).unwrap();
+        loaded.set_training(false);
+        assert_eq!(loaded.forward(input), output);
+
+        // the tensors do not belong to a model of a different architecture
+        let mut other = ModelBuilder::new(vec![1, 4, 4])
+            .conv(2, (3, 3), (1, 1), None)
+            .optimizer(Box::new(GradientDescent::new(0.1)))
+            .cost(cost::mse(cost::Reduction::Mean))
+            .build();
+        assert!(load(&mut other, &path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read() {
+        // a header written by another framework, with metadata, escapes, and reduced precision dtypes
+        let header = r#"{"__metadata__":{"format":"pt"},"bé":{"dtype":"BF16","shape":[2],"data_offsets":[8,12]},
+            "a":{"dtype":"F16","shape":[1,2],"data_offsets":[0,4]},"c":{"dtype":"F32","shape":[],"data_offsets":[4,8]}}"#;
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&[0x00, 0x3c, 0x00, 0xc1]);
+        bytes.extend_from_slice(&0.75f32.to_le_bytes());
+        bytes.extend_from_slice(&[0x80, 0x3f, 0x40, 0xc0]);
+
+        let tensors = read_tensors(&bytes).unwrap();
+        assert_eq!(
+            tensors,
+            vec![
+                ("bé".to_string(), arr![1.0, -3.0]),
+                ("a".to_string(), arr![arr![1.0, -2.5]]),
+                ("c".to_string(), Array::from((vec![], vec![0.75]))),
+            ]
+        );
+
+        let header = r#"{"a":{"dtype":"I64","shape":[1],"data_offsets":[0,8]}}"#;
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        assert!(read_tensors(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_read_sizes() {
+        // tensors with no values are valid, but are skipped
+        let header = r#"{"a":{"dtype":"F32","shape":[0,3],"data_offsets":[0,0]},"b":{"dtype":"F32","shape":[1],"data_offsets":[0,4]}}"#;
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        assert_eq!(
+            read_tensors(&bytes).unwrap(),
+            vec![("b".to_string(), arr![0.5])]
+        );
+
+        // the shape, and header length overflow
+        let header =
+            r#"{"a":{"dtype":"F32","shape":[4294967296,4294967296],"data_offsets":[0,0]}}"#;
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        assert!(read_tensors(&bytes).is_err());
+
+        let mut bytes = u64::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"{}");
+        assert!(read_tensors(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let (a, b) = (arr![arr![1.0, 2.0]], arr![-3.0]);
+        let mut bytes = Vec::new();
+        write_tensors(&mut bytes, &[("weights", &a), ("quote\"", &b)]).unwrap();
+
+        // the header is padded, so that the data is aligned
+        let data_length = 3 * std::mem::size_of::<Float>();
+        assert_eq!((bytes.len() - data_length) % HEADER_ALIGNMENT, 0);
+        assert_eq!(
+            read_tensors(&bytes).unwrap(),
+            vec![("weights".to_string(), a), ("quote\"".to_string(), b)]
+        );
+    }
+}
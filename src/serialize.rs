@@ -0,0 +1,214 @@
+//! Serialization of arrays in a versioned binary format, which is used to save, and load models.
+//!
+//! All integers, and floating point values are little-endian. The format consists of:
+//!
+//! * the magic bytes `CRGI`
+//! * the format version, as a `u32`
+//! * the floating point width of the values in bytes, as a `u8`, which is 4 for `f32`, and 8 for `f64`
+//! * the number of arrays, as a `u64`
+//! * for each array, the number of dimensions as a `u64`, each dimension as a `u64`, and then the values
+//!
+//! Values written with either floating point width may be read by both `f32`, and `f64` builds.
+
+use crate::array::*;
+use crate::numbers::*;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+/// The bytes at the start of every serialized file.
+const MAGIC: &[u8; 4] = b"CRGI";
+
+/// The version of the format, which is incremented when the format changes.
+pub const VERSION: u32 = 1;
+
+/// Constructs an error for malformed, or mismatched data.
+pub(crate) fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("error: {}", message))
+}
+
+pub(crate) fn write_u64(writer: &mut impl Write, value: usize) -> Result<()> {
+    writer.write_all(&(value as u64).to_le_bytes())
+}
+
+pub(crate) fn read_u64(reader: &mut impl Read) -> Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes) as usize)
+}
+
+/// Reads a single value of a given floating point width in bytes.
+fn read_float(reader: &mut impl Read, width: u8) -> Result<Float> {
+    if width == 4 {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(f32::from_le_bytes(bytes) as Float)
+    } else {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes) as Float)
+    }
+}
+
+/// Writes the dimensions, and values of arrays.
+pub fn write_arrays(writer: &mut impl Write, arrays: &[&Array]) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&[std::mem::size_of::<Float>() as u8])?;
+
+    write_u64(writer, arrays.len())?;
+    for array in arrays {
+        write_u64(writer, array.dimensions().len())?;
+        for &dimension in array.dimensions() {
+            write_u64(writer, dimension)?;
+        }
+
+        for value in array.values() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the dimensions, and values of arrays, which are untracked.
+pub fn read_arrays(reader: &mut impl Read) -> Result<Vec<Array>> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data(
+            "the data is not a serialized array file".to_string(),
+        ));
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "the format version {} is not supported, expected {}",
+            version, VERSION
+        )));
+    }
+
+    let mut width = [0; 1];
+    reader.read_exact(&mut width)?;
+    let width = width[0];
+    if width != 4 && width != 8 {
+        return Err(invalid_data(format!(
+            "the floating point width {} must be 4, or 8 bytes",
+            width
+        )));
+    }
+
+    let count = read_u64(reader)?;
+    let mut arrays = Vec::new();
+    for _ in 0..count {
+        let dimension_count = read_u64(reader)?;
+        let dimensions = (0..dimension_count)
+            .map(|_| read_u64(reader))
+            .collect::<Result<Vec<usize>>>()?;
+
+        if dimensions.contains(&0) {
+            return Err(invalid_data(format!(
+                "the dimensions {:?} must be positive",
+                dimensions
+            )));
+        }
+        let length = dimensions
+            .iter()
+            .try_fold(1usize, |length, &d| length.checked_mul(d))
+            .ok_or_else(|| {
+                invalid_data(format!("the dimensions {:?} are too large", dimensions))
+            })?;
+        let values = (0..length)
+            .map(|_| read_float(reader, width))
+            .collect::<Result<Vec<Float>>>()?;
+        arrays.push(Array::from((dimensions, values)));
+    }
+
+    Ok(arrays)
+}
+
+/// Saves arrays to a file.
+pub fn save(path: impl AsRef<Path>, arrays: &[&Array]) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_arrays(&mut writer, arrays)?;
+    writer.flush()
+}
+
+/// Loads arrays from a file.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Array>> {
+    read_arrays(&mut BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let arrays = vec![arr![arr![1.0, 2.0], arr![3.0, 4.5]], arr![-1.0]];
+
+        let mut bytes = Vec::new();
+        write_arrays(&mut bytes, &arrays.iter().collect::<Vec<&Array>>()).unwrap();
+        assert_eq!(read_arrays(&mut bytes.as_slice()).unwrap(), arrays);
+    }
+
+    #[test]
+    fn test_width() {
+        // values written by an f32 build are read by either build
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(4);
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-2.0f32).to_le_bytes());
+
+        assert_eq!(
+            read_arrays(&mut bytes.as_slice()).unwrap(),
+            vec![arr![1.5, -2.0]]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut bytes = Vec::new();
+        write_arrays(&mut bytes, &[&arr![1.0]]).unwrap();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(read_arrays(&mut wrong_magic.as_slice()).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert!(read_arrays(&mut wrong_version.as_slice()).is_err());
+
+        bytes.pop();
+        assert!(read_arrays(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_dimensions() {
+        let header = |dimensions: &[u64]| {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend_from_slice(&VERSION.to_le_bytes());
+            bytes.push(8);
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes.extend_from_slice(&(dimensions.len() as u64).to_le_bytes());
+            for dimension in dimensions {
+                bytes.extend_from_slice(&dimension.to_le_bytes());
+            }
+            bytes.extend_from_slice(&1.0f64.to_le_bytes());
+            bytes
+        };
+
+        for dimensions in &[vec![0], vec![2, 0], vec![1 << 32, 1 << 32]] {
+            let error = read_arrays(&mut header(dimensions).as_slice()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
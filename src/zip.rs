@@ -0,0 +1,528 @@
+//! A minimal reader, and writer of zip archives, which are the containers of NumPy `.npz` files.
+//!
+//! Entries are written without compression, and are read if they are stored, or compressed with deflate.
+
+use crate::serialize::invalid_data;
+
+use std::io::{Result, Write};
+
+/// The signature of a local file header.
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+/// The signature of a central directory file header.
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// The signature of the end of central directory record.
+const END_SIGNATURE: u32 = 0x0605_4b50;
+/// The signature of the zip64 end of central directory locator.
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+/// The value of a field, whose actual value is stored in the zip64 extra field.
+const ZIP64_MARKER: u32 = 0xffff_ffff;
+
+/// The compression method of stored entries.
+const STORED: u16 = 0;
+/// The compression method of entries compressed with deflate.
+const DEFLATED: u16 = 8;
+
+/// Computes the CRC-32 checksum of bytes, using the reflected polynomial 0xEDB88320.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16> {
+    slice_at(bytes, offset, 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32> {
+    slice_at(bytes, offset, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64> {
+    Ok(u32_at(bytes, offset)? as u64 | (u32_at(bytes, offset + 4)? as u64) << 32)
+}
+
+/// Retrieves the bytes at an offset, which may be any value read from the archive.
+fn slice_at(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8]> {
+    offset
+        .checked_add(length)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| invalid_data("the zip archive is truncated".to_string()))
+}
+
+/// Converts a value to a 16-bit field, which must be less than the zip64 marker, as zip64 records are not written.
+fn u16_field(value: usize, field: &str) -> Result<u16> {
+    if value < 0xffff {
+        Ok(value as u16)
+    } else {
+        Err(invalid_data(format!(
+            "the {} {} is too large for a zip archive",
+            field, value
+        )))
+    }
+}
+
+/// Converts a value to a 32-bit field, which must be less than the zip64 marker, as zip64 records are not written.
+fn u32_field(value: usize, field: &str) -> Result<u32> {
+    if value < ZIP64_MARKER as usize {
+        Ok(value as u32)
+    } else {
+        Err(invalid_data(format!(
+            "the {} {} is too large for a zip archive",
+            field, value
+        )))
+    }
+}
+
+/// Writes stored entries of names, and contents as a zip archive.
+pub(crate) fn write_archive(writer: &mut impl Write, entries: &[(String, Vec<u8>)]) -> Result<()> {
+    let count = u16_field(entries.len(), "entry count")?;
+    let mut central = Vec::new();
+    let mut offset = 0;
+    for (name, contents) in entries {
+        let crc = crc32(contents);
+        let size = u32_field(contents.len(), "entry size")?;
+        let entry_offset = u32_field(offset, "entry offset")?;
+
+        // the version needed to extract, flags, method, time, and date are followed by the checksum, and sizes
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&STORED.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0x21u16.to_le_bytes());
+        fields.extend_from_slice(&crc.to_le_bytes());
+        fields.extend_from_slice(&size.to_le_bytes());
+        fields.extend_from_slice(&size.to_le_bytes());
+        fields.extend_from_slice(&u16_field(name.len(), "name length")?.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+
+        writer.write_all(&LOCAL_SIGNATURE.to_le_bytes())?;
+        writer.write_all(&fields)?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(contents)?;
+
+        // the central header also stores the version made by, comment length, disk, attributes, and offset
+        central.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&fields);
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&entry_offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        offset += 4 + fields.len() + name.len() + contents.len();
+    }
+
+    let central_size = u32_field(central.len(), "central directory size")?;
+    let central_offset = u32_field(offset, "central directory offset")?;
+    writer.write_all(&central)?;
+    writer.write_all(&END_SIGNATURE.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&count.to_le_bytes())?;
+    writer.write_all(&count.to_le_bytes())?;
+    writer.write_all(&central_size.to_le_bytes())?;
+    writer.write_all(&central_offset.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())
+}
+
+/// Reads the names, and contents of the entries of a zip archive.
+pub(crate) fn read_archive(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    // the end of central directory record is followed by a comment of at most 65535 bytes
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(65536)
+        .find(|&i| u32_at(bytes, i).ok() == Some(END_SIGNATURE))
+        .ok_or_else(|| invalid_data("the data is not a zip archive".to_string()))?;
+
+    let mut count = u16_at(bytes, end + 10)? as usize;
+    let mut offset = u32_at(bytes, end + 16)? as usize;
+    if offset == ZIP64_MARKER as usize
+        && end >= 20
+        && u32_at(bytes, end - 20)? == ZIP64_LOCATOR_SIGNATURE
+    {
+        let zip64_end = slice_at(bytes, u64_at(bytes, end - 12)? as usize, 56)?;
+        count = u64_at(zip64_end, 32)? as usize;
+        offset = u64_at(zip64_end, 48)? as usize;
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        // the offsets within the record are small, as the record itself is within the archive
+        let record = slice_at(bytes, offset, 46)?;
+        if u32_at(record, 0)? != CENTRAL_SIGNATURE {
+            return Err(invalid_data(
+                "the zip central directory is malformed".to_string(),
+            ));
+        }
+
+        let method = u16_at(record, 10)?;
+        let crc = u32_at(record, 16)?;
+        let mut compressed_size = u32_at(record, 20)? as u64;
+        let mut size = u32_at(record, 24)? as u64;
+        let name_length = u16_at(record, 28)? as usize;
+        let extra_length = u16_at(record, 30)? as usize;
+        let comment_length = u16_at(record, 32)? as usize;
+        let mut local_offset = u32_at(record, 42)? as u64;
+        let name = String::from_utf8_lossy(slice_at(bytes, offset + 46, name_length)?).into_owned();
+
+        // the zip64 extra field stores only the fields which are marked
+        let extra = slice_at(bytes, offset + 46 + name_length, extra_length)?;
+        let mut i = 0;
+        while i + 4 <= extra.len() {
+            let (id, length) = (u16_at(extra, i)?, u16_at(extra, i + 2)? as usize);
+            if id == 1 {
+                let mut field = i + 4;
+                for value in [&mut size, &mut compressed_size, &mut local_offset] {
+                    if *value == ZIP64_MARKER as u64 {
+                        *value = u64_at(extra, field)?;
+                        field += 8;
+                    }
+                }
+            }
+            i += 4 + length;
+        }
+
+        let local_offset = local_offset as usize;
+        let local = slice_at(bytes, local_offset, 30)?;
+        if u32_at(local, 0)? != LOCAL_SIGNATURE {
+            return Err(invalid_data(format!("the zip entry {} is malformed", name)));
+        }
+        let data_offset =
+            local_offset + 30 + u16_at(local, 26)? as usize + u16_at(local, 28)? as usize;
+        let data = slice_at(bytes, data_offset, compressed_size as usize)?;
+
+        let contents = match method {
+            STORED => data.to_vec(),
+            DEFLATED => inflate(data)?,
+            _ => {
+                return Err(invalid_data(format!(
+                    "the compression method {} of the zip entry {} is not supported",
+                    method, name
+                )))
+            }
+        };
+        if contents.len() as u64 != size || crc32(&contents) != crc {
+            return Err(invalid_data(format!("the zip entry {} is corrupt", name)));
+        }
+
+        entries.push((name, contents));
+        offset += 46 + name_length + extra_length + comment_length;
+    }
+
+    Ok(entries)
+}
+
+/// The base lengths of the length symbols 257 to 285.
+const LENGTH_BASES: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// The number of extra bits of the length symbols 257 to 285.
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distances of the distance symbols.
+const DISTANCE_BASES: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// The number of extra bits of the distance symbols.
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which the code lengths of the code length alphabet are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// A canonical Huffman code, storing the number of codes of each length, and the symbols ordered by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<(u8, u16)> = lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, &l)| l > 0)
+            .map(|(s, &l)| (l, s as u16))
+            .collect();
+        symbols.sort_unstable();
+
+        Huffman {
+            counts,
+            symbols: symbols.into_iter().map(|(_, s)| s).collect(),
+        }
+    }
+}
+
+/// A reader of the bits of a deflate stream, from the least significant bit of each byte.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, count: u32) -> Result<usize> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .bytes
+                .get(self.position / 8)
+                .ok_or_else(|| invalid_data("the deflate stream is truncated".to_string()))?;
+            value |= ((byte >> (self.position % 8)) as usize & 1) << i;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Skips the remaining bits of the current byte, so that the next read begins at a byte boundary.
+    fn align(&mut self) {
+        let remainder = self.position % 8;
+        if remainder > 0 {
+            self.position += 8 - remainder;
+        }
+    }
+
+    /// Decodes a symbol, by reading bits until the code is within the codes of the current length.
+    fn decode(&mut self, huffman: &Huffman) -> Result<usize> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &huffman.counts[1..] {
+            code |= self.bits(1)?;
+            let count = count as usize;
+            if code < first + count {
+                return Ok(huffman.symbols[index + code - first] as usize);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid_data(
+            "the deflate stream has an invalid code".to_string(),
+        ))
+    }
+}
+
+/// Decompresses a raw deflate stream.
+pub(crate) fn inflate(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = BitReader { bytes, position: 0 };
+    let mut output = Vec::new();
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                // stored blocks begin at a byte boundary, with the length, and its complement
+                reader.align();
+                let start = reader.position / 8;
+                let length = u16_at(bytes, start)? as usize;
+                if length != !u16_at(bytes, start + 2)? as usize {
+                    return Err(invalid_data(
+                        "the deflate stored block length is corrupt".to_string(),
+                    ));
+                }
+
+                output.extend_from_slice(slice_at(bytes, start + 4, length)?);
+                reader.position = (start + 4 + length) * 8;
+            }
+            1 => {
+                let lengths: Vec<u8> = (0..288)
+                    .map(|s| match s {
+                        0..=143 => 8,
+                        144..=255 => 9,
+                        256..=279 => 7,
+                        _ => 8,
+                    })
+                    .collect();
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => {
+                return Err(invalid_data(
+                    "the deflate block type is invalid".to_string(),
+                ))
+            }
+        }
+
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+/// Reads the literal, and distance codes of a block compressed with dynamic Huffman codes.
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? + 257;
+    let distance_count = reader.bits(5)? + 1;
+    let code_length_count = reader.bits(4)? + 4;
+
+    let mut code_lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[0..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::new();
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match reader.decode(&code_length_code)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or_else(|| {
+                    invalid_data("the deflate code lengths are invalid".to_string())
+                })?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.resize(lengths.len() + repeat, length);
+    }
+
+    if lengths.len() != literal_count + distance_count {
+        return Err(invalid_data(
+            "the deflate code lengths are invalid".to_string(),
+        ));
+    }
+
+    Ok((
+        Huffman::new(&lengths[0..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+/// Decodes the literals, and back references of a compressed block, until the end of block symbol.
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<()> {
+    loop {
+        let symbol = reader.decode(literals)?;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASES[index] + reader.bits(LENGTH_EXTRA_BITS[index])?;
+
+                let index = reader.decode(distances)?;
+                if index >= DISTANCE_BASES.len() {
+                    return Err(invalid_data("the deflate distance is invalid".to_string()));
+                }
+                let distance = DISTANCE_BASES[index] + reader.bits(DISTANCE_EXTRA_BITS[index])?;
+                if distance > output.len() {
+                    return Err(invalid_data(
+                        "the deflate distance is too far back".to_string(),
+                    ));
+                }
+
+                // the copied bytes may overlap the bytes being written
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => {
+                return Err(invalid_data(
+                    "the deflate length symbol is invalid".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_inflate() {
+        // stored, fixed, and dynamic Huffman blocks, compressed by zlib
+        assert_eq!(inflate(&[1, 3, 0, 252, 255, 97, 98, 99]).unwrap(), b"abc");
+        assert_eq!(
+            inflate(&[203, 72, 205, 201, 201, 87, 200, 64, 39, 1]).unwrap(),
+            b"hello hello hello hello"
+        );
+
+        let dynamic = [
+            5, 193, 109, 10, 128, 32, 12, 0, 208, 43, 57, 152, 210, 52, 246, 129, 34, 141, 36, 176,
+            126, 236, 254, 55, 233, 189, 136, 72, 41, 1, 28, 68, 100, 182, 28, 49, 231, 92, 74, 97,
+            102, 153, 243, 125, 168, 214, 218, 90, 59, 123, 31, 227, 94, 241, 137, 136, 170, 154,
+            93, 238, 190, 55, 194, 248, 1,
+        ];
+        assert_eq!(
+            inflate(&dynamic).unwrap(),
+            b"WWW000118999AALI44555666>>>?FFRN9:::;;;<CCDDKLWS???@@@AAHIIIQQ41D".to_vec()
+        );
+
+        assert!(inflate(&[203, 72, 205]).is_err());
+    }
+
+    #[test]
+    fn test_archive() {
+        let entries = vec![
+            ("a.npy".to_string(), b"first".to_vec()),
+            ("b.npy".to_string(), Vec::new()),
+        ];
+
+        let mut bytes = Vec::new();
+        write_archive(&mut bytes, &entries).unwrap();
+        assert_eq!(read_archive(&bytes).unwrap(), entries);
+
+        // a corrupted entry fails its checksum
+        bytes[30 + 5] = b'F';
+        assert!(read_archive(&bytes).is_err());
+        assert!(read_archive(b"not an archive").is_err());
+    }
+
+    #[test]
+    fn test_archive_too_large() {
+        // the fields of archives over 4 GiB are checked without writing them
+        assert!(u32_field(1 << 32, "entry size").is_err());
+        assert!(u32_field(ZIP64_MARKER as usize, "entry offset").is_err());
+        assert_eq!(u32_field(1 << 31, "entry size").unwrap(), 1 << 31);
+
+        let entries = vec![("a.npy".to_string(), Vec::new()); 0xffff];
+        let error = write_archive(&mut Vec::new(), &entries).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let entries = vec![("a".repeat(0x10000), Vec::new())];
+        assert!(write_archive(&mut Vec::new(), &entries).is_err());
+    }
+
+    #[test]
+    fn test_offset_overflow() {
+        assert!(slice_at(b"abc", usize::MAX, 2).is_err());
+        assert!(u32_at(b"abc", usize::MAX - 1).is_err());
+        assert_eq!(slice_at(b"abc", 1, 2).unwrap(), b"bc");
+    }
+}